@@ -0,0 +1,49 @@
+//! Compares the sliding move backends. Run with `cargo bench --features pext` to include PEXT.
+
+use std::{hint::black_box, time::Instant};
+
+use chess::{
+    board::{mask::Mask, square::Square},
+    move_gen::SlidingMoves,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const LOOKUPS: usize = 20_000_000;
+
+fn bench(name: &str, moves: &SlidingMoves, positions: &[(Square, Mask)]) {
+    let started = Instant::now();
+    let mut total = Mask(0);
+
+    for &(square, blockers) in positions.iter().cycle().take(LOOKUPS) {
+        total ^= moves.get_rook_moves(black_box(square), black_box(blockers));
+        total ^= moves.get_bishop_moves(black_box(square), black_box(blockers));
+    }
+    black_box(total);
+
+    let elapsed = started.elapsed();
+    println!(
+        "{name}: {} lookups in {elapsed:.2?} ({:.2} ns each)",
+        LOOKUPS * 2,
+        elapsed.as_nanos() as f64 / (LOOKUPS * 2) as f64
+    );
+}
+
+fn main() {
+    // Sparse random blockers, like a board in the middlegame
+    let mut rng = StdRng::seed_from_u64(0);
+    let positions = (0..4096)
+        .map(|_| {
+            let square = Square::from_usize(rng.gen_range(0..64)).unwrap();
+            let blockers = Mask(rng.gen::<u64>() & rng.gen::<u64>());
+            (square, blockers)
+        })
+        .collect::<Vec<_>>();
+
+    bench("Magic", &SlidingMoves::with_magics(), &positions);
+
+    #[cfg(feature = "pext")]
+    match SlidingMoves::with_pext() {
+        Some(moves) => bench("PEXT", &moves, &positions),
+        None => println!("PEXT: not supported by this CPU"),
+    }
+}
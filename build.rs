@@ -0,0 +1,19 @@
+use std::{env, fs, path::PathBuf};
+
+#[allow(dead_code)]
+#[path = "src/move_gen/generate.rs"]
+mod generate;
+
+fn main() -> std::io::Result<()> {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/move_gen/generate.rs");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("move_masks.rs"), generate::move_masks_source())?;
+    fs::write(
+        out_dir.join("magics.rs"),
+        generate::magics_source(generate::SEED),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,321 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use chess::{
+    board::{piece::Color, Board, START_FEN},
+    eval::Score,
+    search::{
+        self,
+        tt::{self, TranspositionTable},
+        SearchConfig, SearchControl, SearchLimits, MATE_THRESHOLD,
+    },
+};
+use rand::{thread_rng, Rng};
+
+/// Number of random moves played at the start of each game so that games don't repeat
+const RANDOM_OPENING_PLIES: usize = 8;
+/// Games are abandoned as draws after this many plies
+const MAX_GAME_PLIES: usize = 400;
+/// Positions with scores beyond this are already decided and aren't recorded
+const MAX_RECORDED_SCORE: Score = 2000;
+
+/// Settings shared by every worker thread.
+struct Config {
+    games: u64,
+    nodes_per_move: u64,
+}
+
+/// Output shared by every worker thread. Each finished game is appended to the output file in
+/// one go and the number of finished games is written to the progress file, so that generation
+/// can be stopped at any time and resumed later.
+struct Output {
+    file: Mutex<File>,
+    progress_path: String,
+    games_started: AtomicU64,
+    games_finished: AtomicU64,
+}
+
+/// The result of a finished game from white's point of view, or `None` if it goes on. Games
+/// are drawn by the usual rules or once they reach `MAX_GAME_PLIES`.
+fn adjudicate(board: &Board, plies: usize) -> Option<f64> {
+    if board.is_draw() || plies >= MAX_GAME_PLIES {
+        return Some(0.5);
+    }
+    if !board.legal_moves().is_empty() {
+        return None;
+    }
+
+    // Checkmate or stalemate
+    if !board.in_check() {
+        return Some(0.5);
+    }
+    match board.current_position().active_color() {
+        Color::White => Some(0.0),
+        Color::Black => Some(1.0),
+    }
+}
+
+/// One line of output: a position, its score for white and the game's result for white.
+fn format_position(fen: &str, white_score: Score, result: f64) -> String {
+    format!("{fen} | {white_score} | {result:.1}\n")
+}
+
+/// Plays a single self-play game, returning the recorded positions as `(FEN, white score)`
+/// pairs along with the result from white's point of view, or `None` if the random opening
+/// ended the game early. `tt` is cleared first, so that games don't depend on each other.
+fn play_game(
+    board: &mut Board,
+    tt: &TranspositionTable,
+    nodes_per_move: u64,
+) -> Option<(Vec<(String, Score)>, f64)> {
+    let mut rng = thread_rng();
+
+    board.load_from_fen(START_FEN).unwrap();
+    tt.clear();
+
+    for _ in 0..RANDOM_OPENING_PLIES {
+        let moves = board.legal_moves();
+        if moves.is_empty() {
+            return None;
+        }
+
+        let mv = moves[rng.gen_range(0..moves.len())];
+        board.make_packed_move(mv).unwrap();
+    }
+
+    let limits = SearchLimits {
+        nodes: Some(nodes_per_move),
+        ..Default::default()
+    };
+    let config = SearchConfig::default();
+
+    let mut positions = Vec::new();
+
+    for plies in 0.. {
+        if let Some(result) = adjudicate(board, plies) {
+            return Some((positions, result));
+        }
+
+        let result = search::search_with_report(
+            board,
+            limits,
+            &config,
+            tt,
+            &SearchControl::default(),
+            |_| {},
+        );
+        let best_move = result.best_move?;
+
+        let white_score = match board.current_position().active_color() {
+            Color::White => result.score,
+            Color::Black => -result.score,
+        };
+
+        // Only quiet positions are useful for training
+        let quiet = !board.in_check() && !best_move.flag().is_capture();
+        if quiet && white_score.abs() < MAX_RECORDED_SCORE && result.score.abs() < MATE_THRESHOLD {
+            positions.push((board.current_position().to_fen(), white_score));
+        }
+
+        board.make_packed_move(best_move).unwrap();
+    }
+
+    unreachable!("games are adjudicated after MAX_GAME_PLIES")
+}
+
+/// Writes the number of finished games to the progress file. The count is written to a temporary
+/// file first and then renamed over the progress file, so that an interrupted write can't leave
+/// a truncated count behind.
+fn write_progress(path: &str, finished: u64) -> io::Result<()> {
+    let tmp_path = format!("{path}.tmp");
+    fs::write(&tmp_path, finished.to_string())?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Reads the number of finished games from the progress file, returning 0 if it doesn't exist.
+fn read_progress(path: &str) -> io::Result<u64> {
+    match fs::read_to_string(path) {
+        Ok(s) => s.trim().parse().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid progress file {path}: {e}"),
+            )
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+fn worker(config: &Config, output: &Output) -> io::Result<()> {
+    let mut board = Board::new(START_FEN).unwrap();
+    let tt = TranspositionTable::new(tt::DEFAULT_SIZE_MB);
+
+    while output.games_started.fetch_add(1, Ordering::SeqCst) < config.games {
+        let (positions, result) = loop {
+            if let Some(game) = play_game(&mut board, &tt, config.nodes_per_move) {
+                break game;
+            }
+        };
+
+        let mut buf = String::new();
+        for (fen, score) in positions {
+            buf.push_str(&format_position(&fen, score, result));
+        }
+
+        // The progress file is updated while the output lock is held, so that it always matches
+        // the number of games in the output file
+        let finished = {
+            let mut file = output.file.lock().unwrap();
+            file.write_all(buf.as_bytes())?;
+            file.flush()?;
+
+            let finished = output.games_finished.fetch_add(1, Ordering::SeqCst) + 1;
+            write_progress(&output.progress_path, finished)?;
+            finished
+        };
+
+        println!("Finished game {finished}/{}", config.games);
+    }
+
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1);
+    let Some(output_path) = args.next() else {
+        eprintln!("Usage: datagen <output file> [games] [threads] [nodes per move]");
+        process::exit(1);
+    };
+
+    let games = args.next().and_then(|n| n.parse().ok()).unwrap_or(1000);
+    let threads = args
+        .next()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+    let nodes_per_move = args.next().and_then(|n| n.parse().ok()).unwrap_or(5000);
+
+    // Resume from where a previous run left off
+    let progress_path = format!("{output_path}.progress");
+    let finished = read_progress(&progress_path)?;
+
+    if finished >= games {
+        println!("All {games} games have already been generated");
+        return Ok(());
+    }
+    if finished > 0 {
+        println!("Resuming after {finished} games");
+    }
+
+    let config = Config {
+        games,
+        nodes_per_move,
+    };
+    let output = Output {
+        file: Mutex::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&output_path)?,
+        ),
+        progress_path,
+        games_started: AtomicU64::new(finished),
+        games_finished: AtomicU64::new(finished),
+    };
+
+    thread::scope(|scope| {
+        let handles = (0..threads)
+            .map(|_| scope.spawn(|| worker(&config, &output)))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .try_for_each(|handle| handle.join().unwrap())
+    })
+}
+
+#[cfg(test)]
+mod datagen_tests {
+    use super::*;
+
+    fn adjudicate_fen(fen: &str, plies: usize) -> Option<f64> {
+        adjudicate(&Board::new(fen).unwrap(), plies)
+    }
+
+    #[test]
+    fn adjudicates_finished_games() {
+        // Checkmate, either side to move
+        assert_eq!(
+            adjudicate_fen("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1", 10),
+            Some(1.0)
+        );
+        assert_eq!(
+            adjudicate_fen("8/8/8/8/8/1k6/1q6/K7 w - - 0 1", 10),
+            Some(0.0)
+        );
+        // Stalemate, insufficient material and the fifty move rule
+        assert_eq!(
+            adjudicate_fen("k7/8/1Q6/8/8/8/8/6K1 b - - 0 1", 10),
+            Some(0.5)
+        );
+        assert_eq!(
+            adjudicate_fen("8/8/4k3/8/8/4K3/4N3/8 w - - 0 1", 10),
+            Some(0.5)
+        );
+        assert_eq!(
+            adjudicate_fen("8/8/4k3/8/8/4K3/4R3/8 w - - 100 80", 10),
+            Some(0.5)
+        );
+
+        // Games that go on, unless they're too long
+        assert_eq!(adjudicate_fen(START_FEN, 10), None);
+        assert_eq!(adjudicate_fen(START_FEN, MAX_GAME_PLIES), Some(0.5));
+    }
+
+    #[test]
+    fn adjudicates_repetitions() {
+        let mut board = Board::new(START_FEN).unwrap();
+        for mv in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            let mv = board
+                .legal_moves()
+                .into_iter()
+                .find(|legal| legal.to_string() == mv)
+                .unwrap();
+            board.make_packed_move(mv).unwrap();
+        }
+
+        assert_eq!(adjudicate(&board, 4), Some(0.5));
+    }
+
+    #[test]
+    fn formats_output_lines() {
+        assert_eq!(
+            format_position(START_FEN, -35, 1.0),
+            format!("{START_FEN} | -35 | 1.0\n")
+        );
+        assert_eq!(
+            format_position("8/8/4k3/8/8/4K3/4R3/8 w - - 0 1", 412, 0.5),
+            "8/8/4k3/8/8/4K3/4R3/8 w - - 0 1 | 412 | 0.5\n"
+        );
+    }
+
+    #[test]
+    fn plays_games_to_a_result() {
+        let mut board = Board::new(START_FEN).unwrap();
+        let tt = TranspositionTable::new(1);
+
+        let (positions, result) = play_game(&mut board, &tt, 200).unwrap();
+        assert!([0.0, 0.5, 1.0].contains(&result));
+        assert!(positions
+            .iter()
+            .all(|(_, score)| score.abs() < MAX_RECORDED_SCORE));
+    }
+}
@@ -1,74 +1,69 @@
-use std::fs;
+//! Runs the magic search that `build.rs` does and reports how long it takes and how big the
+//! tables are. Other seeds, and searching for magics with fewer index bits, can be tried out
+//! before changing `generate.rs`.
+
+use std::{env, process, time::Instant};
 
 use chess::{
-    board::{mask::Mask, square::Square},
-    move_gen::{direction::Direction, try_fill_magic_table, MagicEntry},
+    board::mask::Mask,
+    move_gen::generate::{self, Magic, Rng},
 };
 
-use rand::{thread_rng, Rng};
-
-fn random_u64() -> u64 {
-    thread_rng().gen()
-}
-
-fn create_magics(direction: Direction, index_bits: u8) -> (Vec<MagicEntry>, Vec<Vec<Mask>>) {
-    let mut magics: Vec<MagicEntry> = Vec::with_capacity(64);
-    let mut masks: Vec<Vec<Mask>> = Vec::with_capacity(64);
-
-    for i in 0..64 {
-        let square = Square::from_usize(i).unwrap();
-
-        let (new_magics, new_masks) = generate_magic(direction, square, index_bits);
-        magics.push(new_magics);
-        masks.push(new_masks);
-    }
-
-    (magics, masks)
+fn print_statistics(name: &str, magics: &[Magic], fixed_bits: u8, started: Instant) {
+    let entries = generate::table_size(magics);
+    let fixed_entries = 64 << fixed_bits;
+    let shrunk = magics
+        .iter()
+        .filter(|magic| magic.index_bits < magic.mask.count_ones() as u8)
+        .count();
+
+    println!(
+        "{name}: {entries} entries ({} KiB, down from {} KiB with {fixed_bits} bits per square), \
+         {shrunk} squares below the blocker count, found in {:.2?}",
+        entries * size_of::<Mask>() / 1024,
+        fixed_entries * size_of::<Mask>() / 1024,
+        started.elapsed(),
+    );
 }
 
-fn generate_magic(direction: Direction, square: Square, index_bits: u8) -> (MagicEntry, Vec<Mask>) {
-    let mask = direction.all_blocker_subsets()[square as usize][0];
-
-    loop {
-        let magic = random_u64() & random_u64() & random_u64();
-        let new_entry = MagicEntry {
-            mask,
-            magic,
-            index_bits,
-        };
+fn parse_args() -> Option<(u64, Option<u64>)> {
+    let mut seed = generate::SEED;
+    let mut shrink_attempts = None;
 
-        if let Ok(table) = try_fill_magic_table(direction, &new_entry, square) {
-            return (new_entry, table);
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = args.next()?.parse().ok()?,
+            "--shrink" => shrink_attempts = Some(args.next()?.parse().ok()?),
+            _ => return None,
         }
     }
-}
-
-fn main() -> std::io::Result<()> {
-    const DIR_PATH: &str = "src/move_gen";
-    const FILE_NAME: &str = "magics.rs";
 
-    let rook_index_bits = 16;
-    let (rook_magics, _rook_table) = create_magics(Direction::Orthogonal, rook_index_bits);
+    Some((seed, shrink_attempts))
+}
 
-    let bishop_index_bits = 14;
-    let (bishop_magics, _bishop_table) = create_magics(Direction::Diagonal, bishop_index_bits);
+fn main() {
+    let Some((seed, shrink_attempts)) = parse_args() else {
+        eprintln!("Usage: generate_magic_bitboards [--seed N] [--shrink ATTEMPTS]");
+        process::exit(1);
+    };
 
-    let mut buf = String::from("use super::MagicEntry;\nuse crate::board::mask::Mask;\n");
+    let mut rng = Rng::new(seed);
 
-    // Rook magics
-    buf.push_str(&format!(
-        "pub const ROOK_MAGICS: &[MagicEntry; 64] = &{:#?};\n",
-        rook_magics
-    ));
+    let started = Instant::now();
+    let rook_magics = generate::find_magics(false, 0, shrink_attempts, &mut rng);
+    print_statistics("Rooks", &rook_magics, 16, started);
 
-    // Bishop magics
-    buf.push_str(&format!(
-        "pub const BISHOP_MAGICS: &[MagicEntry; 64] = &{:#?};\n",
-        bishop_magics
-    ));
+    // Bishop moves come right after the rook moves in the shared table
+    let bishop_offset = generate::table_size(&rook_magics);
 
-    fs::create_dir_all(DIR_PATH)?;
-    fs::write(format!("{DIR_PATH}/{FILE_NAME}"), buf)?;
+    let started = Instant::now();
+    let bishop_magics = generate::find_magics(true, bishop_offset, shrink_attempts, &mut rng);
+    print_statistics("Bishops", &bishop_magics, 14, started);
 
-    Ok(())
+    let table_size = bishop_offset + generate::table_size(&bishop_magics);
+    println!(
+        "Shared table: {table_size} entries ({} KiB)",
+        table_size * size_of::<Mask>() / 1024
+    );
 }
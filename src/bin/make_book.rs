@@ -0,0 +1,460 @@
+use std::{collections::HashMap, env, fs, process};
+
+use chess::{
+    board::{
+        moves::{PackedMove, Promotion},
+        piece::{Color, Piece},
+        square::Square,
+        Board, START_FEN,
+    },
+    book::{self, BookEntry},
+};
+
+/// Build settings, changed with `--plies`, `--min-games` and `--min-score`.
+struct Config {
+    /// Only positions up to this many plies into a game are recorded
+    max_plies: usize,
+    /// Moves played in fewer games than this are left out
+    min_games: u32,
+    /// Moves scoring less than this for the side playing them (from 0.0 to 1.0) are left out
+    min_score: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_plies: 20,
+            min_games: 3,
+            min_score: 0.3,
+        }
+    }
+}
+
+/// How a move did in the games it was played in.
+#[derive(Default)]
+struct Stats {
+    games: u32,
+    /// 2 for a win and 1 for a draw, for the side that played the move
+    points: u32,
+}
+
+/// Games left out of the book, which are reported so that a book built from unusual PGNs doesn't
+/// come out biased without anyone noticing.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Skipped {
+    /// Games without a result
+    unfinished: usize,
+    /// Games with a bad FEN or a move that can't be replayed within the recorded plies
+    unreadable: usize,
+}
+
+/// A game from a PGN file, with its moves still in SAN.
+struct Game {
+    fen: Option<String>,
+    moves: Vec<String>,
+    /// Points for white, or `None` if the game is unfinished
+    white_points: Option<u32>,
+}
+
+fn parse_result(token: &str) -> Option<Option<u32>> {
+    match token {
+        "1-0" => Some(Some(2)),
+        "1/2-1/2" => Some(Some(1)),
+        "0-1" => Some(Some(0)),
+        "*" => Some(None),
+        _ => None,
+    }
+}
+
+/// Splits a PGN file into games. Comments, variations, NAGs and move numbers are skipped.
+fn parse_pgn(pgn: &str) -> Vec<Game> {
+    let mut games = Vec::new();
+    let mut game = Game {
+        fen: None,
+        moves: Vec::new(),
+        white_points: None,
+    };
+
+    let mut finish = |game: &mut Game| {
+        let finished = Game {
+            fen: game.fen.take(),
+            moves: std::mem::take(&mut game.moves),
+            white_points: game.white_points.take(),
+        };
+        if !finished.moves.is_empty() {
+            games.push(finished);
+        }
+    };
+
+    let mut comment_depth = 0;
+    let mut variation_depth = 0;
+
+    for line in pgn.lines() {
+        let line = line.trim();
+
+        if comment_depth == 0 && variation_depth == 0 && line.starts_with('[') {
+            // A tag section after movetext starts a new game, even without a result token
+            if !game.moves.is_empty() {
+                finish(&mut game);
+            }
+
+            let mut parts = line
+                .trim_matches(|ch| ch == '[' || ch == ']')
+                .splitn(2, ' ');
+            let tag = parts.next().unwrap_or("");
+            let value = parts.next().unwrap_or("").trim_matches('"');
+            if tag == "FEN" {
+                game.fen = Some(value.to_string());
+            }
+            continue;
+        }
+
+        // Rest of line comments
+        let line = match line.find(';') {
+            Some(i) if comment_depth == 0 => &line[..i],
+            _ => line,
+        };
+
+        let line = line
+            .replace('{', " { ")
+            .replace('}', " } ")
+            .replace('(', " ( ")
+            .replace(')', " ) ");
+
+        for token in line.split_whitespace() {
+            match token {
+                "{" => comment_depth += 1,
+                "}" => comment_depth -= 1,
+                _ if comment_depth > 0 => (),
+                "(" => variation_depth += 1,
+                ")" => variation_depth -= 1,
+                _ if variation_depth > 0 => (),
+                _ if token.starts_with('$') => (),
+                _ => {
+                    if let Some(white_points) = parse_result(token) {
+                        game.white_points = white_points;
+                        finish(&mut game);
+                        continue;
+                    }
+
+                    // Move numbers can be glued to the move, as in `1.e4`
+                    let san = token.rsplit('.').next().unwrap_or(token);
+                    if !san.is_empty() {
+                        game.moves.push(san.to_string());
+                    }
+                }
+            }
+        }
+    }
+    finish(&mut game);
+
+    games
+}
+
+/// Finds the legal move a SAN move like `Nbd7`, `exd5`, `O-O` or `e8=Q+` stands for.
+fn parse_san(san: &str, board: &Board) -> Option<PackedMove> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let state = board.current_position();
+    let color = state.active_color();
+    let legal_moves = board.legal_moves();
+
+    let castle = |to: Square| {
+        let from = match color {
+            Color::White => Square::E1,
+            Color::Black => Square::E8,
+        };
+        legal_moves
+            .iter()
+            .copied()
+            .find(|mv| mv.from_square() == from && mv.to_square() == to)
+            .filter(|_| state.piece_at_square(from) == Some(Piece::King(color)))
+    };
+
+    match (san, color) {
+        ("O-O" | "0-0", Color::White) => return castle(Square::G1),
+        ("O-O" | "0-0", Color::Black) => return castle(Square::G8),
+        ("O-O-O" | "0-0-0", Color::White) => return castle(Square::C1),
+        ("O-O-O" | "0-0-0", Color::Black) => return castle(Square::C8),
+        _ => (),
+    }
+
+    let (san, promotion) = match san.split_once('=') {
+        Some((san, piece)) => {
+            let piece = piece.chars().next()?.to_ascii_lowercase();
+            (san, Some(Promotion::from_char(piece)?))
+        }
+        None => (san, None),
+    };
+
+    let (piece, rest) = match san.chars().next()? {
+        'N' => (Piece::Knight(color), &san[1..]),
+        'B' => (Piece::Bishop(color), &san[1..]),
+        'R' => (Piece::Rook(color), &san[1..]),
+        'Q' => (Piece::Queen(color), &san[1..]),
+        'K' => (Piece::King(color), &san[1..]),
+        _ => (Piece::Pawn(color), san),
+    };
+
+    let rest = rest.replace('x', "");
+    let to = Square::from_str(rest.get(rest.len().checked_sub(2)?..)?)?;
+    let disambiguation = &rest[..rest.len() - 2];
+
+    let matches = legal_moves
+        .into_iter()
+        .filter(|mv| mv.to_square() == to && state.piece_at_square(mv.from_square()) == Some(piece))
+        .filter(|mv| mv.flag().promotion_kind() == promotion)
+        .filter(|mv| {
+            let from = mv.from_square().to_string();
+            disambiguation.chars().all(|ch| from.contains(ch))
+        })
+        .collect::<Vec<_>>();
+
+    match matches[..] {
+        [mv] => Some(mv),
+        _ => None,
+    }
+}
+
+/// Replays every game, collecting stats for the moves played in their first plies. Games that
+/// can't be replayed that far are left out entirely, rather than cut short.
+fn collect_stats(games: &[Game], config: &Config) -> (HashMap<(u64, u16), Stats>, Skipped) {
+    let mut stats = HashMap::<_, Stats>::new();
+    let mut skipped = Skipped::default();
+
+    for game in games {
+        let Some(white_points) = game.white_points else {
+            skipped.unfinished += 1;
+            continue;
+        };
+
+        match replay(game, config.max_plies) {
+            Some(moves) => {
+                for (key, raw_move, color) in moves {
+                    let entry = stats.entry((key, raw_move)).or_default();
+                    entry.games += 1;
+                    entry.points += match color {
+                        Color::White => white_points,
+                        Color::Black => 2 - white_points,
+                    };
+                }
+            }
+            None => skipped.unreadable += 1,
+        }
+    }
+
+    (stats, skipped)
+}
+
+/// The first `max_plies` moves of a game as `(key, Polyglot move, side to move)`, or `None` if
+/// one of them can't be replayed.
+fn replay(game: &Game, max_plies: usize) -> Option<Vec<(u64, u16, Color)>> {
+    let mut board = Board::new(game.fen.as_deref().unwrap_or(START_FEN)).ok()?;
+    let mut moves = Vec::new();
+
+    for san in game.moves.iter().take(max_plies) {
+        let mv = parse_san(san, &board)?;
+        let state = board.current_position();
+
+        moves.push((
+            book::key(state),
+            book::encode_move(mv.to_move(), state),
+            state.active_color(),
+        ));
+        board.make_packed_move(mv).unwrap();
+    }
+
+    Some(moves)
+}
+
+/// Turns the stats into sorted book entries weighted by points, leaving out rare and bad
+/// moves.
+fn build_entries(stats: HashMap<(u64, u16), Stats>, config: &Config) -> Vec<BookEntry> {
+    let kept = stats
+        .into_iter()
+        .filter(|(_, stats)| {
+            stats.games >= config.min_games
+                && stats.points as f64 / (2 * stats.games) as f64 >= config.min_score
+        })
+        .collect::<Vec<_>>();
+
+    // Weights are 16 bits, so points are scaled down if any of them don't fit
+    let max_points = kept
+        .iter()
+        .map(|(_, stats)| stats.points)
+        .max()
+        .unwrap_or(0);
+    let scale = (max_points as f64 / u16::MAX as f64).max(1.0);
+
+    let mut entries = kept
+        .into_iter()
+        .map(|((key, raw_move), stats)| BookEntry {
+            key,
+            raw_move,
+            weight: ((stats.points as f64 / scale) as u16).max(1),
+            learn: 0,
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| (entry.key, u16::MAX - entry.weight, entry.raw_move));
+
+    entries
+}
+
+fn parse_args() -> Option<(String, Vec<String>, Config)> {
+    let mut args = env::args().skip(1);
+    let output_path = args.next()?;
+
+    let mut pgn_paths = Vec::new();
+    let mut config = Config::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--plies" => config.max_plies = args.next()?.parse().ok()?,
+            "--min-games" => config.min_games = args.next()?.parse().ok()?,
+            "--min-score" => config.min_score = args.next()?.parse().ok()?,
+            _ => pgn_paths.push(arg),
+        }
+    }
+
+    if pgn_paths.is_empty() {
+        return None;
+    }
+
+    Some((output_path, pgn_paths, config))
+}
+
+fn main() -> std::io::Result<()> {
+    let Some((output_path, pgn_paths, config)) = parse_args() else {
+        eprintln!(
+            "Usage: make_book <output file> <PGN files>... [--plies N] \
+             [--min-games N] [--min-score 0.0-1.0]"
+        );
+        process::exit(1);
+    };
+
+    let mut games = Vec::new();
+    for path in &pgn_paths {
+        games.extend(parse_pgn(&fs::read_to_string(path)?));
+    }
+    println!("Read {} games", games.len());
+
+    let (stats, skipped) = collect_stats(&games, &config);
+    if skipped.unfinished > 0 {
+        println!("Skipped {} unfinished games", skipped.unfinished);
+    }
+    if skipped.unreadable > 0 {
+        println!(
+            "Skipped {} games with moves that couldn't be replayed",
+            skipped.unreadable
+        );
+    }
+
+    let entries = build_entries(stats, &config);
+
+    let bytes = entries
+        .iter()
+        .flat_map(|entry| entry.to_bytes())
+        .collect::<Vec<_>>();
+    fs::write(&output_path, bytes)?;
+
+    println!("Wrote {} entries to {output_path}", entries.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod make_book_tests {
+    use super::*;
+
+    fn san(board: &Board, san: &str) -> Option<String> {
+        parse_san(san, board).map(|mv| mv.to_string())
+    }
+
+    #[test]
+    fn parses_pgn_movetext() {
+        let pgn = r#"[Event "First"]
+[White "A"]
+
+1.e4 {best by test} e5 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) Nc6 $1 ; a comment { with a brace
+3. Bb5 a6 1-0
+
+[Event "Second"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1. e4 Kd7 2. e5 1/2-1/2
+
+[Event "Third"]
+
+1. d4 d5 *
+"#;
+        let games = parse_pgn(pgn);
+
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].moves, ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+        assert_eq!(games[0].white_points, Some(2));
+        assert_eq!(games[0].fen, None);
+
+        assert_eq!(games[1].moves, ["e4", "Kd7", "e5"]);
+        assert_eq!(games[1].white_points, Some(1));
+        assert_eq!(
+            games[1].fen.as_deref(),
+            Some("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1")
+        );
+
+        assert_eq!(games[2].moves, ["d4", "d5"]);
+        assert_eq!(games[2].white_points, None);
+    }
+
+    #[test]
+    fn parses_san_moves() {
+        let start = Board::new(START_FEN).unwrap();
+        assert_eq!(san(&start, "e4"), Some(String::from("e2e4")));
+        assert_eq!(san(&start, "Nf3!?"), Some(String::from("g1f3")));
+        assert_eq!(san(&start, "e5"), None);
+        assert_eq!(san(&start, "Qd4"), None);
+
+        // Both knights can reach d7, and both rooks e2
+        let board = Board::new("r3k2r/1n3n2/8/8/8/8/4P3/R3K2R b KQkq - 0 1").unwrap();
+        assert_eq!(san(&board, "Nd6"), None);
+        assert_eq!(san(&board, "Nbd6"), Some(String::from("b7d6")));
+        assert_eq!(san(&board, "Nfd6"), Some(String::from("f7d6")));
+        assert_eq!(san(&board, "O-O"), Some(String::from("e8g8")));
+        assert_eq!(san(&board, "O-O-O"), Some(String::from("e8c8")));
+
+        let board = Board::new("4k3/8/8/8/8/4R3/8/K3R3 w - - 0 1").unwrap();
+        assert_eq!(san(&board, "Re2"), None);
+        assert_eq!(san(&board, "R1e2"), Some(String::from("e1e2")));
+        assert_eq!(san(&board, "R3e2+"), Some(String::from("e3e2")));
+
+        let board = Board::new("3rk3/2P5/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(san(&board, "exd5"), Some(String::from("e4d5")));
+        assert_eq!(san(&board, "c8=Q+"), Some(String::from("c7c8q")));
+        assert_eq!(san(&board, "cxd8=N"), Some(String::from("c7d8n")));
+        assert_eq!(san(&board, "c8"), None);
+        assert_eq!(san(&board, "c8=K"), None);
+    }
+
+    #[test]
+    fn skips_games_that_cant_be_replayed() {
+        let pgn =
+            "1. e4 e5 2. Nf3 Nc6 1-0\n\n1. e4 e5 2. Ke3 Nc6 0-1\n\n1. e4 *\n\n1. e4 c5 1/2-1/2";
+        let games = parse_pgn(pgn);
+        let config = Config::default();
+
+        let (stats, skipped) = collect_stats(&games, &config);
+        assert_eq!(
+            skipped,
+            Skipped {
+                unfinished: 1,
+                unreadable: 1,
+            }
+        );
+
+        // 1. e4 was played in the two games that were kept, for 3 points out of 4
+        let start = Board::new(START_FEN).unwrap();
+        let state = start.current_position();
+        let e4 = book::encode_move(parse_san("e4", &start).unwrap().to_move(), state);
+        let e4 = &stats[&(book::key(state), e4)];
+        assert_eq!((e4.games, e4.points), (2, 3));
+        assert_eq!(stats.values().map(|stats| stats.games).sum::<u32>(), 6);
+    }
+}
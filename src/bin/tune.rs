@@ -0,0 +1,247 @@
+use std::{env, fmt::Write, fs, process, thread};
+
+use chess::{
+    board::{piece::Color, BoardState},
+    eval::{self, weights::WEIGHTS, Score, Weights},
+};
+
+/// A quiet position paired with the result of the game it was taken from.
+struct Entry {
+    state: BoardState,
+    /// Game result from white's point of view (1.0 win, 0.5 draw, 0.0 loss)
+    result: f64,
+}
+
+/// Parses the result at the end of a dataset line, e.g. `[1.0]`, `[0.5]`, `"1-0";` or `1/2-1/2`.
+fn parse_result(token: &str) -> Option<f64> {
+    let token = token.trim_matches(|ch| matches!(ch, '[' | ']' | '"' | ';'));
+
+    match token {
+        "1-0" | "1.0" | "1" => Some(1.0),
+        "1/2-1/2" | "0.5" => Some(0.5),
+        "0-1" | "0.0" | "0" => Some(0.0),
+        _ => None,
+    }
+}
+
+/// Loads a dataset with one position per line: a full FEN string followed by the game result,
+/// optionally with a search score in between. Lines that can't be parsed are skipped.
+fn load_dataset(path: &str) -> std::io::Result<Vec<Entry>> {
+    let contents = fs::read_to_string(path)?;
+    let mut entries = Vec::new();
+
+    for line in contents.lines() {
+        // Self-play data from `datagen` is written as `<FEN> | <score> | <result>`
+        let line = line.trim();
+        let split = match line.split_once(" | ") {
+            Some((fen, rest)) => rest.rsplit_once(" | ").map(|(_, result)| (fen, result)),
+            None => line.rsplit_once(' '),
+        };
+        let Some((fen, result)) = split else {
+            continue;
+        };
+        let Some(result) = parse_result(result) else {
+            continue;
+        };
+
+        // EPD-style datasets put the result in a `c9` opcode
+        let fen = fen.trim_end();
+        let fen = fen.strip_suffix(" c9").unwrap_or(fen);
+
+        let Ok(state) = BoardState::from_fen(fen) else {
+            continue;
+        };
+
+        entries.push(Entry { state, result });
+    }
+
+    Ok(entries)
+}
+
+fn white_score(entry: &Entry, weights: &Weights) -> Score {
+    let score = eval::evaluate_with(&entry.state, weights);
+
+    match entry.state.active_color() {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Maps a centipawn score to an expected game result between 0 and 1.
+fn sigmoid(score: Score, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score as f64 / 400.0))
+}
+
+/// Mean squared error between the predicted and actual results over the whole dataset.
+fn error(entries: &[Entry], weights: &Weights, k: f64) -> f64 {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = entries.len().div_ceil(threads).max(1);
+
+    let total: f64 = thread::scope(|scope| {
+        let handles = entries
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|entry| {
+                            let predicted = sigmoid(white_score(entry, weights), k);
+                            (entry.result - predicted).powi(2)
+                        })
+                        .sum::<f64>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    });
+
+    total / entries.len() as f64
+}
+
+/// Finds the sigmoid scaling constant that best fits the current weights to the dataset.
+fn find_k(entries: &[Entry], weights: &Weights) -> f64 {
+    let mut best_k: f64 = 1.0;
+    let mut best_error = f64::MAX;
+
+    // Coarse scan first, then refine around the best value found
+    for step in [0.1, 0.01] {
+        let start = (best_k - 10.0 * step).max(step);
+
+        for i in 0..=20 {
+            let k = start + step * i as f64;
+
+            let e = error(entries, weights, k);
+            if e < best_error {
+                best_error = e;
+                best_k = k;
+            }
+        }
+    }
+
+    best_k
+}
+
+/// Texel-style local search: nudges each weight up or down by one and keeps any change that
+/// lowers the error, until a full pass makes no improvement.
+fn tune(entries: &[Entry], k: f64, max_iterations: usize) -> Weights {
+    let mut best = WEIGHTS.clone();
+    let mut best_error = error(entries, &best, k);
+    let param_count = best.params_mut().len();
+
+    println!("Initial error: {best_error:.6}");
+
+    for iteration in 1..=max_iterations {
+        let mut improved = false;
+
+        for i in 0..param_count {
+            for delta in [1, -1] {
+                let mut candidate = best.clone();
+                *candidate.params_mut()[i] += delta;
+
+                let candidate_error = error(entries, &candidate, k);
+                if candidate_error < best_error {
+                    best = candidate;
+                    best_error = candidate_error;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+
+        println!("Iteration {iteration}: error {best_error:.6}");
+
+        if !improved {
+            break;
+        }
+    }
+
+    best
+}
+
+/// Writes `weights` as the source of `src/eval/weights.rs`, with each piece-square table in rows
+/// of 8 as the board is laid out.
+fn format_weights(weights: &Weights) -> String {
+    const PIECES: [&str; 6] = ["Pawn", "Knight", "Bishop", "Rook", "Queen", "King"];
+
+    fn field(buf: &mut String, name: &str, value: impl std::fmt::Debug) {
+        writeln!(buf, "    {name}: {value:?},").unwrap();
+    }
+
+    let mut buf = String::from("use super::Weights;\n\n");
+    buf.push_str("#[rustfmt::skip]\npub const WEIGHTS: Weights = Weights {\n");
+    field(&mut buf, "material", weights.material);
+
+    buf.push_str("    pst: [\n");
+    for (name, table) in PIECES.iter().zip(&weights.pst) {
+        writeln!(buf, "        // {name}\n        [").unwrap();
+        for row in table.chunks(8) {
+            let row = row.iter().map(|value| format!("{value:>3},"));
+            writeln!(buf, "            {}", row.collect::<Vec<_>>().join(" ")).unwrap();
+        }
+        buf.push_str("        ],\n");
+    }
+    buf.push_str("    ],\n\n");
+
+    field(&mut buf, "doubled_pawn", weights.doubled_pawn);
+    field(&mut buf, "isolated_pawn", weights.isolated_pawn);
+    field(&mut buf, "passed_pawn", weights.passed_pawn);
+    buf.push('\n');
+
+    field(&mut buf, "king_attacker", weights.king_attacker);
+    field(&mut buf, "king_attack_scale", weights.king_attack_scale);
+    field(&mut buf, "pawn_shield", weights.pawn_shield);
+    field(&mut buf, "king_open_file", weights.king_open_file);
+    field(&mut buf, "king_semi_open_file", weights.king_semi_open_file);
+    buf.push('\n');
+
+    field(&mut buf, "mobility", weights.mobility);
+    field(&mut buf, "trapped_piece", weights.trapped_piece);
+    field(&mut buf, "bishop_pair", weights.bishop_pair);
+    field(&mut buf, "rook_open_file", weights.rook_open_file);
+    field(&mut buf, "rook_semi_open_file", weights.rook_semi_open_file);
+    field(&mut buf, "rook_seventh_rank", weights.rook_seventh_rank);
+    field(&mut buf, "knight_outpost", weights.knight_outpost);
+    buf.push_str("};\n");
+
+    buf
+}
+
+fn main() -> std::io::Result<()> {
+    const DIR_PATH: &str = "src/eval";
+    const FILE_NAME: &str = "weights.rs";
+
+    let mut args = env::args().skip(1);
+    let Some(dataset_path) = args.next() else {
+        eprintln!("Usage: tune <dataset> [max iterations]");
+        process::exit(1);
+    };
+    let max_iterations = args.next().and_then(|n| n.parse().ok()).unwrap_or(100);
+
+    let entries = load_dataset(&dataset_path)?;
+    if entries.is_empty() {
+        eprintln!("No positions could be loaded from {dataset_path}");
+        process::exit(1);
+    }
+    println!("Loaded {} positions", entries.len());
+
+    let k = find_k(&entries, &WEIGHTS);
+    println!("Using K = {k:.2}");
+
+    let weights = tune(&entries, k, max_iterations);
+
+    fs::create_dir_all(DIR_PATH)?;
+    fs::write(format!("{DIR_PATH}/{FILE_NAME}"), format_weights(&weights))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tune_tests {
+    use super::*;
+
+    #[test]
+    fn writes_weights_in_the_existing_layout() {
+        assert_eq!(format_weights(&WEIGHTS), include_str!("../eval/weights.rs"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Text rendering of positions.
+//!
+//! `BoardState` and `Board` print as an ASCII grid with coordinates, the side to move and the
+//! FEN. The alternate flag (`{:#}`) uses chess glyphs instead of letters, and `display` gives
+//! more control, such as highlighting a move or the squares of a mask.
+
+use std::fmt;
+
+use super::{mask::Mask, moves::Move, piece::Color, square::Square, Board, BoardState};
+
+const SEPARATOR: &str = "  +---+---+---+---+---+---+---+---+";
+const FILES: &str = "    a   b   c   d   e   f   g   h";
+
+/// A position ready to be printed, built by `BoardState::display`.
+#[derive(Debug, Clone, Copy)]
+pub struct BoardDisplay<'a> {
+    state: &'a BoardState,
+    unicode: bool,
+    highlight: Mask,
+    footer: bool,
+}
+
+impl<'a> BoardDisplay<'a> {
+    /// Use chess glyphs like `♞` rather than FEN letters.
+    pub fn unicode(mut self, unicode: bool) -> Self {
+        self.unicode = unicode;
+        self
+    }
+
+    /// Marks the squares of `mask`, for example the squares a piece attacks.
+    pub fn highlight(mut self, mask: Mask) -> Self {
+        self.highlight |= mask;
+        self
+    }
+
+    /// Marks the squares a move goes from and to.
+    pub fn highlight_move(self, mv: Move) -> Self {
+        self.highlight(mv.from.mask() | mv.to.mask())
+    }
+
+    /// Whether to print the side to move and the FEN under the board.
+    pub fn footer(mut self, footer: bool) -> Self {
+        self.footer = footer;
+        self
+    }
+}
+
+impl fmt::Display for BoardDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{SEPARATOR}")?;
+
+        for rank in (0..8).rev() {
+            write!(f, "{} |", rank + 1)?;
+
+            for file in 0..8 {
+                let square = Square::from_u8(rank * 8 + file).unwrap();
+                let piece = match self.state.piece_at_square(square) {
+                    Some(piece) if self.unicode => piece.to_unicode(),
+                    Some(piece) => piece.to_char(),
+                    None => ' ',
+                };
+
+                match self.highlight.contains(square) {
+                    true => write!(f, "[{piece}]|")?,
+                    false => write!(f, " {piece} |")?,
+                }
+            }
+
+            writeln!(f)?;
+            writeln!(f, "{SEPARATOR}")?;
+        }
+
+        write!(f, "{FILES}")?;
+
+        if self.footer {
+            let side = match self.state.active_color() {
+                Color::White => "White",
+                Color::Black => "Black",
+            };
+
+            writeln!(f)?;
+            writeln!(f)?;
+            writeln!(f, "{side} to move")?;
+            write!(f, "FEN: {}", self.state.to_fen())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl BoardState {
+    /// Renders the position, with options for glyphs and highlighting.
+    pub fn display(&self) -> BoardDisplay<'_> {
+        BoardDisplay {
+            state: self,
+            unicode: false,
+            highlight: Mask(0),
+            footer: true,
+        }
+    }
+}
+
+impl fmt::Display for BoardState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.display().unicode(f.alternate()))
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.current_position(), f)
+    }
+}
+
+#[cfg(test)]
+mod display_tests {
+    use super::*;
+    use crate::board::{piece::Piece, START_FEN};
+
+    #[test]
+    fn renders_start_position() {
+        let state = BoardState::from_fen(START_FEN).unwrap();
+        let text = state.to_string();
+        let lines = text.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], SEPARATOR);
+        assert_eq!(lines[1], "8 | r | n | b | q | k | b | n | r |");
+        assert_eq!(lines[9], "4 |   |   |   |   |   |   |   |   |");
+        assert_eq!(lines[15], "1 | R | N | B | Q | K | B | N | R |");
+        assert_eq!(lines[17], FILES);
+        assert_eq!(lines[19], "White to move");
+        assert_eq!(lines[20], format!("FEN: {START_FEN}"));
+
+        let unicode = format!("{state:#}");
+        assert!(unicode.contains("8 | ♜ | ♞ | ♝ | ♛ | ♚ | ♝ | ♞ | ♜ |"));
+    }
+
+    #[test]
+    fn highlights_moves_and_masks() {
+        let mut board = Board::new(START_FEN).unwrap();
+        let mv = Move::from_long_algebraic("g1f3").unwrap();
+        board.make_move(mv).unwrap();
+
+        let state = board.current_position();
+        let text = state.display().highlight_move(mv).footer(false).to_string();
+        assert!(text.contains("3 |   |   |   |   |   |[N]|   |   |"));
+        assert!(text.contains("1 | R | N | B | Q | K | B |[ ]| R |"));
+        assert!(text.ends_with(FILES));
+
+        let attacks = state.piece_move_mask(Piece::Knight(Color::White), Square::F3);
+        let text = state.display().highlight(attacks).to_string();
+        assert!(text.contains("5 |   |   |   |   |[ ]|   |[ ]|   |"));
+        assert!(board.to_string().contains("Black to move"));
+    }
+
+    #[test]
+    fn displays_pieces_squares_and_moves() {
+        assert_eq!(Square::E4.to_string(), "e4");
+        assert_eq!(Square::H8.to_string(), "h8");
+        assert_eq!(Piece::Knight(Color::Black).to_string(), "n");
+        assert_eq!(format!("{:#}", Piece::Queen(Color::White)), "♕");
+        assert_eq!(
+            Move::from_long_algebraic("e7e8").unwrap().to_string(),
+            "e7e8"
+        );
+    }
+}
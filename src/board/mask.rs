@@ -6,40 +6,89 @@ use crate::board::square::Square;
 pub struct Mask(pub u64);
 
 impl Mask {
+    pub const EMPTY: Mask = Mask(0);
+    pub const FILE_A: Mask = Mask(0x0101_0101_0101_0101);
+    pub const FILE_H: Mask = Mask(0x8080_8080_8080_8080);
+
+    /// Iterates over the set squares from A1 to H8 without allocating.
+    pub fn iter(&self) -> Squares {
+        Squares(self.0)
+    }
+
+    /// The set squares, from A1 to H8. Prefer `iter` where a `Vec` isn't needed.
     pub fn ones(&self) -> Vec<Square> {
-        let mut squares = vec![];
+        self.iter().collect()
+    }
 
-        let mut square = self.0;
-        let mut i = 0;
+    /// The squares that aren't set, from A1 to H8.
+    pub fn zeroes(&self) -> Vec<Square> {
+        (!*self).iter().collect()
+    }
 
-        while square > 0 {
-            if square & 1 == 1 {
-                squares.push(Square::from_u8(i).unwrap());
-            }
+    /// Number of set squares.
+    pub const fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
 
-            square >>= 1;
-            i += 1;
-        }
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
 
-        squares
+    pub const fn contains(&self, square: Square) -> bool {
+        self.0 & (1 << square as u8) != 0
     }
 
-    pub fn zeroes(&self) -> Vec<Square> {
-        let mut squares = vec![];
+    /// The lowest set square.
+    pub fn lsb(&self) -> Option<Square> {
+        Square::from_u8(self.0.trailing_zeros() as u8)
+    }
 
-        let mut square = !self.0;
-        let mut i = 0;
+    /// The highest set square.
+    pub fn msb(&self) -> Option<Square> {
+        63u8.checked_sub(self.0.leading_zeros() as u8)
+            .and_then(Square::from_u8)
+    }
 
-        while square > 0 {
-            if square & 1 == 1 {
-                squares.push(Square::from_u8(i).unwrap());
-            }
+    /// Removes and returns the lowest set square.
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let square = self.lsb()?;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
 
-            square >>= 1;
-            i += 1;
-        }
+    // Shifts by one square in each direction. Squares shifted off the board are dropped, without
+    // wrapping around to the other side.
+
+    pub const fn north(self) -> Mask {
+        Mask(self.0 << 8)
+    }
 
-        squares
+    pub const fn south(self) -> Mask {
+        Mask(self.0 >> 8)
+    }
+
+    pub const fn east(self) -> Mask {
+        Mask((self.0 & !Self::FILE_H.0) << 1)
+    }
+
+    pub const fn west(self) -> Mask {
+        Mask((self.0 & !Self::FILE_A.0) >> 1)
+    }
+
+    pub const fn north_east(self) -> Mask {
+        self.east().north()
+    }
+
+    pub const fn north_west(self) -> Mask {
+        self.west().north()
+    }
+
+    pub const fn south_east(self) -> Mask {
+        self.east().south()
+    }
+
+    pub const fn south_west(self) -> Mask {
+        self.west().south()
     }
 
     pub fn subsets(&self) -> Vec<Mask> {
@@ -87,6 +136,40 @@ impl Mask {
     }
 }
 
+/// Iterator over the set squares of a mask, lowest first.
+#[derive(Debug, Clone, Copy)]
+pub struct Squares(u64);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0 {
+            return None;
+        }
+
+        let square = Square::from_u8(self.0.trailing_zeros() as u8);
+        self.0 &= self.0 - 1;
+        square
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count_ones() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for Squares {}
+
+impl IntoIterator for Mask {
+    type Item = Square;
+    type IntoIter = Squares;
+
+    fn into_iter(self) -> Squares {
+        self.iter()
+    }
+}
+
 impl BitAnd for Mask {
     fn bitand(self, rhs: Self) -> Self::Output {
         Mask(self.0 & rhs.0)
@@ -157,4 +240,44 @@ mod mask_tests {
         );
         assert_eq!(Mask(1).ones(), vec![Square::A1]);
     }
+
+    #[test]
+    fn iterates_and_queries_bits() {
+        let mask = Square::B1.mask() | Square::E4.mask() | Square::H8.mask();
+
+        assert_eq!(
+            mask.iter().collect::<Vec<_>>(),
+            vec![Square::B1, Square::E4, Square::H8]
+        );
+        assert_eq!(mask.iter().len(), 3);
+        assert_eq!(mask.count(), 3);
+        assert_eq!(mask.lsb(), Some(Square::B1));
+        assert_eq!(mask.msb(), Some(Square::H8));
+        assert!(mask.contains(Square::E4));
+        assert!(!mask.contains(Square::E5));
+        assert_eq!(mask.zeroes().len(), 61);
+
+        let mut popped = mask;
+        assert_eq!(popped.pop_lsb(), Some(Square::B1));
+        assert_eq!(popped.count(), 2);
+
+        assert!(Mask::EMPTY.is_empty());
+        assert_eq!(Mask::EMPTY.lsb(), None);
+        assert_eq!(Mask::EMPTY.msb(), None);
+        assert_eq!(Mask::EMPTY.iter().next(), None);
+    }
+
+    #[test]
+    fn shifts_without_wrapping() {
+        let corners = Square::A1.mask() | Square::H8.mask();
+
+        assert_eq!(corners.north(), Square::A2.mask());
+        assert_eq!(corners.south(), Square::H7.mask());
+        assert_eq!(corners.east(), Square::B1.mask());
+        assert_eq!(corners.west(), Square::G8.mask());
+        assert_eq!(corners.north_east(), Square::B2.mask());
+        assert_eq!(corners.south_west(), Square::G7.mask());
+        assert_eq!(corners.north_west(), Mask::EMPTY);
+        assert_eq!(corners.south_east(), Mask::EMPTY);
+    }
 }
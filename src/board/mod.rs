@@ -1,17 +1,25 @@
+pub mod display;
 pub mod mask;
 pub mod moves;
 pub mod piece;
 pub mod square;
+pub mod zobrist;
+
+use std::sync::Arc;
 
 use crate::board::mask::Mask;
-use crate::board::moves::{Move, MoveError};
+use crate::board::moves::{Move, MoveError, MoveFlag, MoveList, PackedMove, Promotion};
 use crate::board::piece::{Color, Piece};
 use crate::board::square::{Rank, Square};
+#[cfg(feature = "nnue")]
+use crate::eval::nnue::{Accumulator, Network};
+use crate::eval::{self, weights::WEIGHTS, Score, Weights};
 use crate::move_gen::move_masks::{
-    BISHOP_MOVE_MASKS, BLACK_PAWN_CAPTURE_MASKS, BLACK_PAWN_MOVE_MASKS, KING_MOVE_MASKS,
-    KNIGHT_MOVE_MASKS, ROOK_MOVE_MASKS, WHITE_PAWN_CAPTURE_MASKS, WHITE_PAWN_MOVE_MASKS,
+    BLACK_PAWN_CAPTURE_MASKS, BLACK_PAWN_MOVE_MASKS, KING_MOVE_MASKS, KNIGHT_MOVE_MASKS,
+    WHITE_PAWN_CAPTURE_MASKS, WHITE_PAWN_MOVE_MASKS,
 };
 use crate::move_gen::SlidingMoves;
+use crate::tablebase::Probe;
 
 // Starting position
 pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
@@ -49,6 +57,10 @@ pub enum SpecialMove {
 pub struct BoardState {
     active_color: Color,
     masks: [Mask; 12],
+    // Kept in sync with the masks for quick lookups
+    mailbox: [Option<Piece>; 64],
+    occupancy: [Mask; 2], // Indexed by color
+    hash: u64,            // Zobrist hash, updated as moves are made
 
     // Historical data
     last_move: Option<Move>, // En passant
@@ -66,6 +78,9 @@ impl BoardState {
         Self {
             active_color: Color::White,
             masks: [Mask(0); 12],
+            mailbox: [None; 64],
+            occupancy: [Mask(0); 2],
+            hash: 0,
 
             last_move: None,
             a1_rook_moved: false,
@@ -109,8 +124,11 @@ impl BoardState {
                                 // * Guaranteed to never panic due to above range check
                                 let piece_type = Piece::from_char(ch).unwrap();
 
-                                let mask = state.mask_mut(piece_type);
-                                mask.0 |= 1 << (current_pos + i * 8);
+                                let Some(square) = Square::from_u8((current_pos + i * 8) as u8)
+                                else {
+                                    return Err(FenError::BadPosition);
+                                };
+                                state.put_piece(piece_type, square);
                                 current_pos += 1;
                             }
 
@@ -140,15 +158,15 @@ impl BoardState {
                 return Err(FenError::MissingSection);
             };
 
-            if castling_rights == "-" {
-                break 'castling_rights;
-            }
-
             state.a1_rook_moved = true;
             state.h1_rook_moved = true;
             state.a8_rook_moved = true;
             state.h8_rook_moved = true;
 
+            if castling_rights == "-" {
+                break 'castling_rights;
+            }
+
             let mut prev: u8 = 0;
 
             for ch in castling_rights.chars() {
@@ -158,27 +176,27 @@ impl BoardState {
                         panic!("Castling rights in FEN string ordered incorrectly");
                     }
                     prev = 1;
-                    state.a1_rook_moved = false;
+                    state.h1_rook_moved = false;
                 }
                 'Q' => {
                     if prev > 1 {
                         panic!("Castling rights in FEN string ordered incorrectly");
                     }
                     prev = 2;
-                    state.h1_rook_moved = false;
+                    state.a1_rook_moved = false;
                 }
                 'k' => {
                     if prev > 2 {
                         panic!("Castling rights in FEN string ordered incorrectly");
                     }
                     prev = 3;
-                    state.a8_rook_moved = false;
+                    state.h8_rook_moved = false;
                 }
                 'q' => {
                     if prev > 3 {
                         panic!("Castling rights in FEN string ordered incorrectly");
                     }
-                    state.h8_rook_moved = false;
+                    state.a8_rook_moved = false;
                 }
                 _ => panic!("Third segment of FEN string should only contain the characters 'K', 'Q', 'k', 'q'"),
             }
@@ -209,14 +227,14 @@ impl BoardState {
             let file = square.file();
 
             let mut mv = match above_rank {
-                Rank::Four => Move {
-                    from: Square::from_coords(below_rank, file),
-                    to: Square::from_coords(above_rank, file),
-                },
-                Rank::Seven => Move {
-                    from: Square::from_coords(above_rank, file),
-                    to: Square::from_coords(below_rank, file),
-                },
+                Rank::Four => Move::new(
+                    Square::from_coords(below_rank, file),
+                    Square::from_coords(above_rank, file),
+                ),
+                Rank::Seven => Move::new(
+                    Square::from_coords(above_rank, file),
+                    Square::from_coords(below_rank, file),
+                ),
                 _ => return Err(FenError::BadEnPassant),
             };
 
@@ -253,54 +271,112 @@ impl BoardState {
             return Err(FenError::TooManySections);
         }
 
+        state.hash = zobrist::hash(&state);
+
         Ok(state)
     }
 
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty_squares = 0;
+
+            for file in 0..8 {
+                let square = Square::from_u8(rank * 8 + file).unwrap();
+
+                if let Some(piece) = self.piece_at_square(square) {
+                    if empty_squares > 0 {
+                        fen.push_str(&empty_squares.to_string());
+                        empty_squares = 0;
+                    }
+                    fen.push(piece.to_char());
+                } else {
+                    empty_squares += 1;
+                }
+            }
+
+            if empty_squares > 0 {
+                fen.push_str(&empty_squares.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push(' ');
+        fen.push(match self.active_color {
+            Color::White => 'w',
+            Color::Black => 'b',
+        });
+
+        let mut castling_rights = String::new();
+        for (moved, ch) in [
+            (self.h1_rook_moved, 'K'),
+            (self.a1_rook_moved, 'Q'),
+            (self.h8_rook_moved, 'k'),
+            (self.a8_rook_moved, 'q'),
+        ] {
+            if !moved {
+                castling_rights.push(ch);
+            }
+        }
+        if castling_rights.is_empty() {
+            castling_rights.push('-');
+        }
+
+        let en_passant = match self.en_passant_mask().and_then(Square::from_mask) {
+            Some(square) => square.to_string(),
+            None => String::from("-"),
+        };
+
+        format!(
+            "{} {} {} {} {}",
+            fen, castling_rights, en_passant, self.halfmoves, self.fullmoves
+        )
+    }
+
     /// Makes a move on the board, regardless of whether the move is legal or not.
     /// Despite its name, this function does still check if the move is possible to make or not.
+    /// Generated moves should be made with `make_packed_move` instead, which doesn't need to
+    /// work out what kind of move they are.
     pub fn make_move_unchecked(&self, mv: Move) -> Result<BoardState, MoveError> {
+        self.make_packed_move(PackedMove::from_move(mv, self))
+    }
+
+    /// Like `make_move_unchecked`, but castling, en passant and promotions are taken from the
+    /// move's flag rather than worked out from the board.
+    pub fn make_packed_move(&self, mv: PackedMove) -> Result<BoardState, MoveError> {
         // Store copy of old board state
         let mut new_state = self.clone();
         let active_color = new_state.active_color;
+        let (from, to, flag) = (mv.from_square(), mv.to_square(), mv.flag());
 
-        let Some(from_piece) = new_state.piece_at_square(mv.from) else {
+        let Some(from_piece) = new_state.piece_at_square(from) else {
             return Err(MoveError::MissingPiece);
         };
 
-        let mut special_move = None;
+        // Pieces are hashed as they're put and removed, everything else is swapped at the end
+        new_state.hash ^= zobrist::state_keys(self);
+
+        let special_move = flag.special_move();
 
         // Handle source-piece specific actions
         match from_piece {
-            Piece::Pawn(_) => {
-                // Check for promotion
-                if mv.to.rank() == Rank::One || mv.to.rank() == Rank::Eight {
-                    special_move = Some(SpecialMove::Promotion);
-                }
-
-                // Check for en passant
-                if let Some(mask) = new_state.en_passant_mask() {
-                    // Check if en passant mask equals move mask
-                    if mask == mv.to.mask() {
-                        special_move = Some(SpecialMove::EnPassant);
+            Piece::King(color) => {
+                // Moving the king loses both castling rights
+                match color {
+                    Color::White => {
+                        new_state.a1_rook_moved = true;
+                        new_state.h1_rook_moved = true;
+                    }
+                    Color::Black => {
+                        new_state.a8_rook_moved = true;
+                        new_state.h8_rook_moved = true;
                     }
                 }
             }
-            Piece::King(_) => {
-                // Check for castling by checking if king moved 2 squares horizontally
-                if mv.file_diff() == 2 {
-                    special_move = {
-                        // Check if king moved to the right or not
-                        let is_kingside = mv.to.file() > mv.from.file();
-
-                        if is_kingside {
-                            Some(SpecialMove::Castle(CastleDirection::Kingside))
-                        } else {
-                            Some(SpecialMove::Castle(CastleDirection::Queenside))
-                        }
-                    };
-                }
-            }
-            Piece::Rook(_) => match mv.from {
+            Piece::Rook(_) => match from {
                 Square::A1 => new_state.a1_rook_moved = true,
                 Square::A8 => new_state.a8_rook_moved = true,
                 Square::H1 => new_state.h1_rook_moved = true,
@@ -312,76 +388,10 @@ impl BoardState {
 
         // Handle target-square specific actions
 
-        // Update board state
-        let mut is_capture = false;
-
-        // Handle special moves/captures and normal captures separately
-        if let Some(special_move) = special_move {
-            match special_move {
-                SpecialMove::EnPassant => {
-                    is_capture = true;
-
-                    let rank = mv.to.rank();
-                    let file = mv.to.file();
-
-                    let offset_rank = match active_color {
-                        Color::White => rank.minus(1),
-                        Color::Black => rank.plus(1),
-                    }
-                    .unwrap();
-
-                    // Capture the pawn when en passant is played
-                    let enemy_pawns = match active_color {
-                        Color::White => &mut new_state.masks[Piece::BLACK_PAWN_INDEX],
-                        Color::Black => &mut new_state.masks[Piece::WHITE_PAWN_INDEX],
-                    };
-                    *enemy_pawns &= !(Square::from_coords(offset_rank, file)).mask();
-                }
-                SpecialMove::Castle(direction) => {
-                    let rook_mask = new_state.mask_mut(Piece::Rook(active_color));
-                    let start_square: Square;
-                    let end_square: Square;
-
-                    match direction {
-                        CastleDirection::Kingside => {
-                            start_square = match active_color {
-                                Color::White => Square::H1,
-                                Color::Black => Square::H8,
-                            };
-                            end_square = match active_color {
-                                Color::White => Square::F1,
-                                Color::Black => Square::F8,
-                            };
-                        }
-                        CastleDirection::Queenside => {
-                            start_square = match active_color {
-                                Color::White => Square::A1,
-                                Color::Black => Square::A8,
-                            };
-                            end_square = match active_color {
-                                Color::White => Square::D1,
-                                Color::Black => Square::D8,
-                            };
-                        }
-                    }
-
-                    *rook_mask &= !start_square.mask();
-                    *rook_mask |= end_square.mask();
-                }
-                SpecialMove::Promotion => {
-                    // Only allow promotion to a queen for now
-                    let promoted_piece_mask = new_state.mask_mut(Piece::Queen(active_color));
-                    *promoted_piece_mask |= mv.to.mask();
-
-                    let pawn_mask = new_state.mask_mut(from_piece);
-                    *pawn_mask &= !mv.from.mask();
-                }
-            }
-        } else if let Some(captured_piece) = new_state.piece_at_square(mv.to) {
-            is_capture = true;
-
-            if let Piece::Rook(_) = captured_piece {
-                match mv.to {
+        // Handle normal captures (including promotion captures) before special moves
+        if flag.is_capture() {
+            if let Some(Piece::Rook(_)) = new_state.remove_piece(to) {
+                match to {
                     // Castling rights
                     Square::A1 => new_state.a1_rook_moved = true,
                     Square::A8 => new_state.a8_rook_moved = true,
@@ -390,24 +400,64 @@ impl BoardState {
                     _ => (),
                 }
             }
+        }
+
+        match special_move {
+            Some(SpecialMove::EnPassant) => {
+                let rank = to.rank();
+                let file = to.file();
 
-            // Remove captured piece
-            let mask = new_state.mask_mut(captured_piece);
-            *mask &= !mv.to.mask();
+                let offset_rank = match active_color {
+                    Color::White => rank.minus(1),
+                    Color::Black => rank.plus(1),
+                }
+                .unwrap();
+
+                // Capture the pawn when en passant is played
+                new_state.remove_piece(Square::from_coords(offset_rank, file));
+            }
+            Some(SpecialMove::Castle(direction)) => {
+                let start_square: Square;
+                let end_square: Square;
+
+                match direction {
+                    CastleDirection::Kingside => {
+                        start_square = match active_color {
+                            Color::White => Square::H1,
+                            Color::Black => Square::H8,
+                        };
+                        end_square = match active_color {
+                            Color::White => Square::F1,
+                            Color::Black => Square::F8,
+                        };
+                    }
+                    CastleDirection::Queenside => {
+                        start_square = match active_color {
+                            Color::White => Square::A1,
+                            Color::Black => Square::A8,
+                        };
+                        end_square = match active_color {
+                            Color::White => Square::D1,
+                            Color::Black => Square::D8,
+                        };
+                    }
+                }
+
+                new_state.remove_piece(start_square);
+                new_state.put_piece(Piece::Rook(active_color), end_square);
+            }
+            Some(SpecialMove::Promotion) => {
+                new_state.remove_piece(from);
+                new_state.put_piece(flag.promotion(active_color).unwrap(), to);
+            }
+            None => (),
         }
 
         // Move piece
         // Movement is handled separately for promoting pawns
-        if let Some(special_move) = special_move {
-            if special_move != SpecialMove::Promotion {
-                let from_mask = new_state.mask_mut(from_piece);
-                *from_mask &= !mv.from.mask();
-                *from_mask |= mv.to.mask();
-            }
-        } else {
-            let from_mask = new_state.mask_mut(from_piece);
-            *from_mask &= !mv.from.mask();
-            *from_mask |= mv.to.mask();
+        if special_move != Some(SpecialMove::Promotion) {
+            new_state.remove_piece(from);
+            new_state.put_piece(from_piece, to);
         }
 
         // Update move counts
@@ -417,7 +467,7 @@ impl BoardState {
         new_state.halfmoves = match from_piece {
             Piece::Pawn(_) => 0,
             _ => {
-                if is_capture {
+                if flag.is_capture() {
                     0
                 } else {
                     new_state.halfmoves + 1
@@ -425,19 +475,35 @@ impl BoardState {
             }
         };
 
-        new_state.last_move = Some(mv);
+        new_state.last_move = Some(mv.to_move());
         new_state.swap_active_color();
+        new_state.hash ^= zobrist::state_keys(&new_state);
+
+        debug_assert!(new_state.is_consistent(), "{}", new_state.to_fen());
 
         Ok(new_state)
     }
 
-    pub fn make_move(
-        &self,
-        mv: Move,
-        sliding_moves: &SlidingMoves,
-    ) -> Result<BoardState, MoveError> {
+    /// Passes the turn to the other side without moving a piece. Used by null move pruning.
+    pub fn make_null_move(&self) -> BoardState {
+        let mut new_state = self.clone();
+
+        if new_state.active_color == Color::Black {
+            new_state.fullmoves += 1;
+        }
+
+        // Positions on either side of a null move must never count as repetitions
+        new_state.halfmoves = 0;
+        new_state.last_move = None;
+        new_state.swap_active_color();
+        new_state.hash ^= zobrist::state_keys(self) ^ zobrist::state_keys(&new_state);
+
+        new_state
+    }
+
+    pub fn make_move(&self, mv: Move) -> Result<BoardState, MoveError> {
         // Make sure move is in pseudolegal move mask
-        let possible_moves = self.get_pseudolegal_move_mask(mv.from, sliding_moves);
+        let possible_moves = self.get_pseudolegal_move_mask(mv.from);
         if possible_moves == Mask(0) {
             println!("No legal moves from that square");
             return Err(MoveError::IllegalMove);
@@ -468,22 +534,54 @@ impl BoardState {
         }
     }
 
-    fn piece_at_square(&self, square: Square) -> Option<Piece> {
-        for (i, mask) in self.masks.iter().enumerate() {
-            if mask.0 & 1 << square as u8 > 0 {
-                return Piece::from_mask_index(i);
+    pub fn piece_at_square(&self, square: Square) -> Option<Piece> {
+        self.mailbox[square as usize]
+    }
+
+    /// Puts a piece on an empty square, updating the masks and the mailbox.
+    fn put_piece(&mut self, piece: Piece, square: Square) {
+        self.masks[piece.to_mask_index()] |= square.mask();
+        self.occupancy[piece.color() as usize] |= square.mask();
+        self.mailbox[square as usize] = Some(piece);
+        self.hash ^= zobrist::PIECE_KEYS[piece.to_mask_index()][square as usize];
+    }
+
+    /// Takes the piece off a square, if there is one.
+    fn remove_piece(&mut self, square: Square) -> Option<Piece> {
+        let piece = self.mailbox[square as usize].take()?;
+        self.masks[piece.to_mask_index()] &= !square.mask();
+        self.occupancy[piece.color() as usize] &= !square.mask();
+        self.hash ^= zobrist::PIECE_KEYS[piece.to_mask_index()][square as usize];
+        Some(piece)
+    }
+
+    /// Whether the mailbox, occupancy masks and hash agree with the piece masks. Checked after
+    /// every move in debug builds.
+    pub fn is_consistent(&self) -> bool {
+        let mut masks = [Mask(0); 12];
+        let mut occupancy = [Mask(0); 2];
+
+        for (i, piece) in self.mailbox.iter().enumerate() {
+            if let Some(piece) = piece {
+                masks[piece.to_mask_index()] |= Mask(1 << i);
+                occupancy[piece.color() as usize] |= Mask(1 << i);
             }
         }
 
-        None
+        masks == self.masks && occupancy == self.occupancy && self.hash == zobrist::hash(self)
     }
 
-    fn mask_mut(&mut self, piece: Piece) -> &mut Mask {
-        &mut self.masks[piece.to_mask_index()]
+    pub fn mask(&self, piece: Piece) -> Mask {
+        self.masks[piece.to_mask_index()]
     }
 
-    fn mask(&self, piece: Piece) -> Mask {
-        self.masks[piece.to_mask_index()]
+    /// The mask at index `i` of the piece masks (see the `Piece::*_INDEX` constants).
+    pub fn mask_at(&self, i: usize) -> Mask {
+        self.masks[i]
+    }
+
+    pub fn active_color(&self) -> Color {
+        self.active_color
     }
 
     fn swap_active_color(&mut self) {
@@ -493,33 +591,77 @@ impl BoardState {
         };
     }
 
-    fn clear_pieces(&mut self) {
-        for mask in self.masks.iter_mut() {
-            mask.0 = 0
+    pub fn halfmoves(&self) -> u8 {
+        self.halfmoves
+    }
+
+    /// White kingside, white queenside, black kingside and black queenside castling rights.
+    pub fn castling_rights(&self) -> [bool; 4] {
+        [
+            !self.h1_rook_moved,
+            !self.a1_rook_moved,
+            !self.h8_rook_moved,
+            !self.a8_rook_moved,
+        ]
+    }
+
+    /// Whether both states have the same pieces, side to move and castling rights, as required
+    /// for a position to count as repeated.
+    pub fn same_position(&self, other: &BoardState) -> bool {
+        self.masks == other.masks
+            && self.active_color == other.active_color
+            && self.a1_rook_moved == other.a1_rook_moved
+            && self.h1_rook_moved == other.h1_rook_moved
+            && self.a8_rook_moved == other.a8_rook_moved
+            && self.h8_rook_moved == other.h8_rook_moved
+    }
+
+    /// Zobrist hash of the position.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Neither side has enough material left to checkmate (bare kings, or a single minor piece
+    /// against a bare king).
+    pub fn insufficient_material(&self) -> bool {
+        let heavy_pieces_or_pawns = [Piece::Pawn, Piece::Rook, Piece::Queen]
+            .into_iter()
+            .flat_map(|piece| [piece(Color::White), piece(Color::Black)])
+            .any(|piece| self.mask(piece) != Mask(0));
+
+        if heavy_pieces_or_pawns {
+            return false;
         }
+
+        let minor_pieces = [Piece::Knight, Piece::Bishop]
+            .into_iter()
+            .flat_map(|piece| [piece(Color::White), piece(Color::Black)])
+            .map(|piece| self.mask(piece).count())
+            .sum::<u32>();
+
+        minor_pieces <= 1
+    }
+
+    fn clear_pieces(&mut self) {
+        self.masks = [Mask(0); 12];
+        self.mailbox = [None; 64];
+        self.occupancy = [Mask(0); 2];
+        self.hash = zobrist::hash(self);
     }
 
-    fn all_pieces_mask(&self) -> Mask {
+    pub fn all_pieces_mask(&self) -> Mask {
         self.white_pieces_mask() | self.black_pieces_mask()
     }
 
     fn black_pieces_mask(&self) -> Mask {
-        let mut mask = Mask(0);
-        for m in self.black_pieces() {
-            mask |= *m;
-        }
-        mask
+        self.occupancy[Color::Black as usize]
     }
 
     fn white_pieces_mask(&self) -> Mask {
-        let mut mask = Mask(0);
-        for m in self.white_pieces() {
-            mask |= *m;
-        }
-        mask
+        self.occupancy[Color::White as usize]
     }
 
-    fn friendly_pieces_mask(&self, color: Color) -> Mask {
+    pub fn friendly_pieces_mask(&self, color: Color) -> Mask {
         match color {
             Color::White => self.white_pieces_mask(),
             Color::Black => self.black_pieces_mask(),
@@ -533,6 +675,17 @@ impl BoardState {
         }
     }
 
+    /// Whether `color` has any pieces besides pawns and the king. Positions where this isn't
+    /// the case are prone to zugzwang.
+    pub fn has_non_pawn_material(&self, color: Color) -> bool {
+        let pieces = self.mask(Piece::Knight(color))
+            | self.mask(Piece::Bishop(color))
+            | self.mask(Piece::Rook(color))
+            | self.mask(Piece::Queen(color));
+
+        pieces != Mask(0)
+    }
+
     pub fn possible_en_passant(&self) -> bool {
         let active_color = self.active_color;
         let Some(last_move) = self.last_move else {
@@ -578,48 +731,35 @@ impl BoardState {
         Some(Square::from_coords(capture_rank, capture_file).mask())
     }
 
-    pub fn is_move_legal(&self, mv: Move, sliding_moves: &SlidingMoves) -> bool {
+    pub fn is_move_legal(&self, mv: Move) -> bool {
+        self.is_packed_move_legal(PackedMove::from_move(mv, self))
+    }
+
+    pub fn is_packed_move_legal(&self, mv: PackedMove) -> bool {
         // Prevent piece from moving to itself
-        if mv.from == mv.to {
+        if mv.from_square() == mv.to_square() {
             return false;
         }
 
         // Play move temporarily (also ensures it can be played)
-        let Ok(mut potential_state) = self.make_move_unchecked(mv) else {
+        let Ok(mut potential_state) = self.make_packed_move(mv) else {
             return false;
         };
 
-        // Make sure move doesn't leave king in check
-        let Some(king_square) = Square::from_mask(self.mask(Piece::King(self.active_color))) else {
+        // Make sure move doesn't leave king in check (the king may have been the piece that moved)
+        let Some(king_square) =
+            Square::from_mask(potential_state.mask(Piece::King(self.active_color)))
+        else {
             return false;
         };
-        if potential_state.attacked_by(king_square, potential_state.active_color, sliding_moves) {
+        if potential_state.attacked_by(king_square, potential_state.active_color) {
             return false;
         }
 
         true
     }
 
-    fn move_masks(&self, piece: Piece) -> Vec<Mask> {
-        match piece {
-            Piece::Pawn(color) => match color {
-                Color::White => Vec::from_iter(WHITE_PAWN_MOVE_MASKS),
-                Color::Black => Vec::from_iter(BLACK_PAWN_MOVE_MASKS),
-            },
-            Piece::Knight(_) => Vec::from_iter(KNIGHT_MOVE_MASKS),
-            Piece::Bishop(_) => Vec::from_iter(BISHOP_MOVE_MASKS),
-            Piece::King(_) => Vec::from_iter(KING_MOVE_MASKS),
-            Piece::Rook(_) => Vec::from_iter(ROOK_MOVE_MASKS),
-            Piece::Queen(_) => Vec::from_iter([ROOK_MOVE_MASKS, BISHOP_MOVE_MASKS].concat()),
-        }
-    }
-
-    pub fn can_castle(
-        &self,
-        color: Color,
-        direction: CastleDirection,
-        sliding_moves: &SlidingMoves,
-    ) -> bool {
+    pub fn can_castle(&self, color: Color, direction: CastleDirection) -> bool {
         const WHITE_BLOCKERS_SHORT: &[Square] = &[Square::F1, Square::G1];
         const WHITE_BLOCKERS_LONG: &[Square] = &[Square::D1, Square::C1, Square::B1];
         const BLACK_BLOCKERS_SHORT: &[Square] = &[Square::F8, Square::G8];
@@ -632,8 +772,22 @@ impl BoardState {
 
         let enemy_color = color.swapped();
 
+        // Check castling rights
+        let (rook_moved, rook_square) = match (color, direction) {
+            (Color::White, CastleDirection::Kingside) => (self.h1_rook_moved, Square::H1),
+            (Color::White, CastleDirection::Queenside) => (self.a1_rook_moved, Square::A1),
+            (Color::Black, CastleDirection::Kingside) => (self.h8_rook_moved, Square::H8),
+            (Color::Black, CastleDirection::Queenside) => (self.a8_rook_moved, Square::A8),
+        };
+        if rook_moved
+            || self.mask(Piece::King(color)) & king_square.mask() == Mask(0)
+            || self.mask(Piece::Rook(color)) & rook_square.mask() == Mask(0)
+        {
+            return false;
+        }
+
         // Check if king in check
-        if self.attacked_by(king_square, enemy_color, sliding_moves) {
+        if self.attacked_by(king_square, enemy_color) {
             return false;
         }
 
@@ -657,7 +811,7 @@ impl BoardState {
 
         // Check for checks in the way of the king's path
         for blocker_square in &relevant_blockers[..2] {
-            if self.attacked_by(*blocker_square, enemy_color, sliding_moves) {
+            if self.attacked_by(*blocker_square, enemy_color) {
                 return false;
             };
         }
@@ -665,22 +819,26 @@ impl BoardState {
         true
     }
 
-    pub fn in_check(&self, color: Color, sliding_moves: &SlidingMoves) -> bool {
-        let king_mask = self.mask(Piece::King(color));
-        let king_square: Square = king_mask.ones()[0];
+    pub fn in_check(&self, color: Color) -> bool {
+        let Some(king_square) = Square::from_mask(self.mask(Piece::King(color))) else {
+            return false;
+        };
 
-        self.attacked_by(king_square, color, sliding_moves)
+        self.attacked_by(king_square, color.swapped())
     }
 
-    pub fn attacked_by(&self, square: Square, color: Color, sliding_moves: &SlidingMoves) -> bool {
+    pub fn attacked_by(&self, square: Square, color: Color) -> bool {
         let square_index = square as usize;
 
-        let pawn_mask = self.mask(Piece::Pawn(color));
-        let pawn_attacks = match color {
-            Color::White => &WHITE_PAWN_CAPTURE_MASKS,
-            Color::Black => &BLACK_PAWN_CAPTURE_MASKS,
+        // Squares a pawn of `color` would have to stand on to attack `square`
+        const NOT_A_FILE: u64 = !0x0101010101010101;
+        const NOT_H_FILE: u64 = !0x8080808080808080;
+        let target = square.mask().0;
+        let pawn_sources = match color {
+            Color::White => ((target >> 7) & NOT_A_FILE) | ((target >> 9) & NOT_H_FILE),
+            Color::Black => ((target << 7) & NOT_H_FILE) | ((target << 9) & NOT_A_FILE),
         };
-        if (pawn_attacks[square_index] & pawn_mask).0 > 0 {
+        if (Mask(pawn_sources) & self.mask(Piece::Pawn(color))).0 > 0 {
             return true;
         }
 
@@ -694,6 +852,7 @@ impl BoardState {
             return true;
         }
 
+        let sliding_moves = SlidingMoves::get();
         let rooks_queens = self.mask(Piece::Rook(color)) | self.mask(Piece::Queen(color));
         if (sliding_moves.get_rook_moves(square, self.all_pieces_mask()) & rooks_queens).0 > 0 {
             return true;
@@ -707,24 +866,28 @@ impl BoardState {
         return false;
     }
 
-    pub fn get_pseudolegal_move_mask(&self, square: Square, sliding_moves: &SlidingMoves) -> Mask {
-        let blockers = self.all_pieces_mask();
-
+    pub fn get_pseudolegal_move_mask(&self, square: Square) -> Mask {
         let Some(piece) = self.piece_at_square(square) else {
             return Mask(0);
         };
-        let color = piece.color();
 
         // Prevent moving pieces of the wrong colour
-        if color != self.active_color {
+        if piece.color() != self.active_color {
             return Mask(0);
         }
 
-        let mask = self.mask(piece);
+        self.piece_move_mask(piece, square)
+    }
+
+    /// Pseudolegal moves for `piece` standing on `square`, regardless of whose turn it is.
+    pub fn piece_move_mask(&self, piece: Piece, square: Square) -> Mask {
+        let blockers = self.all_pieces_mask();
+        let color = piece.color();
 
         let mut move_mask: Mask;
 
         if piece.is_slider() {
+            let sliding_moves = SlidingMoves::get();
             move_mask = Mask(0);
 
             // Rook moves
@@ -744,7 +907,15 @@ impl BoardState {
             }
         } else {
             // Grab move mask for the piece at the current square
-            move_mask = self.move_masks(piece)[square.to_shift()];
+            move_mask = match piece {
+                Piece::Pawn(Color::White) => WHITE_PAWN_MOVE_MASKS[square.to_shift()],
+                Piece::Pawn(Color::Black) => BLACK_PAWN_MOVE_MASKS[square.to_shift()],
+                Piece::Knight(_) => KNIGHT_MOVE_MASKS[square.to_shift()],
+                Piece::King(_) => KING_MOVE_MASKS[square.to_shift()],
+                Piece::Bishop(_) | Piece::Rook(_) | Piece::Queen(_) => {
+                    unreachable!("sliders are handled above")
+                }
+            };
 
             // Special moves
             match piece {
@@ -765,6 +936,9 @@ impl BoardState {
                         }
                     }
 
+                    // Pawns can't capture forwards
+                    move_mask &= !blockers;
+
                     // Handle pawn captures and en passant
                     let capture_mask = match color {
                         Color::White => WHITE_PAWN_CAPTURE_MASKS[square.to_shift()],
@@ -782,7 +956,7 @@ impl BoardState {
                 }
                 Piece::King(_) => {
                     // Kingside castling
-                    if self.can_castle(color, CastleDirection::Kingside, sliding_moves) {
+                    if self.can_castle(color, CastleDirection::Kingside) {
                         move_mask |= match color {
                             Color::White => Square::G1,
                             Color::Black => Square::G8,
@@ -791,7 +965,7 @@ impl BoardState {
                     }
 
                     // Queenside castling
-                    if self.can_castle(color, CastleDirection::Queenside, sliding_moves) {
+                    if self.can_castle(color, CastleDirection::Queenside) {
                         move_mask |= match color {
                             Color::White => Square::C1,
                             Color::Black => Square::C8,
@@ -809,19 +983,56 @@ impl BoardState {
         move_mask
     }
 
-    pub fn get_pseudolegal_moves(&self, square: Square, sliding_moves: &SlidingMoves) -> Vec<Move> {
-        let move_mask = self.get_pseudolegal_move_mask(square, sliding_moves);
-        Move::from_move_mask(square, move_mask)
+    pub fn legal_moves(&self) -> MoveList {
+        let mut moves = MoveList::new();
+
+        for square in self.friendly_pieces_mask(self.active_color).iter() {
+            self.push_pseudolegal_moves(square, &mut moves);
+        }
+
+        moves.retain(|mv| self.is_packed_move_legal(*mv));
+        moves
+    }
+
+    pub fn get_pseudolegal_moves(&self, square: Square) -> MoveList {
+        let mut moves = MoveList::new();
+        self.push_pseudolegal_moves(square, &mut moves);
+        moves
     }
 
-    pub fn print_debugging_information(&self) {
-        let sliding_moves = SlidingMoves::init();
+    /// Adds the pseudolegal moves from `square` to `moves`, with their flags set.
+    fn push_pseudolegal_moves(&self, square: Square, moves: &mut MoveList) {
+        let Some(piece) = self.piece_at_square(square) else {
+            return;
+        };
+        let move_mask = self.get_pseudolegal_move_mask(square);
+
+        let enemies = self.enemy_pieces_mask(piece.color());
+        let en_passant = match piece {
+            Piece::Pawn(_) => self.en_passant_mask(),
+            _ => None,
+        };
+
+        for to in move_mask.iter() {
+            let is_capture = enemies.contains(to);
+            let flag = MoveFlag::for_move(piece, square, to, is_capture, en_passant, None);
+
+            if flag.is_promotion() {
+                for promotion in Promotion::ALL {
+                    let flag = MoveFlag::promotion_to(promotion, is_capture);
+                    moves.push(PackedMove::new(square, to, flag));
+                }
+            } else {
+                moves.push(PackedMove::new(square, to, flag));
+            }
+        }
+    }
 
+    pub fn print_debugging_information(&self) {
         for i in 0..64 {
             let square = Square::from_u8(i).unwrap();
-            println!("Pseudolegal moves from {}: ", square.to_string());
-            self.get_pseudolegal_move_mask(square, &sliding_moves)
-                .print();
+            println!("Pseudolegal moves from {square}: ");
+            self.get_pseudolegal_move_mask(square).print();
             println!();
         }
     }
@@ -830,20 +1041,35 @@ impl BoardState {
 #[derive(Debug)]
 pub struct BoardInitError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Board {
     // Board state and state history
     states: Vec<BoardState>,
 
-    // Sliding piece magic bitboard helper struct
-    sliding_moves: SlidingMoves,
+    // Weights of the handcrafted evaluation
+    weights: Weights,
+
+    // Optional endgame tablebases, shared read-only between clones of the board
+    tablebases: Option<Arc<dyn Probe>>,
+
+    // Optional neural network, plus its accumulator for each state in `states`
+    #[cfg(feature = "nnue")]
+    network: Option<Arc<Network>>,
+    #[cfg(feature = "nnue")]
+    accumulators: Vec<Accumulator>,
 }
 
 impl Board {
     pub fn new(fen: &str) -> Result<Self, FenError> {
         let mut board = Board {
             states: Vec::new(),
-            sliding_moves: SlidingMoves::init(),
+            weights: WEIGHTS.clone(),
+            tablebases: None,
+
+            #[cfg(feature = "nnue")]
+            network: None,
+            #[cfg(feature = "nnue")]
+            accumulators: Vec::new(),
         };
 
         board.load_from_fen(fen)?;
@@ -861,44 +1087,62 @@ impl Board {
         self.states.clear();
         self.states.push(state);
 
+        #[cfg(feature = "nnue")]
+        self.refresh_accumulators();
+
         Ok(())
     }
 
-    pub fn legal_moves(&self) -> Vec<Move> {
-        let mut legal_moves = Vec::new();
-        let state = self.current_position();
-
-        for i in 0..64 {
-            let square = Square::from_usize(i).unwrap();
-            let pseudolegal_moves = state.get_pseudolegal_moves(square, &self.sliding_moves);
-
-            for mv in pseudolegal_moves {
-                if self.is_move_legal(mv) {
-                    legal_moves.push(mv);
-                }
-            }
-        }
-
-        legal_moves
+    pub fn legal_moves(&self) -> MoveList {
+        self.current_position().legal_moves()
     }
 
     pub fn make_move_unchecked(&mut self, mv: Move) -> Result<(), MoveError> {
         let old_state = self.current_position();
         let new_state = old_state.make_move_unchecked(mv)?;
-        self.states.push(new_state);
+        self.push_state(new_state);
         Ok(())
     }
 
+    pub fn make_packed_move(&mut self, mv: PackedMove) -> Result<(), MoveError> {
+        let new_state = self.current_position().make_packed_move(mv)?;
+        self.push_state(new_state);
+        Ok(())
+    }
+
+    pub fn make_null_move(&mut self) {
+        let new_state = self.current_position().make_null_move();
+        self.push_state(new_state);
+    }
+
     pub fn make_move(&mut self, mv: Move) -> Result<(), MoveError> {
         let old_state = self.current_position();
-        let new_state = old_state.make_move(mv, &self.sliding_moves)?;
-        self.states.push(new_state);
+        let new_state = old_state.make_move(mv)?;
+        self.push_state(new_state);
         Ok(())
     }
 
+    fn push_state(&mut self, state: BoardState) {
+        #[cfg(feature = "nnue")]
+        if let Some(network) = &self.network {
+            let accumulator =
+                self.accumulators
+                    .last()
+                    .unwrap()
+                    .update(network, self.current_position(), &state);
+            self.accumulators.push(accumulator);
+        }
+
+        self.states.push(state);
+    }
+
     pub fn unmake_move(&mut self) -> Result<(), MoveError> {
         if self.states.len() > 1 {
             self.states.pop();
+
+            #[cfg(feature = "nnue")]
+            self.accumulators.pop();
+
             Ok(())
         } else {
             Err(MoveError::NoPreviousMoves)
@@ -906,8 +1150,79 @@ impl Board {
     }
 
     pub fn is_move_legal(&self, mv: Move) -> bool {
-        self.current_position()
-            .is_move_legal(mv, &self.sliding_moves)
+        self.current_position().is_move_legal(mv)
+    }
+
+    pub fn in_check(&self) -> bool {
+        let state = self.current_position();
+        state.in_check(state.active_color)
+    }
+
+    /// Whether the current position is drawn by the fifty move rule, repetition or insufficient
+    /// material.
+    pub fn is_draw(&self) -> bool {
+        let state = self.current_position();
+
+        state.halfmoves >= 100 || state.insufficient_material() || self.is_repetition()
+    }
+
+    /// Whether the current position has occurred before. A single repetition is treated as a
+    /// draw, which is enough for searching.
+    pub fn is_repetition(&self) -> bool {
+        let current = self.current_position();
+
+        // Positions can't repeat across captures or pawn moves
+        self.states
+            .iter()
+            .rev()
+            .take(current.halfmoves as usize + 1)
+            .skip(2)
+            .step_by(2)
+            .any(|state| state.same_position(current))
+    }
+
+    /// Static evaluation of the current position from the side to move's point of view.
+    pub fn evaluate(&self) -> Score {
+        #[cfg(feature = "nnue")]
+        if let Some(network) = &self.network {
+            let accumulator = self.accumulators.last().unwrap();
+            return network.evaluate(accumulator, self.current_position().active_color());
+        }
+
+        eval::evaluate_with(self.current_position(), &self.weights)
+    }
+
+    /// Replaces the weights used by the handcrafted evaluation.
+    pub fn set_weights(&mut self, weights: Weights) {
+        self.weights = weights;
+    }
+
+    /// Uses `tablebases` to look up endgame results, or stops using them if it's `None`.
+    pub fn set_tablebases(&mut self, tablebases: Option<Arc<dyn Probe>>) {
+        self.tablebases = tablebases;
+    }
+
+    pub fn tablebases(&self) -> Option<Arc<dyn Probe>> {
+        self.tablebases.clone()
+    }
+
+    /// Evaluates positions with `network` instead of the handcrafted evaluation, or switches back
+    /// to the handcrafted evaluation if `network` is `None`.
+    #[cfg(feature = "nnue")]
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.network = network;
+        self.refresh_accumulators();
+    }
+
+    #[cfg(feature = "nnue")]
+    fn refresh_accumulators(&mut self) {
+        self.accumulators.clear();
+
+        if let Some(network) = &self.network {
+            for state in &self.states {
+                self.accumulators.push(Accumulator::new(network, state));
+            }
+        }
     }
 }
 
@@ -943,53 +1258,35 @@ mod board_tests {
         let mut board = Board::new(START_FEN).unwrap();
 
         board
-            .make_move_unchecked(Move {
-                from: Square::E2,
-                to: Square::E5,
-            })
+            .make_move_unchecked(Move::new(Square::E2, Square::E5))
             .unwrap();
 
         board
-            .make_move_unchecked(Move {
-                from: Square::D7,
-                to: Square::D5,
-            })
+            .make_move_unchecked(Move::new(Square::D7, Square::D5))
             .unwrap();
 
-        assert!(board.is_move_legal(Move {
-            from: Square::E5,
-            to: Square::D6,
-        }));
+        assert!(board.is_move_legal(Move::new(Square::E5, Square::D6)));
     }
 
     #[test]
     fn en_passant_mask() {
         let mut board = Board::new(START_FEN).unwrap();
 
-        let _ = board.make_move_unchecked(Move {
-            from: Square::E2,
-            to: Square::E4,
-        });
+        let _ = board.make_move_unchecked(Move::new(Square::E2, Square::E4));
 
         assert_eq!(
             board.current_position().en_passant_mask(),
             Some(Square::E3.mask())
         );
 
-        let _ = board.make_move_unchecked(Move {
-            from: Square::E7,
-            to: Square::E5,
-        });
+        let _ = board.make_move_unchecked(Move::new(Square::E7, Square::E5));
 
         assert_eq!(
             board.current_position().en_passant_mask(),
             Some(Square::E6.mask())
         );
 
-        let _ = board.make_move_unchecked(Move {
-            from: Square::G1,
-            to: Square::F3,
-        });
+        let _ = board.make_move_unchecked(Move::new(Square::G1, Square::F3));
 
         assert_eq!(board.current_position().en_passant_mask(), None);
     }
@@ -1009,6 +1306,114 @@ mod board_tests {
         assert!(board.is_move_legal(Move::from_long_algebraic("e1c1").unwrap()));
     }
 
+    #[test]
+    fn fen_round_trip() {
+        const KIWIPETE_FEN: &str =
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+        for fen in [START_FEN, TEST_POSITION_FEN, KIWIPETE_FEN] {
+            assert_eq!(BoardState::from_fen(fen).unwrap().to_fen(), fen);
+        }
+
+        let mut board = Board::new(START_FEN).unwrap();
+        board
+            .make_move(Move::from_long_algebraic("e2e4").unwrap())
+            .unwrap();
+        assert_eq!(
+            board.current_position().to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+    }
+
+    #[test]
+    fn repetition_is_draw() {
+        let mut board = Board::new(START_FEN).unwrap();
+
+        for mv in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            assert!(!board.is_draw());
+            board
+                .make_move(Move::from_long_algebraic(mv).unwrap())
+                .unwrap();
+        }
+
+        assert!(board.is_draw());
+    }
+
+    #[test]
+    fn mailbox_stays_in_sync() {
+        // En passant, castling both ways, a promotion capture and a plain capture
+        let mut board = Board::new("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        for mv in ["e5d6", "e8g8", "b7a8", "f8a8", "e1c1"] {
+            board
+                .make_move_unchecked(Move::from_long_algebraic(mv).unwrap())
+                .unwrap();
+
+            let state = board.current_position();
+            assert!(state.is_consistent());
+            assert_eq!(
+                state.all_pieces_mask().count() as usize,
+                state.mailbox.iter().flatten().count()
+            );
+        }
+
+        let state = board.current_position();
+        assert_eq!(
+            state.piece_at_square(Square::A8),
+            Some(Piece::Rook(Color::Black))
+        );
+        assert_eq!(
+            state.piece_at_square(Square::D6),
+            Some(Piece::Pawn(Color::White))
+        );
+        assert_eq!(state.piece_at_square(Square::D5), None);
+        assert_eq!(
+            state.piece_at_square(Square::C1),
+            Some(Piece::King(Color::White))
+        );
+        assert_eq!(
+            state.piece_at_square(Square::D1),
+            Some(Piece::Rook(Color::White))
+        );
+        assert_eq!(
+            state.piece_at_square(Square::G8),
+            Some(Piece::King(Color::Black))
+        );
+
+        let mut broken = state.clone();
+        broken.masks[Piece::WHITE_KING_INDEX] = Mask(0);
+        assert!(!broken.is_consistent());
+    }
+
+    #[test]
+    fn generates_move_flags() {
+        let board = Board::new("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let moves = board.legal_moves();
+        let flag = |mv| {
+            let mv = Move::from_long_algebraic(mv).unwrap();
+            moves
+                .iter()
+                .find(|packed| packed.to_move() == mv)
+                .map(|mv| mv.flag())
+        };
+
+        assert_eq!(flag("e1g1"), Some(MoveFlag::KingCastle));
+        assert_eq!(flag("e1c1"), Some(MoveFlag::QueenCastle));
+        assert_eq!(flag("e5d6"), Some(MoveFlag::EnPassant));
+        assert_eq!(flag("b7b8q"), Some(MoveFlag::QueenPromotion));
+        assert_eq!(flag("b7b8n"), Some(MoveFlag::KnightPromotion));
+        assert_eq!(flag("b7a8q"), Some(MoveFlag::QueenPromotionCapture));
+        assert_eq!(flag("b7a8b"), Some(MoveFlag::BishopPromotionCapture));
+        assert_eq!(flag("b7b8"), None);
+        assert_eq!(flag("a1a8"), Some(MoveFlag::Capture));
+        assert_eq!(flag("h1h5"), Some(MoveFlag::Quiet));
+
+        // Generated moves are packed the same way as moves given as input
+        let state = board.current_position();
+        for mv in &moves {
+            assert_eq!(PackedMove::from_move(mv.to_move(), state), *mv);
+        }
+    }
+
     #[test]
     fn excessive_moves() {
         const TEST_POS_FEN: &str = "rnbqk2r/ppppbppp/4pn2/8/3P1B2/2N5/PPPQPPPP/R3KBNR b KQkq - 3 4";
@@ -1,42 +1,467 @@
-use crate::board::{mask::Mask, square::Square};
+use std::ops::{Deref, DerefMut};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use crate::{
+    board::{
+        mask::Mask,
+        piece::{Color, Piece},
+        square::{Rank, Square},
+        BoardState, CastleDirection, SpecialMove,
+    },
+    eval::Score,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Move {
     pub from: Square,
     pub to: Square,
+    /// The piece a pawn promotes to, if this is a promotion
+    pub promotion: Option<Promotion>,
 }
 
 impl Move {
+    pub fn new(from: Square, to: Square) -> Move {
+        Move {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+
+    /// Parses a move like `e2e4`, or `e7e8n` for a promotion.
     pub fn from_long_algebraic(input: &str) -> Option<Move> {
+        let mut suffix = input.get(4..)?.chars();
+        let promotion = match suffix.next() {
+            Some(ch) => Some(Promotion::from_char(ch)?),
+            None => None,
+        };
+        if suffix.next().is_some() {
+            return None;
+        }
+
         Some(Move {
-            from: Square::from_str(&input[..2])?,
-            to: Square::from_str(&input[2..])?,
+            from: Square::from_str(input.get(..2)?)?,
+            to: Square::from_str(input.get(2..4)?)?,
+            promotion,
         })
     }
 
     pub fn long_algebraic(&self) -> String {
-        let first_algebraic = self.from.to_string();
-        let second_algebraic = self.to.to_string();
-        format!("{}{}", first_algebraic, second_algebraic)
+        self.to_string()
+    }
+
+    pub fn rank_diff(&self) -> u8 {
+        self.to.rank().diff(self.from.rank())
+    }
+
+    pub fn file_diff(&self) -> u8 {
+        self.to.file().diff(self.from.file())
+    }
+}
+
+impl std::fmt::Display for Move {
+    /// The move in long algebraic notation, such as `e2e4`, or `e7e8q` for a promotion.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+
+        if let Some(promotion) = self.promotion {
+            write!(f, "{}", promotion.to_char())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The piece a pawn promotes to, numbered as in the lowest two bits of a promotion `MoveFlag`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Promotion {
+    Knight = 0,
+    Bishop = 1,
+    Rook = 2,
+    Queen = 3,
+}
+
+impl Promotion {
+    pub const ALL: [Promotion; 4] = [Self::Queen, Self::Knight, Self::Rook, Self::Bishop];
+
+    /// Parses the lowercase letter used in UCI moves.
+    pub fn from_char(ch: char) -> Option<Self> {
+        match ch {
+            'n' => Some(Self::Knight),
+            'b' => Some(Self::Bishop),
+            'r' => Some(Self::Rook),
+            'q' => Some(Self::Queen),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        self.piece(Color::Black).to_char()
+    }
+
+    pub fn piece(self, color: Color) -> Piece {
+        match self {
+            Self::Knight => Piece::Knight(color),
+            Self::Bishop => Piece::Bishop(color),
+            Self::Rook => Piece::Rook(color),
+            Self::Queen => Piece::Queen(color),
+        }
+    }
+}
+
+/// What kind of move a `PackedMove` is. Captures have the third bit set and promotions the
+/// fourth, with the promoted piece in the lowest two bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveFlag {
+    Quiet = 0,
+    DoublePush = 1,
+    KingCastle = 2,
+    QueenCastle = 3,
+    Capture = 4,
+    EnPassant = 5,
+    KnightPromotion = 8,
+    BishopPromotion = 9,
+    RookPromotion = 10,
+    QueenPromotion = 11,
+    KnightPromotionCapture = 12,
+    BishopPromotionCapture = 13,
+    RookPromotionCapture = 14,
+    QueenPromotionCapture = 15,
+}
+
+impl MoveFlag {
+    pub fn from_u8(val: u8) -> Option<Self> {
+        match val {
+            0 => Some(Self::Quiet),
+            1 => Some(Self::DoublePush),
+            2 => Some(Self::KingCastle),
+            3 => Some(Self::QueenCastle),
+            4 => Some(Self::Capture),
+            5 => Some(Self::EnPassant),
+            8 => Some(Self::KnightPromotion),
+            9 => Some(Self::BishopPromotion),
+            10 => Some(Self::RookPromotion),
+            11 => Some(Self::QueenPromotion),
+            12 => Some(Self::KnightPromotionCapture),
+            13 => Some(Self::BishopPromotionCapture),
+            14 => Some(Self::RookPromotionCapture),
+            15 => Some(Self::QueenPromotionCapture),
+            _ => None,
+        }
+    }
+
+    /// The flag of a move by `piece`, given whether it lands on an enemy piece, the en passant
+    /// square, if any, and the piece promoted to, which is a queen unless given.
+    pub fn for_move(
+        piece: Piece,
+        from: Square,
+        to: Square,
+        is_capture: bool,
+        en_passant: Option<Mask>,
+        promotion: Option<Promotion>,
+    ) -> Self {
+        match piece {
+            Piece::Pawn(_) if to.rank() == Rank::One || to.rank() == Rank::Eight => {
+                Self::promotion_to(promotion.unwrap_or(Promotion::Queen), is_capture)
+            }
+            Piece::Pawn(_) if en_passant == Some(to.mask()) => Self::EnPassant,
+            Piece::Pawn(_) if to.rank().diff(from.rank()) == 2 => Self::DoublePush,
+            Piece::King(_) if to.file().diff(from.file()) == 2 => match to.file() > from.file() {
+                true => Self::KingCastle,
+                false => Self::QueenCastle,
+            },
+            _ if is_capture => Self::Capture,
+            _ => Self::Quiet,
+        }
+    }
+
+    /// The flag of a promotion to `promotion`.
+    pub fn promotion_to(promotion: Promotion, is_capture: bool) -> Self {
+        Self::from_u8(0b1000 | (is_capture as u8) << 2 | promotion as u8).unwrap()
+    }
+
+    /// Whether a piece is taken, en passant included.
+    pub fn is_capture(self) -> bool {
+        self as u8 & 0b100 != 0
+    }
+
+    pub fn is_promotion(self) -> bool {
+        self as u8 & 0b1000 != 0
+    }
+
+    /// Whether this is a promotion to anything but a queen.
+    pub fn is_underpromotion(self) -> bool {
+        self.promotion_kind()
+            .is_some_and(|promotion| promotion != Promotion::Queen)
+    }
+
+    /// The piece a pawn promotes to, if this is a promotion.
+    pub fn promotion_kind(self) -> Option<Promotion> {
+        if !self.is_promotion() {
+            return None;
+        }
+
+        Some(match self as u8 & 0b11 {
+            0 => Promotion::Knight,
+            1 => Promotion::Bishop,
+            2 => Promotion::Rook,
+            _ => Promotion::Queen,
+        })
+    }
+
+    /// The piece a pawn of `color` promotes to, if this is a promotion.
+    pub fn promotion(self, color: Color) -> Option<Piece> {
+        self.promotion_kind()
+            .map(|promotion| promotion.piece(color))
+    }
+
+    pub fn special_move(self) -> Option<SpecialMove> {
+        match self {
+            Self::KingCastle => Some(SpecialMove::Castle(CastleDirection::Kingside)),
+            Self::QueenCastle => Some(SpecialMove::Castle(CastleDirection::Queenside)),
+            Self::EnPassant => Some(SpecialMove::EnPassant),
+            _ if self.is_promotion() => Some(SpecialMove::Promotion),
+            _ => None,
+        }
+    }
+}
+
+/// A move packed into 16 bits: the from square (6 bits), the to square (6 bits) and a
+/// `MoveFlag` (4 bits). Unlike `Move`, it knows what kind of move it is without a board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedMove(u16);
+
+impl PackedMove {
+    pub fn new(from: Square, to: Square, flag: MoveFlag) -> Self {
+        Self(from as u16 | (to as u16) << 6 | (flag as u16) << 12)
+    }
+
+    /// Unpacks the bits of `PackedMove::bits`, or `None` if they hold an unused flag.
+    pub fn from_bits(bits: u16) -> Option<Self> {
+        MoveFlag::from_u8((bits >> 12) as u8)?;
+        Some(Self(bits))
+    }
+
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn from_square(self) -> Square {
+        Square::from_u8((self.0 & 0x3f) as u8).unwrap()
+    }
+
+    pub fn to_square(self) -> Square {
+        Square::from_u8((self.0 >> 6 & 0x3f) as u8).unwrap()
+    }
+
+    pub fn flag(self) -> MoveFlag {
+        MoveFlag::from_u8((self.0 >> 12) as u8).unwrap()
+    }
+
+    /// Packs a move given as input, such as a UCI move, along with its flag, worked out from the
+    /// position it's played in. Pawns promote to a queen unless the move says otherwise.
+    /// Generated moves come with their flags already set, so this is only needed for moves from
+    /// outside.
+    pub fn from_move(mv: Move, state: &BoardState) -> Self {
+        let flag = match state.piece_at_square(mv.from) {
+            Some(piece) => MoveFlag::for_move(
+                piece,
+                mv.from,
+                mv.to,
+                state.piece_at_square(mv.to).is_some(),
+                state.en_passant_mask(),
+                mv.promotion,
+            ),
+            None => MoveFlag::Quiet,
+        };
+
+        Self::new(mv.from, mv.to, flag)
+    }
+
+    pub fn to_move(self) -> Move {
+        Move {
+            from: self.from_square(),
+            to: self.to_square(),
+            promotion: self.flag().promotion_kind(),
+        }
+    }
+}
+
+impl From<PackedMove> for Move {
+    fn from(mv: PackedMove) -> Move {
+        mv.to_move()
+    }
+}
+
+impl std::fmt::Display for PackedMove {
+    /// The move in UCI notation, such as `e2e4`, or `e7e8q` for a promotion.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_move())
     }
+}
+
+/// Enough room for the moves of any legal position (the most known is 218).
+pub const MAX_MOVES: usize = 256;
+
+/// A list of moves stored on the stack, so that generating moves doesn't allocate. Derefs to a
+/// slice for indexing, iterating by reference and the like.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [PackedMove; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        Self {
+            moves: [PackedMove::new(Square::A1, Square::A1, MoveFlag::Quiet); MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, mv: PackedMove) {
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+
+    /// Adds a move with the same flag from `from` to every square in `targets`.
+    pub fn push_targets(&mut self, from: Square, targets: Mask, flag: MoveFlag) {
+        for to in targets.iter() {
+            self.push(PackedMove::new(from, to, flag));
+        }
+    }
+
+    /// Keeps only the moves for which `keep` returns true, in the same order.
+    pub fn retain(&mut self, mut keep: impl FnMut(&PackedMove) -> bool) {
+        let mut kept = 0;
+
+        for i in 0..self.len {
+            if keep(&self.moves[i]) {
+                self.moves[kept] = self.moves[i];
+                kept += 1;
+            }
+        }
 
-    pub fn from_move_mask(from: Square, move_mask: Mask) -> Vec<Move> {
-        let targets = move_mask.ones();
-        let mut moves = Vec::with_capacity(targets.len());
+        self.len = kept;
+    }
 
-        for to in targets {
-            moves.push(Move { from, to });
+    /// Sorts the moves from highest to lowest score, keeping the order of equal moves. Each
+    /// move is only scored once.
+    pub fn sort_by_score(&mut self, mut score: impl FnMut(&PackedMove) -> Score) {
+        let mut scores = [0; MAX_MOVES];
+        for (mv_score, mv) in scores.iter_mut().zip(self.iter()) {
+            *mv_score = score(mv);
         }
 
+        // Insertion sort, since move lists are short and usually partly sorted already
+        for i in 1..self.len {
+            let (mv, mv_score) = (self.moves[i], scores[i]);
+
+            let mut j = i;
+            while j > 0 && scores[j - 1] < mv_score {
+                self.moves[j] = self.moves[j - 1];
+                scores[j] = scores[j - 1];
+                j -= 1;
+            }
+
+            self.moves[j] = mv;
+            scores[j] = mv_score;
+        }
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [PackedMove];
+
+    fn deref(&self) -> &[PackedMove] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [PackedMove] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for MoveList {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for MoveList {}
+
+impl FromIterator<PackedMove> for MoveList {
+    fn from_iter<T: IntoIterator<Item = PackedMove>>(iter: T) -> Self {
+        let mut moves = Self::new();
+        for mv in iter {
+            moves.push(mv);
+        }
         moves
     }
+}
 
-    pub fn rank_diff(&self) -> u8 {
-        self.to.rank().diff(self.from.rank())
+impl Extend<PackedMove> for MoveList {
+    fn extend<T: IntoIterator<Item = PackedMove>>(&mut self, iter: T) {
+        for mv in iter {
+            self.push(mv);
+        }
     }
+}
 
-    pub fn file_diff(&self) -> u8 {
-        self.to.file().diff(self.from.file())
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a PackedMove;
+    type IntoIter = std::slice::Iter<'a, PackedMove>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Owning iterator over a `MoveList`.
+pub struct IntoIter {
+    list: MoveList,
+    next: usize,
+}
+
+impl Iterator for IntoIter {
+    type Item = PackedMove;
+
+    fn next(&mut self) -> Option<PackedMove> {
+        let mv = self.list.get(self.next).copied();
+        self.next += 1;
+        mv
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.list.len().saturating_sub(self.next);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+impl IntoIterator for MoveList {
+    type Item = PackedMove;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter {
+            list: self,
+            next: 0,
+        }
     }
 }
 
@@ -48,3 +473,135 @@ pub enum MoveError {
     WrongColor,
     NoPreviousMoves,
 }
+
+#[cfg(test)]
+mod moves_tests {
+    use super::*;
+
+    fn moves(list: &MoveList) -> Vec<String> {
+        list.iter().map(|mv| mv.to_string()).collect()
+    }
+
+    #[test]
+    fn move_list_works_like_a_vec() {
+        let mut list = MoveList::new();
+        list.push_targets(
+            Square::E2,
+            Square::E3.mask() | Square::E4.mask(),
+            MoveFlag::Quiet,
+        );
+        list.push(PackedMove::new(Square::G1, Square::F3, MoveFlag::Quiet));
+
+        assert_eq!(list.len(), 3);
+        assert_eq!(moves(&list), ["e2e3", "e2e4", "g1f3"]);
+        assert_eq!(list.clone().into_iter().len(), 3);
+
+        list.retain(|mv| mv.from_square() == Square::E2);
+        assert_eq!(moves(&list), ["e2e3", "e2e4"]);
+        assert_eq!(
+            list.into_iter().last(),
+            Some(PackedMove::new(Square::E2, Square::E4, MoveFlag::Quiet))
+        );
+    }
+
+    #[test]
+    fn sorts_by_score_stably() {
+        let mut list = [Square::A2, Square::B2, Square::C2, Square::D2]
+            .into_iter()
+            .map(|from| {
+                PackedMove::new(
+                    from,
+                    Square::from_u8(from as u8 + 8).unwrap(),
+                    MoveFlag::Quiet,
+                )
+            })
+            .collect::<MoveList>();
+
+        list.sort_by_score(|mv| {
+            if mv.from_square() == Square::C2 {
+                10
+            } else {
+                0
+            }
+        });
+        assert_eq!(moves(&list), ["c2c3", "a2a3", "b2b3", "d2d3"]);
+    }
+
+    #[test]
+    fn packs_moves_with_flags() {
+        let state = BoardState::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let flag =
+            |mv| PackedMove::from_move(Move::from_long_algebraic(mv).unwrap(), &state).flag();
+
+        assert_eq!(flag("e1e2"), MoveFlag::Quiet);
+        assert_eq!(flag("e1g1"), MoveFlag::KingCastle);
+        assert_eq!(flag("e1c1"), MoveFlag::QueenCastle);
+        assert_eq!(flag("a1a8"), MoveFlag::Capture);
+        assert_eq!(flag("e5d6"), MoveFlag::EnPassant);
+        assert_eq!(flag("b7b8"), MoveFlag::QueenPromotion);
+        assert_eq!(flag("b7a8"), MoveFlag::QueenPromotionCapture);
+        assert_eq!(flag("b7b8q"), MoveFlag::QueenPromotion);
+        assert_eq!(flag("b7b8n"), MoveFlag::KnightPromotion);
+        assert_eq!(flag("b7a8r"), MoveFlag::RookPromotionCapture);
+
+        let start = BoardState::from_fen(crate::board::START_FEN).unwrap();
+        let double_push = PackedMove::from_move(Move::from_long_algebraic("e2e4").unwrap(), &start);
+        assert_eq!(double_push.flag(), MoveFlag::DoublePush);
+    }
+
+    #[test]
+    fn parses_long_algebraic_moves() {
+        let mv = Move::from_long_algebraic("e7e8n").unwrap();
+        assert_eq!(
+            mv,
+            Move {
+                from: Square::E7,
+                to: Square::E8,
+                promotion: Some(Promotion::Knight),
+            }
+        );
+        assert_eq!(mv.to_string(), "e7e8n");
+        assert_eq!(
+            Move::from_long_algebraic("e2e4"),
+            Some(Move::new(Square::E2, Square::E4))
+        );
+
+        assert_eq!(Move::from_long_algebraic("e7e8k"), None);
+        assert_eq!(Move::from_long_algebraic("e7e8qq"), None);
+        assert_eq!(Move::from_long_algebraic("e7e"), None);
+    }
+
+    #[test]
+    fn packed_moves_round_trip() {
+        let mv = PackedMove::new(Square::G7, Square::H8, MoveFlag::KnightPromotionCapture);
+
+        assert_eq!(PackedMove::from_bits(mv.bits()), Some(mv));
+        assert_eq!(mv.from_square(), Square::G7);
+        assert_eq!(mv.to_square(), Square::H8);
+        assert!(mv.flag().is_capture());
+        assert_eq!(
+            mv.flag().promotion(Color::White),
+            Some(Piece::Knight(Color::White))
+        );
+        assert_eq!(Move::from(mv), Move::from_long_algebraic("g7h8n").unwrap());
+        assert_eq!(mv.to_string(), "g7h8n");
+        assert_eq!(
+            PackedMove::new(Square::E2, Square::E1, MoveFlag::QueenPromotion).to_string(),
+            "e2e1q"
+        );
+        assert_eq!(
+            PackedMove::new(Square::E1, Square::G1, MoveFlag::KingCastle).to_string(),
+            "e1g1"
+        );
+
+        assert_eq!(
+            MoveFlag::promotion_to(Promotion::Bishop, false),
+            MoveFlag::BishopPromotion
+        );
+        assert!(MoveFlag::RookPromotion.is_underpromotion());
+        assert!(!MoveFlag::QueenPromotionCapture.is_underpromotion());
+
+        assert_eq!(PackedMove::from_bits(6 << 12), None);
+        assert_eq!(std::mem::size_of::<PackedMove>(), 2);
+    }
+}
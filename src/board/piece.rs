@@ -77,6 +77,41 @@ impl Piece {
         }
     }
 
+    pub fn to_char(&self) -> char {
+        match self {
+            Self::Pawn(Color::White) => 'P',
+            Self::Knight(Color::White) => 'N',
+            Self::Bishop(Color::White) => 'B',
+            Self::Rook(Color::White) => 'R',
+            Self::Queen(Color::White) => 'Q',
+            Self::King(Color::White) => 'K',
+            Self::Pawn(Color::Black) => 'p',
+            Self::Knight(Color::Black) => 'n',
+            Self::Bishop(Color::Black) => 'b',
+            Self::Rook(Color::Black) => 'r',
+            Self::Queen(Color::Black) => 'q',
+            Self::King(Color::Black) => 'k',
+        }
+    }
+
+    /// The chess glyph for the piece, such as `♘` for a white knight.
+    pub fn to_unicode(&self) -> char {
+        match self {
+            Self::Pawn(Color::White) => '♙',
+            Self::Knight(Color::White) => '♘',
+            Self::Bishop(Color::White) => '♗',
+            Self::Rook(Color::White) => '♖',
+            Self::Queen(Color::White) => '♕',
+            Self::King(Color::White) => '♔',
+            Self::Pawn(Color::Black) => '♟',
+            Self::Knight(Color::Black) => '♞',
+            Self::Bishop(Color::Black) => '♝',
+            Self::Rook(Color::Black) => '♜',
+            Self::Queen(Color::Black) => '♛',
+            Self::King(Color::Black) => '♚',
+        }
+    }
+
     pub fn to_mask_index(&self) -> usize {
         match self {
             Piece::Pawn(Color::White) => Piece::WHITE_PAWN_INDEX,
@@ -94,6 +129,11 @@ impl Piece {
         }
     }
 
+    /// Index of the piece's kind regardless of color (pawn = 0, ..., king = 5).
+    pub fn kind_index(&self) -> usize {
+        self.to_mask_index() % 6
+    }
+
     pub fn from_mask_index(i: usize) -> Option<Piece> {
         match i {
             Piece::WHITE_PAWN_INDEX => Some(Piece::Pawn(Color::White)),
@@ -112,3 +152,13 @@ impl Piece {
         }
     }
 }
+
+impl std::fmt::Display for Piece {
+    /// The piece's letter as in FEN, or its glyph with `{:#}`.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match f.alternate() {
+            true => write!(f, "{}", self.to_unicode()),
+            false => write!(f, "{}", self.to_char()),
+        }
+    }
+}
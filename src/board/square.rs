@@ -1,6 +1,6 @@
 use crate::board::mask::Mask;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum Square {
     A1,
@@ -189,13 +189,11 @@ impl Square {
     }
 
     pub fn from_mask(mask: Mask) -> Option<Self> {
-        let ones = mask.ones();
-
-        if ones.len() != 1 {
+        if mask.count() != 1 {
             return None;
         }
 
-        Self::from_u8(ones[0].to_shift() as u8)
+        mask.lsb()
     }
 
     pub fn from_coords(rank: Rank, file: File) -> Self {
@@ -340,74 +338,12 @@ impl Square {
             Self::H8 => 63,
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
-        match self {
-            Square::A1 => String::from("a1"),
-            Square::B1 => String::from("b1"),
-            Square::C1 => String::from("c1"),
-            Square::D1 => String::from("d1"),
-            Square::E1 => String::from("e1"),
-            Square::F1 => String::from("f1"),
-            Square::G1 => String::from("g1"),
-            Square::H1 => String::from("h1"),
-            Square::A2 => String::from("a2"),
-            Square::B2 => String::from("b2"),
-            Square::C2 => String::from("c2"),
-            Square::D2 => String::from("d2"),
-            Square::E2 => String::from("e2"),
-            Square::F2 => String::from("f2"),
-            Square::G2 => String::from("g2"),
-            Square::H2 => String::from("h2"),
-            Square::A3 => String::from("a3"),
-            Square::B3 => String::from("b3"),
-            Square::C3 => String::from("c3"),
-            Square::D3 => String::from("d3"),
-            Square::E3 => String::from("e3"),
-            Square::F3 => String::from("f3"),
-            Square::G3 => String::from("g3"),
-            Square::H3 => String::from("h3"),
-            Square::A4 => String::from("a4"),
-            Square::B4 => String::from("b4"),
-            Square::C4 => String::from("c4"),
-            Square::D4 => String::from("d4"),
-            Square::E4 => String::from("e4"),
-            Square::F4 => String::from("f4"),
-            Square::G4 => String::from("g4"),
-            Square::H4 => String::from("h4"),
-            Square::A5 => String::from("a5"),
-            Square::B5 => String::from("b5"),
-            Square::C5 => String::from("c5"),
-            Square::D5 => String::from("d5"),
-            Square::E5 => String::from("e5"),
-            Square::F5 => String::from("f5"),
-            Square::G5 => String::from("g5"),
-            Square::H5 => String::from("h5"),
-            Square::A6 => String::from("a6"),
-            Square::B6 => String::from("b6"),
-            Square::C6 => String::from("c6"),
-            Square::D6 => String::from("d6"),
-            Square::E6 => String::from("e6"),
-            Square::F6 => String::from("f6"),
-            Square::G6 => String::from("g6"),
-            Square::H6 => String::from("h6"),
-            Square::A7 => String::from("a7"),
-            Square::B7 => String::from("b7"),
-            Square::C7 => String::from("c7"),
-            Square::D7 => String::from("d7"),
-            Square::E7 => String::from("e7"),
-            Square::F7 => String::from("f7"),
-            Square::G7 => String::from("g7"),
-            Square::H7 => String::from("h7"),
-            Square::A8 => String::from("a8"),
-            Square::B8 => String::from("b8"),
-            Square::C8 => String::from("c8"),
-            Square::D8 => String::from("d8"),
-            Square::E8 => String::from("e8"),
-            Square::F8 => String::from("f8"),
-            Square::G8 => String::from("g8"),
-            Square::H8 => String::from("h8"),
-        }
+impl std::fmt::Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let file = (b'a' + self.file() as u8) as char;
+        write!(f, "{}{}", file, self.rank() as u8 + 1)
     }
 }
 
@@ -0,0 +1,176 @@
+//! Zobrist hashing. Every (piece, square) pair, the side to move, each castling right and each
+//! en passant file gets a random 64-bit key, and a position's hash is the XOR of the keys of
+//! everything in it. The keys are generated at compile time from a fixed seed, so hashes are the
+//! same on every run.
+
+use super::{piece::Color, square::Square, BoardState};
+
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Keys indexed by [piece mask index][square]
+pub const PIECE_KEYS: [[u64; 64]; 12] = generate_piece_keys();
+pub const BLACK_TO_MOVE_KEY: u64 = random(12 * 64);
+/// Keys for white kingside, white queenside, black kingside and black queenside castling
+pub const CASTLING_KEYS: [u64; 4] = generate_keys::<4>(12 * 64 + 1);
+/// Keys indexed by the file of the en passant square
+pub const EN_PASSANT_KEYS: [u64; 8] = generate_keys::<8>(12 * 64 + 5);
+
+/// The `n`th number of a SplitMix64 sequence.
+const fn random(n: usize) -> u64 {
+    let mut z = SEED.wrapping_add((n as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys<const N: usize>(offset: usize) -> [u64; N] {
+    let mut keys = [0; N];
+
+    let mut i = 0;
+    while i < N {
+        keys[i] = random(offset + i);
+        i += 1;
+    }
+
+    keys
+}
+
+const fn generate_piece_keys() -> [[u64; 64]; 12] {
+    let mut keys = [[0; 64]; 12];
+
+    let mut piece = 0;
+    while piece < 12 {
+        keys[piece] = generate_keys::<64>(piece * 64);
+        piece += 1;
+    }
+
+    keys
+}
+
+/// Computes the hash of a position from scratch. `BoardState` keeps its hash up to date as moves
+/// are made, so this is only needed for new positions and for checking that hash.
+pub fn hash(state: &BoardState) -> u64 {
+    let mut hash = state_keys(state);
+
+    for (i, keys) in PIECE_KEYS.iter().enumerate() {
+        for square in state.mask_at(i).iter() {
+            hash ^= keys[square as usize];
+        }
+    }
+
+    hash
+}
+
+/// The keys for the side to move, castling rights and en passant file of a position, which
+/// make up its hash along with the keys of its pieces.
+pub fn state_keys(state: &BoardState) -> u64 {
+    let mut hash = 0;
+
+    if state.active_color == Color::Black {
+        hash ^= BLACK_TO_MOVE_KEY;
+    }
+
+    let castling_rights = [
+        state.h1_rook_moved,
+        state.a1_rook_moved,
+        state.h8_rook_moved,
+        state.a8_rook_moved,
+    ];
+    for (moved, key) in castling_rights.into_iter().zip(CASTLING_KEYS) {
+        if !moved {
+            hash ^= key;
+        }
+    }
+
+    if let Some(square) = state.en_passant_mask().and_then(Square::from_mask) {
+        hash ^= EN_PASSANT_KEYS[square.file() as usize];
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod zobrist_tests {
+    use super::*;
+    use crate::board::{moves::Move, Board, START_FEN};
+
+    #[test]
+    fn transpositions_hash_equally() {
+        let mut a = Board::new(START_FEN).unwrap();
+        let mut b = Board::new(START_FEN).unwrap();
+
+        for mv in ["g1f3", "g8f6", "b1c3", "b8c6"] {
+            a.make_move(Move::from_long_algebraic(mv).unwrap()).unwrap();
+        }
+        for mv in ["b1c3", "b8c6", "g1f3", "g8f6"] {
+            b.make_move(Move::from_long_algebraic(mv).unwrap()).unwrap();
+        }
+
+        assert_eq!(a.current_position().hash(), b.current_position().hash());
+    }
+
+    /// Checks the incremental hash of every position up to `depth` plies from the current one,
+    /// including positions reached with a null move.
+    fn check_hashes(board: &mut Board, depth: usize) {
+        let state = board.current_position();
+        assert_eq!(state.hash(), hash(state), "{}", state.to_fen());
+
+        if depth == 0 {
+            return;
+        }
+
+        board.make_null_move();
+        let state = board.current_position();
+        assert_eq!(state.hash(), hash(state), "{}", state.to_fen());
+        board.unmake_move().unwrap();
+
+        for mv in board.legal_moves() {
+            board.make_packed_move(mv).unwrap();
+            check_hashes(board, depth - 1);
+            board.unmake_move().unwrap();
+        }
+    }
+
+    #[test]
+    fn incremental_hash_matches_full_hash() {
+        // Perft positions with castling, en passant and promotions (with and without captures)
+        const POSITIONS: &[(&str, usize)] = &[
+            (START_FEN, 3),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                2,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 3),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                2,
+            ),
+        ];
+
+        for (fen, depth) in POSITIONS {
+            check_hashes(&mut Board::new(fen).unwrap(), *depth);
+        }
+    }
+
+    #[test]
+    fn side_castling_and_en_passant_change_hash() {
+        let start = BoardState::from_fen(START_FEN).unwrap();
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1",
+        ] {
+            assert_ne!(BoardState::from_fen(fen).unwrap().hash(), start.hash());
+        }
+
+        let mut board = Board::new(START_FEN).unwrap();
+        board
+            .make_move(Move::from_long_algebraic("e2e4").unwrap())
+            .unwrap();
+        let without_en_passant =
+            BoardState::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+                .unwrap();
+
+        assert_ne!(board.current_position().hash(), without_en_passant.hash());
+    }
+}
@@ -0,0 +1,443 @@
+//! Polyglot opening books.
+//!
+//! A book is a file of 16-byte big-endian entries sorted by key: the position's Polyglot hash
+//! (8 bytes), a move (2 bytes), a weight (2 bytes) and a learn value (4 bytes) that's ignored.
+//! The hash uses the Polyglot layout of 781 random numbers (768 for pieces on squares, 4 for
+//! castling rights, 8 for en passant files and 1 for white to move), which are embedded in
+//! `random64`.
+
+mod random64;
+
+use std::{fmt, fs, path::Path};
+
+use rand::{thread_rng, Rng};
+
+use crate::board::{
+    moves::{Move, Promotion},
+    piece::{Color, Piece},
+    square::Square,
+    BoardState,
+};
+pub use random64::RANDOM64;
+
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+const ENTRY_SIZE: usize = 16;
+
+#[derive(Debug)]
+pub enum BookError {
+    Io(std::io::Error),
+    BadSize { expected: usize, found: usize },
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::BadSize { expected, found } => {
+                write!(f, "expected {expected} bytes, found {found}")
+            }
+        }
+    }
+}
+
+/// Computes the Polyglot hash of a position.
+pub fn key(state: &BoardState) -> u64 {
+    let mut key = 0;
+
+    for i in 0..12 {
+        let piece = Piece::from_mask_index(i).unwrap();
+        // Polyglot interleaves the colors, starting with black: black pawn, white pawn, black
+        // knight, ...
+        let kind = 2 * piece.kind_index() + (piece.color() == Color::White) as usize;
+
+        for square in state.mask_at(i).iter() {
+            key ^= RANDOM64[64 * kind + square as usize];
+        }
+    }
+
+    for (i, allowed) in state.castling_rights().into_iter().enumerate() {
+        if allowed {
+            key ^= RANDOM64[CASTLING_OFFSET + i];
+        }
+    }
+
+    if let Some(file) = en_passant_file(state) {
+        key ^= RANDOM64[EN_PASSANT_OFFSET + file];
+    }
+
+    if state.active_color() == Color::White {
+        key ^= RANDOM64[TURN_OFFSET];
+    }
+
+    key
+}
+
+/// The en passant file as Polyglot counts it: only when a pawn of the side to move stands next
+/// to the pawn that just moved two squares, whether or not the capture would be legal.
+fn en_passant_file(state: &BoardState) -> Option<usize> {
+    let target = Square::from_mask(state.en_passant_mask()?)?;
+    let color = state.active_color();
+    let pushed_rank = match color {
+        Color::White => target as u8 / 8 - 1,
+        Color::Black => target as u8 / 8 + 1,
+    };
+    let file = target as u8 % 8;
+
+    let adjacent_pawn = [file.checked_sub(1), (file < 7).then_some(file + 1)]
+        .into_iter()
+        .flatten()
+        .filter_map(|file| Square::from_u8(pushed_rank * 8 + file))
+        .any(|square| state.piece_at_square(square) == Some(Piece::Pawn(color)));
+
+    adjacent_pawn.then_some(file as usize)
+}
+
+/// Converts a Polyglot move into a `Move`. Castling is stored as the king capturing its own
+/// rook, and promotions as 1 to 4 for a knight to a queen.
+pub fn decode_move(raw: u16, state: &BoardState) -> Option<Move> {
+    let to = Square::from_u8((raw & 0x3f) as u8)?;
+    let from = Square::from_u8(((raw >> 6) & 0x3f) as u8)?;
+    let promotion = match (raw >> 12) & 0x7 {
+        0 => None,
+        1 => Some(Promotion::Knight),
+        2 => Some(Promotion::Bishop),
+        3 => Some(Promotion::Rook),
+        4 => Some(Promotion::Queen),
+        _ => return None,
+    };
+
+    let to = match (state.piece_at_square(from), from, to) {
+        (Some(Piece::King(Color::White)), Square::E1, Square::H1) => Square::G1,
+        (Some(Piece::King(Color::White)), Square::E1, Square::A1) => Square::C1,
+        (Some(Piece::King(Color::Black)), Square::E8, Square::H8) => Square::G8,
+        (Some(Piece::King(Color::Black)), Square::E8, Square::A8) => Square::C8,
+        _ => to,
+    };
+
+    Some(Move {
+        from,
+        to,
+        promotion,
+    })
+}
+
+/// Converts a `Move` into a Polyglot move, the opposite of `decode_move`.
+pub fn encode_move(mv: Move, state: &BoardState) -> u16 {
+    let piece = state.piece_at_square(mv.from);
+
+    let to = match (piece, mv.from, mv.to) {
+        (Some(Piece::King(Color::White)), Square::E1, Square::G1) => Square::H1,
+        (Some(Piece::King(Color::White)), Square::E1, Square::C1) => Square::A1,
+        (Some(Piece::King(Color::Black)), Square::E8, Square::G8) => Square::H8,
+        (Some(Piece::King(Color::Black)), Square::E8, Square::C8) => Square::A8,
+        _ => mv.to,
+    };
+
+    let last_rank = matches!(mv.to as u8 / 8, 0 | 7);
+    let promotion = match piece {
+        Some(Piece::Pawn(_)) if last_rank => mv.promotion.unwrap_or(Promotion::Queen) as u16 + 1,
+        _ => 0,
+    };
+
+    promotion << 12 | (mv.from as u16) << 6 | to as u16
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// How a move is picked when a position has several book moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// Randomly, in proportion to the move weights
+    Weighted,
+    /// Always the move with the highest weight
+    Best,
+}
+
+#[derive(Debug, Clone)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BookError> {
+        let bytes = fs::read(path).map_err(BookError::Io)?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(BookError::BadSize {
+                expected: bytes.len() / ENTRY_SIZE * ENTRY_SIZE,
+                found: bytes.len(),
+            });
+        }
+
+        let mut entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(BookEntry::from_bytes)
+            .collect::<Vec<_>>();
+        // Books should already be sorted, but lookups rely on it
+        entries.sort_by_key(|entry| entry.key);
+
+        Ok(Self { entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Every book entry for the position.
+    pub fn entries(&self, state: &BoardState) -> &[BookEntry] {
+        let key = key(state);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+
+        &self.entries[start..end]
+    }
+
+    /// The book moves for the position that can be played, with their weights.
+    pub fn moves(&self, state: &BoardState) -> Vec<(Move, u16)> {
+        self.entries(state)
+            .iter()
+            .filter_map(|entry| Some((decode_move(entry.raw_move, state)?, entry.weight)))
+            .collect()
+    }
+
+    /// Picks a book move for the position. Moves with a weight of zero are never picked.
+    pub fn probe(&self, state: &BoardState, selection: Selection) -> Option<Move> {
+        let moves = self.moves(state);
+
+        match selection {
+            Selection::Best => moves
+                .iter()
+                .filter(|(_, weight)| *weight > 0)
+                .max_by_key(|(_, weight)| *weight)
+                .map(|(mv, _)| *mv),
+            Selection::Weighted => {
+                let total = moves.iter().map(|(_, weight)| *weight as u32).sum::<u32>();
+                if total == 0 {
+                    return None;
+                }
+
+                let mut pick = thread_rng().gen_range(0..total);
+                for (mv, weight) in moves {
+                    if pick < weight as u32 {
+                        return Some(mv);
+                    }
+                    pick -= weight as u32;
+                }
+                unreachable!()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod book_tests {
+    use super::*;
+    use crate::board::START_FEN;
+
+    fn state(fen: &str) -> BoardState {
+        BoardState::from_fen(fen).unwrap()
+    }
+
+    fn raw_move(mv: &str) -> u16 {
+        let mv = Move::from_long_algebraic(mv).unwrap();
+        (mv.from as u16) << 6 | mv.to as u16
+    }
+
+    fn entry_bytes(key: u64, raw_move: u16, weight: u16) -> [u8; 16] {
+        BookEntry {
+            key,
+            raw_move,
+            weight,
+            learn: 0,
+        }
+        .to_bytes()
+    }
+
+    #[test]
+    fn hashes_reference_positions() {
+        // The example positions and keys from the Polyglot book format description
+        for (fen, expected) in [
+            (START_FEN, 0x463b96181691fc9c),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                0x823c9b50fd114196,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                0x0756b94461c50fb0,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+                0x662fafb965db29d4,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22a48b5a8e47ff78,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+                0x652a607ca3f242c1,
+            ),
+            (
+                "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+                0x00fdd303c946bdd9,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+                0x3c8123ea7b067637,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5c3f9b829b279560,
+            ),
+        ] {
+            assert_eq!(key(&state(fen)), expected, "{fen}");
+        }
+    }
+
+    #[test]
+    fn hashes_in_polyglot_layout() {
+        let start = key(&state(START_FEN));
+
+        let black_to_move = key(&state(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+        ));
+        assert_eq!(start ^ black_to_move, RANDOM64[TURN_OFFSET]);
+
+        let no_white_kingside = key(&state(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Qkq - 0 1",
+        ));
+        assert_eq!(start ^ no_white_kingside, RANDOM64[CASTLING_OFFSET]);
+
+        // A white pawn on e2 is piece kind 1, square 12
+        let no_e2_pawn = key(&state(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1",
+        ));
+        assert_eq!(start ^ no_e2_pawn, RANDOM64[64 + 12]);
+    }
+
+    #[test]
+    fn counts_en_passant_only_next_to_a_pawn() {
+        // Nothing can capture on e3
+        assert_eq!(
+            key(&state(
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+            )),
+            key(&state(
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+            ))
+        );
+
+        // The d4 pawn can
+        let with_en_passant = key(&state(
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        ));
+        let without_en_passant = key(&state(
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+        ));
+        assert_eq!(
+            with_en_passant ^ without_en_passant,
+            RANDOM64[EN_PASSANT_OFFSET + 4]
+        );
+    }
+
+    #[test]
+    fn decodes_castling_and_promotions() {
+        let castling = state("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let decode = |mv, state| decode_move(raw_move(mv), state).map(|mv| mv.long_algebraic());
+
+        assert_eq!(decode("e1h1", &castling), Some(String::from("e1g1")));
+        assert_eq!(decode("e1a1", &castling), Some(String::from("e1c1")));
+        assert_eq!(decode("a1a8", &castling), Some(String::from("a1a8")));
+
+        let promotion = state("8/4P3/8/8/8/8/k7/6K1 w - - 0 1");
+        let queen = raw_move("e7e8") | 4 << 12;
+        let knight = raw_move("e7e8") | 1 << 12;
+        assert_eq!(
+            decode_move(queen, &promotion),
+            Move::from_long_algebraic("e7e8q")
+        );
+        assert_eq!(
+            decode_move(knight, &promotion),
+            Move::from_long_algebraic("e7e8n")
+        );
+        assert_eq!(decode_move(raw_move("e7e8") | 5 << 12, &promotion), None);
+
+        for (mv, state) in [
+            ("e1g1", &castling),
+            ("e8c8", &castling),
+            ("e7e8q", &promotion),
+            ("e7e8b", &promotion),
+        ] {
+            let mv = Move::from_long_algebraic(mv).unwrap();
+            assert_eq!(decode_move(encode_move(mv, state), state), Some(mv));
+        }
+        assert_eq!(
+            encode_move(Move::from_long_algebraic("e7e8").unwrap(), &promotion),
+            queen
+        );
+    }
+
+    #[test]
+    fn probes_entries() {
+        let start = state(START_FEN);
+        let key = key(&start);
+
+        let mut bytes = entry_bytes(key.wrapping_add(1), raw_move("a2a3"), 100).to_vec();
+        bytes.extend(entry_bytes(key, raw_move("d2d4"), 30));
+        bytes.extend(entry_bytes(key, raw_move("e2e4"), 50));
+        bytes.extend(entry_bytes(key, raw_move("h2h4"), 0));
+        bytes.extend(entry_bytes(key.wrapping_sub(1), raw_move("b2b3"), 100));
+
+        let book = Book::from_bytes(&bytes).unwrap();
+        assert_eq!(book.len(), 5);
+        assert_eq!(book.entries(&start).len(), 3);
+
+        assert_eq!(
+            book.probe(&start, Selection::Best),
+            Move::from_long_algebraic("e2e4")
+        );
+        for _ in 0..20 {
+            let mv = book.probe(&start, Selection::Weighted).unwrap();
+            assert!(["d2d4", "e2e4"].contains(&mv.long_algebraic().as_str()));
+        }
+
+        assert!(Book::from_bytes(&bytes[..20]).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::board::{
+    mask::Mask,
+    piece::{Color, Piece},
+    square::{Rank, Square},
+    BoardState,
+};
+
+use super::{file_mask, pawn_attacks, rank_mask, Score, Weights};
+
+/// Piece activity score for `color`: bishop pair, rooks on open files and the seventh rank, and
+/// knight outposts.
+pub fn evaluate(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    bishop_pair(state, color, weights)
+        + rook_files(state, color, weights)
+        + rooks_on_seventh(state, color, weights)
+        + knight_outposts(state, color, weights)
+}
+
+pub fn bishop_pair(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    if state.mask(Piece::Bishop(color)).count() >= 2 {
+        weights.bishop_pair
+    } else {
+        0
+    }
+}
+
+/// Bonus for rooks on files with no friendly pawns (open if there are no enemy pawns either).
+pub fn rook_files(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    let friendly_pawns = state.mask(Piece::Pawn(color));
+    let enemy_pawns = state.mask(Piece::Pawn(color.swapped()));
+
+    let mut score = 0;
+
+    for square in state.mask(Piece::Rook(color)).iter() {
+        let file_mask = file_mask(square.file());
+
+        if friendly_pawns & file_mask != Mask(0) {
+            continue;
+        }
+
+        if enemy_pawns & file_mask == Mask(0) {
+            score += weights.rook_open_file;
+        } else {
+            score += weights.rook_semi_open_file;
+        }
+    }
+
+    score
+}
+
+pub fn rooks_on_seventh(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    let seventh_rank = match color {
+        Color::White => rank_mask(Rank::Seven),
+        Color::Black => rank_mask(Rank::Two),
+    };
+
+    let rooks = (state.mask(Piece::Rook(color)) & seventh_rank).count();
+    weights.rook_seventh_rank * rooks as Score
+}
+
+/// Bonus for knights in the enemy half that are defended by a pawn and can never be chased away
+/// by an enemy pawn.
+pub fn knight_outposts(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    let outpost_ranks = match color {
+        Color::White => [Rank::Four, Rank::Five, Rank::Six],
+        Color::Black => [Rank::Five, Rank::Four, Rank::Three],
+    };
+
+    let defended = pawn_attacks(state, color);
+    let enemy_pawns = state.mask(Piece::Pawn(color.swapped()));
+
+    let mut score = 0;
+
+    for square in state.mask(Piece::Knight(color)).iter() {
+        if !outpost_ranks.contains(&square.rank()) || defended & square.mask() == Mask(0) {
+            continue;
+        }
+
+        if enemy_pawns & attack_span(square, color) == Mask(0) {
+            score += weights.knight_outpost;
+        }
+    }
+
+    score
+}
+
+/// Squares on the adjacent files in front of `square` (from `color`'s point of view), from which
+/// an enemy pawn could eventually attack it.
+pub fn attack_span(square: Square, color: Color) -> Mask {
+    let file = square.file() as u8;
+
+    let mut files = Mask(0);
+    if file > 0 {
+        files |= Mask(file_mask(square.file()).0 >> 1);
+    }
+    if file < 7 {
+        files |= Mask(file_mask(square.file()).0 << 1);
+    }
+
+    files & ranks_ahead(square, color)
+}
+
+/// Every square on the ranks in front of `square`, from `color`'s point of view.
+pub fn ranks_ahead(square: Square, color: Color) -> Mask {
+    let rank = square.rank() as u8;
+
+    match color {
+        Color::White => Mask(u64::MAX.checked_shl(8 * (rank as u32 + 1)).unwrap_or(0)),
+        Color::Black => Mask((1 << (8 * rank)) - 1),
+    }
+}
+
+#[cfg(test)]
+mod activity_tests {
+    use super::*;
+    use crate::eval::weights::WEIGHTS;
+
+    #[test]
+    fn bishop_pair_bonus() {
+        let pair = BoardState::from_fen("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").unwrap();
+        let single = BoardState::from_fen("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+
+        assert_eq!(
+            bishop_pair(&pair, Color::White, &WEIGHTS),
+            WEIGHTS.bishop_pair
+        );
+        assert_eq!(bishop_pair(&single, Color::White, &WEIGHTS), 0);
+    }
+
+    #[test]
+    fn rook_file_bonuses() {
+        let state = BoardState::from_fen("4k3/p7/8/8/8/8/2P5/R1R1K2R w - - 0 1").unwrap();
+
+        // a-file is semi-open, c-file is closed and h-file is open
+        assert_eq!(
+            rook_files(&state, Color::White, &WEIGHTS),
+            WEIGHTS.rook_semi_open_file + WEIGHTS.rook_open_file
+        );
+    }
+
+    #[test]
+    fn rook_on_seventh() {
+        let state = BoardState::from_fen("4k3/R7/8/8/8/8/r7/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(
+            rooks_on_seventh(&state, Color::White, &WEIGHTS),
+            WEIGHTS.rook_seventh_rank
+        );
+        assert_eq!(
+            rooks_on_seventh(&state, Color::Black, &WEIGHTS),
+            WEIGHTS.rook_seventh_rank
+        );
+    }
+
+    #[test]
+    fn knight_outpost() {
+        let outpost = BoardState::from_fen("4k3/8/8/3N4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let chased = BoardState::from_fen("4k3/2p5/8/3N4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let undefended = BoardState::from_fen("4k3/8/8/3N4/8/8/8/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(
+            knight_outposts(&outpost, Color::White, &WEIGHTS),
+            WEIGHTS.knight_outpost
+        );
+        assert_eq!(knight_outposts(&chased, Color::White, &WEIGHTS), 0);
+        assert_eq!(knight_outposts(&undefended, Color::White, &WEIGHTS), 0);
+    }
+
+    #[test]
+    fn attack_span_covers_adjacent_files_ahead() {
+        let span = attack_span(Square::D5, Color::White);
+
+        assert_eq!(span.count(), 6);
+        assert!(span & Square::C6.mask() != Mask(0));
+        assert!(span & Square::E8.mask() != Mask(0));
+        assert!(span & Square::D6.mask() == Mask(0));
+        assert!(span & Square::C4.mask() == Mask(0));
+
+        let span = attack_span(Square::A4, Color::Black);
+        assert_eq!(span.count(), 3);
+        assert!(span & Square::B1.mask() != Mask(0));
+    }
+}
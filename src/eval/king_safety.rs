@@ -0,0 +1,237 @@
+use crate::{
+    board::{
+        mask::Mask,
+        piece::{Color, Piece},
+        square::{File, Rank, Square},
+        BoardState,
+    },
+    move_gen::{
+        move_masks::{
+            BLACK_PAWN_CAPTURE_MASKS, KING_MOVE_MASKS, KNIGHT_MOVE_MASKS, WHITE_PAWN_CAPTURE_MASKS,
+        },
+        SlidingMoves,
+    },
+};
+
+use super::{file_mask, Score, Weights};
+
+/// Summary of the enemy pieces attacking a king zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KingAttack {
+    /// Number of enemy pieces attacking at least one square in the zone
+    pub attackers: usize,
+    /// Sum of each attacker's weight multiplied by the number of zone squares it attacks
+    pub weight: Score,
+}
+
+/// King safety score for `color`'s king. Positive values mean the king is safe.
+pub fn evaluate(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    let Some(king_square) = Square::from_mask(state.mask(Piece::King(color))) else {
+        return 0;
+    };
+
+    let zone = king_zone(king_square, color);
+    let attack = zone_attacks(state, color, zone, weights);
+
+    pawn_shield(state, color, king_square, weights)
+        - open_files(state, color, king_square, weights)
+        - king_danger(attack, weights)
+}
+
+/// The squares surrounding the king, plus the row of squares in front of those.
+pub fn king_zone(king_square: Square, color: Color) -> Mask {
+    let zone = KING_MOVE_MASKS[king_square as usize] | king_square.mask();
+
+    let forward = match color {
+        Color::White => Mask(zone.0 << 8),
+        Color::Black => Mask(zone.0 >> 8),
+    };
+
+    zone | forward
+}
+
+/// Counts and weighs the enemy pieces attacking `zone`, which belongs to `color`'s king.
+pub fn zone_attacks(state: &BoardState, color: Color, zone: Mask, weights: &Weights) -> KingAttack {
+    let enemy = color.swapped();
+    let blockers = state.all_pieces_mask();
+    let sliding_moves = SlidingMoves::get();
+
+    let mut attack = KingAttack::default();
+
+    for piece in super::pieces(enemy) {
+        for square in state.mask(piece).iter() {
+            let attacks = match piece {
+                Piece::Pawn(Color::White) => WHITE_PAWN_CAPTURE_MASKS[square as usize],
+                Piece::Pawn(Color::Black) => BLACK_PAWN_CAPTURE_MASKS[square as usize],
+                Piece::Knight(_) => KNIGHT_MOVE_MASKS[square as usize],
+                Piece::Bishop(_) => sliding_moves.get_bishop_moves(square, blockers),
+                Piece::Rook(_) => sliding_moves.get_rook_moves(square, blockers),
+                Piece::Queen(_) => {
+                    sliding_moves.get_rook_moves(square, blockers)
+                        | sliding_moves.get_bishop_moves(square, blockers)
+                }
+                // The enemy king can never safely attack the zone on its own
+                Piece::King(_) => Mask(0),
+            };
+
+            let hits = (attacks & zone).count();
+            if hits > 0 {
+                attack.attackers += 1;
+                attack.weight += weights.king_attacker[piece.kind_index()] * hits as Score;
+            }
+        }
+    }
+
+    attack
+}
+
+/// Penalty for an attack on the king zone. A lone attacker is rarely dangerous, so the attack
+/// weight is scaled up as more pieces join in.
+pub fn king_danger(attack: KingAttack, weights: &Weights) -> Score {
+    let scale_index = attack.attackers.min(weights.king_attack_scale.len() - 1);
+    attack.weight * weights.king_attack_scale[scale_index] / 100
+}
+
+/// Bonus for friendly pawns directly in front of a king that is still on its first two ranks.
+pub fn pawn_shield(
+    state: &BoardState,
+    color: Color,
+    king_square: Square,
+    weights: &Weights,
+) -> Score {
+    let (home_ranks, shield_ranks) = match color {
+        Color::White => ([Rank::One, Rank::Two], [1, 2]),
+        Color::Black => ([Rank::Eight, Rank::Seven], [-1, -2]),
+    };
+
+    if !home_ranks.contains(&king_square.rank()) {
+        return 0;
+    }
+
+    let pawns = state.mask(Piece::Pawn(color));
+    let mut score = 0;
+
+    for file in adjacent_files(king_square.file()) {
+        for (i, offset) in shield_ranks.into_iter().enumerate() {
+            let rank = king_square.rank() as i8 + offset;
+            let Some(rank) = Rank::from_u8(rank as u8) else {
+                continue;
+            };
+
+            if pawns & Square::from_coords(rank, file).mask() != Mask(0) {
+                score += weights.pawn_shield[i];
+            }
+        }
+    }
+
+    score
+}
+
+/// Penalty for open and semi-open files on or next to the king's file.
+pub fn open_files(
+    state: &BoardState,
+    color: Color,
+    king_square: Square,
+    weights: &Weights,
+) -> Score {
+    let friendly_pawns = state.mask(Piece::Pawn(color));
+    let enemy_pawns = state.mask(Piece::Pawn(color.swapped()));
+
+    let mut penalty = 0;
+
+    for file in adjacent_files(king_square.file()) {
+        let file_mask = file_mask(file);
+
+        if friendly_pawns & file_mask != Mask(0) {
+            continue;
+        }
+
+        if enemy_pawns & file_mask == Mask(0) {
+            penalty += weights.king_open_file;
+        } else {
+            penalty += weights.king_semi_open_file;
+        }
+    }
+
+    penalty
+}
+
+/// The given file and the files either side of it.
+fn adjacent_files(file: File) -> Vec<File> {
+    let file = file as u8;
+
+    [file.checked_sub(1), Some(file), Some(file + 1)]
+        .into_iter()
+        .flatten()
+        .filter_map(File::from_u8)
+        .collect()
+}
+
+#[cfg(test)]
+mod king_safety_tests {
+    use super::*;
+    use crate::eval::weights::WEIGHTS;
+
+    #[test]
+    fn king_zone_extends_forward() {
+        let zone = king_zone(Square::G1, Color::White);
+
+        for square in [
+            Square::F1,
+            Square::G1,
+            Square::H1,
+            Square::F2,
+            Square::G2,
+            Square::H2,
+            Square::F3,
+            Square::G3,
+            Square::H3,
+        ] {
+            assert!(zone & square.mask() != Mask(0));
+        }
+        assert_eq!(zone.count(), 9);
+
+        let zone = king_zone(Square::E8, Color::Black);
+        assert!(zone & Square::E6.mask() != Mask(0));
+        assert!(zone & Square::E3.mask() == Mask(0));
+    }
+
+    #[test]
+    fn counts_attackers() {
+        let state = BoardState::from_fen("r5k1/5ppp/8/8/8/5N1Q/5PPP/6K1 w - - 0 1").unwrap();
+
+        let zone = king_zone(Square::G8, Color::Black);
+        let attack = zone_attacks(&state, Color::Black, zone, &WEIGHTS);
+        assert_eq!(attack.attackers, 1);
+
+        let state = BoardState::from_fen("r5k1/5ppp/8/6N1/8/7Q/5PPP/6K1 w - - 0 1").unwrap();
+        let attack = zone_attacks(&state, Color::Black, zone, &WEIGHTS);
+        assert_eq!(attack.attackers, 2);
+        assert!(king_danger(attack, &WEIGHTS) > 0);
+    }
+
+    #[test]
+    fn pawn_shield_and_open_files() {
+        let sheltered = BoardState::from_fen("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        let exposed = BoardState::from_fen("6k1/5ppp/8/8/8/8/8/6K1 w - - 0 1").unwrap();
+
+        assert_eq!(
+            pawn_shield(&sheltered, Color::White, Square::G1, &WEIGHTS),
+            3 * WEIGHTS.pawn_shield[0]
+        );
+        assert_eq!(pawn_shield(&exposed, Color::White, Square::G1, &WEIGHTS), 0);
+
+        assert_eq!(
+            open_files(&sheltered, Color::White, Square::G1, &WEIGHTS),
+            0
+        );
+        assert_eq!(
+            open_files(&exposed, Color::White, Square::G1, &WEIGHTS),
+            3 * WEIGHTS.king_semi_open_file
+        );
+        assert!(
+            evaluate(&sheltered, Color::White, &WEIGHTS)
+                > evaluate(&exposed, Color::White, &WEIGHTS)
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::board::{
+    mask::Mask,
+    piece::{Color, Piece},
+    square::{Rank, Square},
+    BoardState,
+};
+
+use super::{pawn_attacks, Score, Weights};
+
+/// Mobility score for `color`'s knights, bishops, rooks and queens, including penalties for
+/// pieces that have become trapped.
+pub fn evaluate(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    let enemy_pawn_attacks = pawn_attacks(state, color.swapped());
+    let mut score = 0;
+
+    for piece in [
+        Piece::Knight(color),
+        Piece::Bishop(color),
+        Piece::Rook(color),
+        Piece::Queen(color),
+    ] {
+        for square in state.mask(piece).iter() {
+            let mobility = safe_mobility(state, piece, square, enemy_pawn_attacks);
+
+            score += weights.mobility[piece.kind_index()] * mobility as Score;
+
+            if is_trapped(piece, square, mobility) {
+                score -= weights.trapped_piece[piece.kind_index()];
+            }
+        }
+    }
+
+    score
+}
+
+/// Number of pseudolegal moves for the piece on `square` that don't land on a square attacked
+/// by an enemy pawn.
+pub fn safe_mobility(
+    state: &BoardState,
+    piece: Piece,
+    square: Square,
+    enemy_pawn_attacks: Mask,
+) -> u32 {
+    let moves = state.piece_move_mask(piece, square);
+    (moves & !enemy_pawn_attacks).count()
+}
+
+/// A piece counts as trapped when it has ventured into the enemy half of the board and has at
+/// most one safe square left to go to.
+pub fn is_trapped(piece: Piece, square: Square, safe_mobility: u32) -> bool {
+    let in_enemy_half = match piece.color() {
+        Color::White => square.rank() >= Rank::Five,
+        Color::Black => square.rank() <= Rank::Four,
+    };
+
+    in_enemy_half && safe_mobility <= 1
+}
+
+#[cfg(test)]
+mod mobility_tests {
+    use super::*;
+    use crate::eval::weights::WEIGHTS;
+
+    #[test]
+    fn centralised_knight_is_more_mobile() {
+        let centre = BoardState::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        let corner = BoardState::from_fen("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").unwrap();
+
+        let knight = Piece::Knight(Color::White);
+        assert_eq!(safe_mobility(&centre, knight, Square::D4, Mask(0)), 8);
+        assert_eq!(safe_mobility(&corner, knight, Square::A1, Mask(0)), 2);
+        assert!(
+            evaluate(&centre, Color::White, &WEIGHTS) > evaluate(&corner, Color::White, &WEIGHTS)
+        );
+    }
+
+    #[test]
+    fn enemy_pawn_attacks_are_excluded() {
+        let state = BoardState::from_fen("4k3/8/2p5/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+
+        let attacks = pawn_attacks(&state, Color::Black);
+        assert_eq!(
+            safe_mobility(&state, Piece::Knight(Color::White), Square::D4, attacks),
+            7
+        );
+    }
+
+    #[test]
+    fn trapped_bishop() {
+        let state = BoardState::from_fen("4k3/B1p5/1p6/8/8/8/8/4K3 w - - 0 1").unwrap();
+
+        let bishop = Piece::Bishop(Color::White);
+        let attacks = pawn_attacks(&state, Color::Black);
+        let mobility = safe_mobility(&state, bishop, Square::A7, attacks);
+
+        assert_eq!(mobility, 1);
+        assert!(is_trapped(bishop, Square::A7, mobility));
+        assert!(!is_trapped(bishop, Square::A3, mobility));
+    }
+}
@@ -0,0 +1,227 @@
+pub mod activity;
+pub mod king_safety;
+pub mod mobility;
+#[cfg(feature = "nnue")]
+pub mod nnue;
+pub mod pawns;
+pub mod pst;
+pub mod weights;
+
+use crate::{
+    board::{
+        mask::Mask,
+        piece::{Color, Piece},
+        square::{File, Rank},
+        BoardState,
+    },
+    move_gen::move_masks::{BLACK_PAWN_CAPTURE_MASKS, WHITE_PAWN_CAPTURE_MASKS},
+};
+
+use weights::WEIGHTS;
+
+/// Evaluation score in centipawns.
+pub type Score = i32;
+
+/// Evaluates the position from the point of view of the side to move, using the default weights.
+pub fn evaluate(state: &BoardState) -> Score {
+    evaluate_with(state, &WEIGHTS)
+}
+
+/// Evaluates the position from the point of view of the side to move.
+pub fn evaluate_with(state: &BoardState, weights: &Weights) -> Score {
+    let score =
+        evaluate_side(state, Color::White, weights) - evaluate_side(state, Color::Black, weights);
+
+    match state.active_color() {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+fn evaluate_side(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    material(state, color, weights)
+        + pst::evaluate(state, color, weights)
+        + pawns::evaluate(state, color, weights)
+        + king_safety::evaluate(state, color, weights)
+        + mobility::evaluate(state, color, weights)
+        + activity::evaluate(state, color, weights)
+}
+
+/// Tunable evaluation parameters. Arrays indexed by piece kind use the order pawn, knight,
+/// bishop, rook, queen, king (see `Piece::kind_index`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Weights {
+    pub material: [Score; 6],
+    /// Piece-square tables, laid out as described in `pst::table_index`
+    pub pst: [[Score; 64]; 6],
+
+    // Pawn structure
+    pub doubled_pawn: Score,
+    pub isolated_pawn: Score,
+    /// Passed pawn bonus by rank, from the pawn's own side
+    pub passed_pawn: [Score; 8],
+
+    // King safety
+    pub king_attacker: [Score; 6],
+    pub king_attack_scale: [Score; 8],
+    pub pawn_shield: [Score; 2],
+    pub king_open_file: Score,
+    pub king_semi_open_file: Score,
+
+    // Mobility and piece activity
+    pub mobility: [Score; 6],
+    pub trapped_piece: [Score; 6],
+    pub bishop_pair: Score,
+    pub rook_open_file: Score,
+    pub rook_semi_open_file: Score,
+    pub rook_seventh_rank: Score,
+    pub knight_outpost: Score,
+}
+
+impl Weights {
+    /// Mutable references to every weight the evaluation reads, in declaration order. Used by
+    /// the tuner to adjust weights one at a time. Weights for the king that are never read, and
+    /// pawn weights for the first and last ranks, are left out.
+    pub fn params_mut(&mut self) -> Vec<&mut Score> {
+        let mut params = Vec::new();
+
+        params.extend(self.material[..5].iter_mut());
+        let (pawn_table, piece_tables) = self.pst.split_first_mut().unwrap();
+        params.extend(pawn_table[8..56].iter_mut());
+        params.extend(piece_tables.iter_mut().flatten());
+
+        params.push(&mut self.doubled_pawn);
+        params.push(&mut self.isolated_pawn);
+        params.extend(self.passed_pawn[1..7].iter_mut());
+
+        params.extend(self.king_attacker[..5].iter_mut());
+        // Without attackers there's no attack weight to scale
+        params.extend(self.king_attack_scale[1..].iter_mut());
+        params.extend(self.pawn_shield.iter_mut());
+        params.push(&mut self.king_open_file);
+        params.push(&mut self.king_semi_open_file);
+
+        // Only knights, bishops, rooks and queens are counted
+        params.extend(self.mobility[1..5].iter_mut());
+        params.extend(self.trapped_piece[1..5].iter_mut());
+        params.push(&mut self.bishop_pair);
+        params.push(&mut self.rook_open_file);
+        params.push(&mut self.rook_semi_open_file);
+        params.push(&mut self.rook_seventh_rank);
+        params.push(&mut self.knight_outpost);
+
+        params
+    }
+
+    /// Zeroes the king safety terms.
+    pub fn disable_king_safety(&mut self) {
+        self.king_attacker = [0; 6];
+        self.king_attack_scale = [0; 8];
+        self.pawn_shield = [0; 2];
+        self.king_open_file = 0;
+        self.king_semi_open_file = 0;
+    }
+
+    /// Zeroes the mobility and trapped piece terms.
+    pub fn disable_mobility(&mut self) {
+        self.mobility = [0; 6];
+        self.trapped_piece = [0; 6];
+    }
+
+    /// Zeroes the piece activity terms (bishop pair, rook files and knight outposts).
+    pub fn disable_piece_activity(&mut self) {
+        self.bishop_pair = 0;
+        self.rook_open_file = 0;
+        self.rook_semi_open_file = 0;
+        self.rook_seventh_rank = 0;
+        self.knight_outpost = 0;
+    }
+}
+
+pub fn material(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    let mut score = 0;
+
+    for piece in pieces(color) {
+        score += weights.material[piece.kind_index()] * state.mask(piece).count() as Score;
+    }
+
+    score
+}
+
+/// All six pieces of the given color, in mask index order.
+pub fn pieces(color: Color) -> [Piece; 6] {
+    [
+        Piece::Pawn(color),
+        Piece::Knight(color),
+        Piece::Bishop(color),
+        Piece::Rook(color),
+        Piece::Queen(color),
+        Piece::King(color),
+    ]
+}
+
+/// Squares attacked by `color`'s pawns.
+pub fn pawn_attacks(state: &BoardState, color: Color) -> Mask {
+    let capture_masks = match color {
+        Color::White => &WHITE_PAWN_CAPTURE_MASKS,
+        Color::Black => &BLACK_PAWN_CAPTURE_MASKS,
+    };
+
+    let mut attacks = Mask(0);
+    for square in state.mask(Piece::Pawn(color)).iter() {
+        attacks |= capture_masks[square as usize];
+    }
+
+    attacks
+}
+
+pub fn file_mask(file: File) -> Mask {
+    const A_FILE: u64 = 0x0101010101010101;
+    Mask(A_FILE << file as u8)
+}
+
+pub fn rank_mask(rank: Rank) -> Mask {
+    const FIRST_RANK: u64 = 0xFF;
+    Mask(FIRST_RANK << (rank as u8 * 8))
+}
+
+#[cfg(test)]
+mod eval_tests {
+    use super::*;
+    use crate::board::START_FEN;
+
+    #[test]
+    fn start_position_is_balanced() {
+        let state = BoardState::from_fen(START_FEN).unwrap();
+
+        assert_eq!(evaluate(&state), 0);
+    }
+
+    #[test]
+    fn leaves_out_unread_params() {
+        let mut weights = WEIGHTS.clone();
+        for param in weights.params_mut() {
+            *param += 1;
+        }
+
+        assert_eq!(weights.material[5], WEIGHTS.material[5]);
+        assert_eq!(weights.material[4], WEIGHTS.material[4] + 1);
+        assert_eq!(weights.pst[0][..8], WEIGHTS.pst[0][..8]);
+        assert_eq!(weights.pst[0][8], WEIGHTS.pst[0][8] + 1);
+        assert_eq!(weights.pst[5][0], WEIGHTS.pst[5][0] + 1);
+        assert_eq!(weights.passed_pawn[7], WEIGHTS.passed_pawn[7]);
+        assert_eq!(weights.king_attacker[5], WEIGHTS.king_attacker[5]);
+        assert_eq!(weights.mobility[0], WEIGHTS.mobility[0]);
+        assert_eq!(weights.mobility[5], WEIGHTS.mobility[5]);
+        assert_eq!(weights.knight_outpost, WEIGHTS.knight_outpost + 1);
+    }
+
+    #[test]
+    fn eval_is_side_to_move_relative() {
+        let white = BoardState::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let black = BoardState::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+
+        assert!(evaluate(&white) > 0);
+        assert_eq!(evaluate(&white), -evaluate(&black));
+    }
+}
@@ -0,0 +1,303 @@
+//! Optional neural network evaluation, enabled with the `nnue` cargo feature.
+//!
+//! The network is a simple (768 -> HIDDEN) x 2 -> 1 perceptron. Each of the 768 inputs is one
+//! (piece, square) pair taken straight from the 12 piece masks in a `BoardState`, seen from both
+//! the white and black perspective. The first layer output (the accumulator) is updated
+//! incrementally as moves are made, so a full refresh is only needed when a new position is set.
+
+use std::{fmt, fs, path::Path};
+
+use crate::board::{mask::Mask, piece::Color, BoardState};
+
+use super::Score;
+
+pub const INPUTS: usize = 768;
+pub const HIDDEN: usize = 256;
+
+/// Quantisation factor of the feature transformer weights and biases
+const QA: i32 = 255;
+/// Quantisation factor of the output layer weights
+const QB: i32 = 64;
+/// Converts the network output into centipawns
+const EVAL_SCALE: i32 = 400;
+
+/// Number of little-endian `i16` values in a network file.
+const FILE_VALUES: usize = INPUTS * HIDDEN + HIDDEN + 2 * HIDDEN + 1;
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(std::io::Error),
+    BadSize { expected: usize, found: usize },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::BadSize { expected, found } => {
+                write!(f, "expected {expected} bytes, found {found}")
+            }
+        }
+    }
+}
+
+/// Quantised network weights. Network files store the feature weights (input-major), feature
+/// biases, output weights (side to move first) and output bias as consecutive little-endian
+/// `i16`s.
+#[derive(Debug, Clone)]
+pub struct Network {
+    feature_weights: Vec<[i16; HIDDEN]>,
+    feature_bias: [i16; HIDDEN],
+    output_weights: [i16; 2 * HIDDEN],
+    output_bias: i16,
+}
+
+impl Network {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NetworkError> {
+        let bytes = fs::read(path).map_err(NetworkError::Io)?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NetworkError> {
+        if bytes.len() != FILE_VALUES * 2 {
+            return Err(NetworkError::BadSize {
+                expected: FILE_VALUES * 2,
+                found: bytes.len(),
+            });
+        }
+
+        let mut values = bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]));
+
+        let mut feature_weights = vec![[0; HIDDEN]; INPUTS];
+        for row in feature_weights.iter_mut() {
+            row.fill_with(|| values.next().unwrap());
+        }
+
+        let mut feature_bias = [0; HIDDEN];
+        feature_bias.fill_with(|| values.next().unwrap());
+
+        let mut output_weights = [0; 2 * HIDDEN];
+        output_weights.fill_with(|| values.next().unwrap());
+
+        let output_bias = values.next().unwrap();
+
+        Ok(Self {
+            feature_weights,
+            feature_bias,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    /// Runs the output layer on an up to date accumulator, returning a score from the point of
+    /// view of `side_to_move`.
+    pub fn evaluate(&self, accumulator: &Accumulator, side_to_move: Color) -> Score {
+        let (us, them) = match side_to_move {
+            Color::White => (&accumulator.white, &accumulator.black),
+            Color::Black => (&accumulator.black, &accumulator.white),
+        };
+
+        let (us_weights, them_weights) = self.output_weights.split_at(HIDDEN);
+
+        let mut output = screlu_dot(us, us_weights) + screlu_dot(them, them_weights);
+        output /= QA as i64;
+        output += self.output_bias as i64;
+
+        (output * EVAL_SCALE as i64 / (QA * QB) as i64) as Score
+    }
+}
+
+/// Dot product of the squared clipped ReLU of `inputs` with `weights`. Written as a plain loop
+/// over fixed size arrays so the compiler can vectorise it. A single term can take up to 46
+/// bits, so the sum is kept in an `i64`.
+fn screlu_dot(inputs: &[i16; HIDDEN], weights: &[i16]) -> i64 {
+    let mut sum = 0;
+
+    for (&input, &weight) in inputs.iter().zip(weights) {
+        let clipped = (input as i32).clamp(0, QA);
+        sum += (clipped * clipped) as i64 * weight as i64;
+    }
+
+    sum
+}
+
+/// First layer output for both perspectives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accumulator {
+    white: [i16; HIDDEN],
+    black: [i16; HIDDEN],
+}
+
+impl Accumulator {
+    /// Builds the accumulator for `state` from scratch.
+    pub fn new(network: &Network, state: &BoardState) -> Self {
+        let mut accumulator = Self {
+            white: network.feature_bias,
+            black: network.feature_bias,
+        };
+
+        for i in 0..12 {
+            for square in state.mask_at(i).iter() {
+                accumulator.add_feature(network, i, square as usize);
+            }
+        }
+
+        accumulator
+    }
+
+    /// Returns the accumulator for `new`, given that `self` is the accumulator for `old`. Only
+    /// the pieces that differ between the two positions are touched.
+    pub fn update(&self, network: &Network, old: &BoardState, new: &BoardState) -> Self {
+        let mut accumulator = self.clone();
+
+        for i in 0..12 {
+            let old_mask = old.mask_at(i);
+            let new_mask = new.mask_at(i);
+            let changed = old_mask ^ new_mask;
+
+            if changed == Mask(0) {
+                continue;
+            }
+
+            for square in (changed & old_mask).iter() {
+                accumulator.remove_feature(network, i, square as usize);
+            }
+            for square in (changed & new_mask).iter() {
+                accumulator.add_feature(network, i, square as usize);
+            }
+        }
+
+        accumulator
+    }
+
+    fn add_feature(&mut self, network: &Network, mask_index: usize, square: usize) {
+        let (white, black) = feature_indices(mask_index, square);
+
+        for (value, weight) in self.white.iter_mut().zip(&network.feature_weights[white]) {
+            *value = value.wrapping_add(*weight);
+        }
+        for (value, weight) in self.black.iter_mut().zip(&network.feature_weights[black]) {
+            *value = value.wrapping_add(*weight);
+        }
+    }
+
+    fn remove_feature(&mut self, network: &Network, mask_index: usize, square: usize) {
+        let (white, black) = feature_indices(mask_index, square);
+
+        for (value, weight) in self.white.iter_mut().zip(&network.feature_weights[white]) {
+            *value = value.wrapping_sub(*weight);
+        }
+        for (value, weight) in self.black.iter_mut().zip(&network.feature_weights[black]) {
+            *value = value.wrapping_sub(*weight);
+        }
+    }
+}
+
+/// Input indices of a piece (given by its mask index) on `square`, from white's and black's
+/// perspective. Black sees the board flipped vertically with the piece colors swapped.
+fn feature_indices(mask_index: usize, square: usize) -> (usize, usize) {
+    let white = mask_index * 64 + square;
+    let black = ((mask_index + 6) % 12) * 64 + (square ^ 56);
+
+    (white, black)
+}
+
+#[cfg(test)]
+mod nnue_tests {
+    use std::sync::Arc;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::board::{moves::Move, Board, START_FEN};
+
+    fn random_network() -> Network {
+        let mut rng = StdRng::seed_from_u64(0);
+        let bytes = (0..FILE_VALUES)
+            .flat_map(|_| rng.gen_range(-64i16..64).to_le_bytes())
+            .collect::<Vec<u8>>();
+
+        Network::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn rejects_bad_size() {
+        assert!(matches!(
+            Network::from_bytes(&[0; 10]),
+            Err(NetworkError::BadSize { found: 10, .. })
+        ));
+    }
+
+    #[test]
+    fn large_weights_dont_overflow() {
+        let inputs = [QA as i16; HIDDEN];
+        let weights = [i16::MAX; HIDDEN];
+
+        assert_eq!(
+            screlu_dot(&inputs, &weights),
+            HIDDEN as i64 * (QA * QA) as i64 * i16::MAX as i64
+        );
+    }
+
+    #[test]
+    fn incremental_update_matches_refresh() {
+        let network = random_network();
+        let mut board = Board::new(START_FEN).unwrap();
+
+        let mut accumulator = Accumulator::new(&network, board.current_position());
+
+        // Includes a capture and castling
+        for mv in [
+            "e2e4", "d7d5", "e4d5", "g8f6", "g1f3", "f6d5", "f1c4", "e7e6", "e1g1",
+        ] {
+            let old = board.current_position().clone();
+            board
+                .make_move_unchecked(Move::from_long_algebraic(mv).unwrap())
+                .unwrap();
+
+            accumulator = accumulator.update(&network, &old, board.current_position());
+            assert_eq!(
+                accumulator,
+                Accumulator::new(&network, board.current_position())
+            );
+        }
+    }
+
+    #[test]
+    fn mirrored_positions_evaluate_equally() {
+        let network = random_network();
+
+        let white = BoardState::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let black = BoardState::from_fen("4k3/4p3/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+
+        assert_eq!(
+            network.evaluate(&Accumulator::new(&network, &white), Color::White),
+            network.evaluate(&Accumulator::new(&network, &black), Color::Black)
+        );
+    }
+
+    #[test]
+    fn board_uses_network() {
+        let network = Arc::new(random_network());
+        let mut board = Board::new(START_FEN).unwrap();
+        board.set_network(Some(network.clone()));
+
+        board
+            .make_move(Move::from_long_algebraic("e2e4").unwrap())
+            .unwrap();
+        let expected = network.evaluate(
+            &Accumulator::new(&network, board.current_position()),
+            Color::Black,
+        );
+        assert_eq!(board.evaluate(), expected);
+
+        board.unmake_move().unwrap();
+        let expected = network.evaluate(
+            &Accumulator::new(&network, board.current_position()),
+            Color::White,
+        );
+        assert_eq!(board.evaluate(), expected);
+    }
+}
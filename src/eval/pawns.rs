@@ -0,0 +1,117 @@
+use crate::board::{
+    mask::Mask,
+    piece::{Color, Piece},
+    square::{File, Square},
+    BoardState,
+};
+
+use super::{
+    activity::{attack_span, ranks_ahead},
+    file_mask, Score, Weights,
+};
+
+/// Pawn structure score for `color`: penalties for doubled and isolated pawns and bonuses for
+/// passed pawns.
+pub fn evaluate(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    passed_pawns(state, color, weights)
+        - doubled_pawns(state, color, weights)
+        - isolated_pawns(state, color, weights)
+}
+
+/// Penalty for every pawn beyond the first on each file.
+pub fn doubled_pawns(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    let pawns = state.mask(Piece::Pawn(color));
+    let mut penalty = 0;
+
+    for file in (0..8).filter_map(File::from_u8) {
+        let count = (pawns & file_mask(file)).count() as Score;
+        penalty += weights.doubled_pawn * (count - 1).max(0);
+    }
+
+    penalty
+}
+
+/// Penalty for pawns with no friendly pawns on the adjacent files.
+pub fn isolated_pawns(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    let pawns = state.mask(Piece::Pawn(color));
+    let mut penalty = 0;
+
+    for square in pawns.iter() {
+        if pawns & neighbouring_files(square) == Mask(0) {
+            penalty += weights.isolated_pawn;
+        }
+    }
+
+    penalty
+}
+
+/// Bonus for pawns that no enemy pawn can stop, by how far they have advanced.
+pub fn passed_pawns(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    let enemy_pawns = state.mask(Piece::Pawn(color.swapped()));
+    let mut score = 0;
+
+    for square in state.mask(Piece::Pawn(color)).iter() {
+        let front_span = file_mask(square.file()) & ranks_ahead(square, color);
+
+        if enemy_pawns & (front_span | attack_span(square, color)) == Mask(0) {
+            let rank = match color {
+                Color::White => square.rank() as usize,
+                Color::Black => 7 - square.rank() as usize,
+            };
+            score += weights.passed_pawn[rank];
+        }
+    }
+
+    score
+}
+
+/// The files either side of `square`'s file.
+fn neighbouring_files(square: Square) -> Mask {
+    let file = file_mask(square.file());
+    let mut files = Mask(0);
+
+    if square.file() != File::A {
+        files |= Mask(file.0 >> 1);
+    }
+    if square.file() != File::H {
+        files |= Mask(file.0 << 1);
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod pawns_tests {
+    use super::*;
+    use crate::eval::weights::WEIGHTS;
+
+    #[test]
+    fn doubled_and_isolated_pawns() {
+        // The c-pawns are doubled and isolated, the g- and h-pawns are neither
+        let state = BoardState::from_fen("4k3/8/8/8/2P5/2P5/6PP/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(
+            doubled_pawns(&state, Color::White, &WEIGHTS),
+            WEIGHTS.doubled_pawn
+        );
+        assert_eq!(
+            isolated_pawns(&state, Color::White, &WEIGHTS),
+            2 * WEIGHTS.isolated_pawn
+        );
+    }
+
+    #[test]
+    fn passed_pawns_by_rank() {
+        // The d6 and h3 pawns are passed, but the a4 and b5 pawns can stop each other
+        let state = BoardState::from_fen("4k3/8/3P4/1p6/P7/7p/8/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(
+            passed_pawns(&state, Color::White, &WEIGHTS),
+            WEIGHTS.passed_pawn[5]
+        );
+        assert_eq!(
+            passed_pawns(&state, Color::Black, &WEIGHTS),
+            WEIGHTS.passed_pawn[5]
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use crate::board::{piece::Color, square::Square, BoardState};
+
+use super::{pieces, Score, Weights};
+
+/// Piece-square table score for `color`'s pieces.
+pub fn evaluate(state: &BoardState, color: Color, weights: &Weights) -> Score {
+    let mut score = 0;
+
+    for piece in pieces(color) {
+        let table = &weights.pst[piece.kind_index()];
+
+        for square in state.mask(piece).iter() {
+            score += table[table_index(square, color)];
+        }
+    }
+
+    score
+}
+
+/// Index into a piece-square table. Tables are laid out as white sees the board, starting from
+/// a8, and are mirrored vertically for black.
+pub fn table_index(square: Square, color: Color) -> usize {
+    match color {
+        Color::White => square as usize ^ 56,
+        Color::Black => square as usize,
+    }
+}
+
+#[cfg(test)]
+mod pst_tests {
+    use super::*;
+    use crate::eval::weights::WEIGHTS;
+
+    #[test]
+    fn tables_are_mirrored_for_black() {
+        let state = BoardState::from_fen("3nk3/8/8/4p3/4P3/8/8/3NK3 w - - 0 1").unwrap();
+
+        assert_eq!(table_index(Square::A8, Color::White), 0);
+        assert_eq!(table_index(Square::A8, Color::Black), 56);
+        assert_eq!(
+            evaluate(&state, Color::White, &WEIGHTS),
+            evaluate(&state, Color::Black, &WEIGHTS)
+        );
+    }
+}
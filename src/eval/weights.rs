@@ -0,0 +1,92 @@
+use super::Weights;
+
+#[rustfmt::skip]
+pub const WEIGHTS: Weights = Weights {
+    material: [100, 320, 330, 500, 900, 0],
+    pst: [
+        // Pawn
+        [
+              0,   0,   0,   0,   0,   0,   0,   0,
+             20,  20,  20,  20,  20,  20,  20,  20,
+             10,  10,  15,  20,  20,  15,  10,  10,
+              5,   5,  10,  25,  25,  10,   5,   5,
+              0,   0,   0,  20,  20,   0,   0,   0,
+              5,  -5, -10,   0,   0, -10,  -5,   5,
+              5,  10,  10, -20, -20,  10,  10,   5,
+              0,   0,   0,   0,   0,   0,   0,   0,
+        ],
+        // Knight
+        [
+            -50, -40, -30, -30, -30, -30, -40, -50,
+            -40, -20,   0,   0,   0,   0, -20, -40,
+            -30,   0,  10,  15,  15,  10,   0, -30,
+            -30,   5,  15,  20,  20,  15,   5, -30,
+            -30,   0,  15,  20,  20,  15,   0, -30,
+            -30,   5,  10,  15,  15,  10,   5, -30,
+            -40, -20,   0,   5,   5,   0, -20, -40,
+            -50, -40, -30, -30, -30, -30, -40, -50,
+        ],
+        // Bishop
+        [
+            -20, -10, -10, -10, -10, -10, -10, -20,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -10,   0,   5,  10,  10,   5,   0, -10,
+            -10,   5,   5,  10,  10,   5,   5, -10,
+            -10,   0,  10,  10,  10,  10,   0, -10,
+            -10,  10,  10,  10,  10,  10,  10, -10,
+            -10,   5,   0,   0,   0,   0,   5, -10,
+            -20, -10, -10, -10, -10, -10, -10, -20,
+        ],
+        // Rook
+        [
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   5,   5,   5,   5,   5,   5,   0,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+              0,   0,   0,   5,   5,   0,   0,   0,
+        ],
+        // Queen
+        [
+            -20, -10, -10,  -5,  -5, -10, -10, -20,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -10,   0,   5,   5,   5,   5,   0, -10,
+             -5,   0,   5,   5,   5,   5,   0,  -5,
+              0,   0,   5,   5,   5,   5,   0,  -5,
+            -10,   5,   5,   5,   5,   5,   0, -10,
+            -10,   0,   5,   0,   0,   0,   0, -10,
+            -20, -10, -10,  -5,  -5, -10, -10, -20,
+        ],
+        // King
+        [
+            -30, -30, -30, -30, -30, -30, -30, -30,
+            -30, -30, -30, -30, -30, -30, -30, -30,
+            -20, -20, -20, -20, -20, -20, -20, -20,
+            -20, -20, -20, -20, -20, -20, -20, -20,
+            -10, -10, -10, -15, -15, -10, -10, -10,
+             -5,  -5, -10, -10, -10, -10,  -5,  -5,
+             10,  10,   0,  -5,  -5,   0,  10,  10,
+             15,  20,  10,   0,   0,  10,  20,  15,
+        ],
+    ],
+
+    doubled_pawn: 10,
+    isolated_pawn: 10,
+    passed_pawn: [0, 5, 10, 20, 35, 60, 100, 0],
+
+    king_attacker: [5, 10, 10, 20, 40, 0],
+    king_attack_scale: [0, 0, 50, 75, 88, 94, 97, 99],
+    pawn_shield: [10, 5],
+    king_open_file: 25,
+    king_semi_open_file: 10,
+
+    mobility: [0, 4, 5, 2, 1, 0],
+    trapped_piece: [0, 30, 50, 40, 60, 0],
+    bishop_pair: 30,
+    rook_open_file: 20,
+    rook_semi_open_file: 10,
+    rook_seventh_rank: 20,
+    knight_outpost: 20,
+};
@@ -1,2 +1,7 @@
 pub mod board;
+pub mod book;
+pub mod eval;
 pub mod move_gen;
+pub mod search;
+pub mod tablebase;
+pub mod uci;
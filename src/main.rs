@@ -1,5 +1,28 @@
-use chess::board::{moves::Move, Board, START_FEN};
-use rand::{thread_rng, Rng};
+use std::{
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use chess::{
+    board::{
+        moves::{Move, PackedMove},
+        piece::Color,
+        Board, START_FEN,
+    },
+    book::{Book, Selection},
+    eval::{weights::WEIGHTS, Score, Weights},
+    search::{
+        self,
+        tt::{self, TranspositionTable},
+        SearchConfig, SearchControl, SearchInfo, SearchLimits,
+    },
+    tablebase::{Probe, Syzygy},
+    uci::{self, OptionKind, OptionValue, UciOption},
+};
+
+#[cfg(feature = "nnue")]
+use chess::eval::nnue::Network;
 
 #[derive(Debug, PartialEq, Eq)]
 enum Command {
@@ -7,77 +30,496 @@ enum Command {
     IsReady,
     UciNewGame,
     Position(Vec<String>),
-    Go,
+    Go(Vec<String>),
+    SetOption(Vec<String>),
+    Stop,
+    PonderHit,
     Quit,
 }
 
 fn parse_command(input: &str) -> Option<Command> {
-    let mut parts = input.split(" ");
-
-    match parts.next() {
-        Some(str) => match str {
-            "uci\n" => Some(Command::Uci),
-            "isready\n" => Some(Command::IsReady),
-            "ucinewgame\n" => Some(Command::UciNewGame),
-            "position" => Some(Command::Position(
-                parts.map(|s| s.to_string()).collect::<Vec<String>>(),
-            )),
-            "go\n" => Some(Command::Go),
-            "quit\n" => Some(Command::Quit),
-            _ => None,
-        },
+    let mut parts = input.split_whitespace();
+    let args = |parts: std::str::SplitWhitespace| parts.map(|s| s.to_string()).collect();
+
+    match parts.next()? {
+        "uci" => Some(Command::Uci),
+        "isready" => Some(Command::IsReady),
+        "ucinewgame" => Some(Command::UciNewGame),
+        "position" => Some(Command::Position(args(parts))),
+        "go" => Some(Command::Go(args(parts))),
+        "setoption" => Some(Command::SetOption(args(parts))),
+        "stop" => Some(Command::Stop),
+        "ponderhit" => Some(Command::PonderHit),
+        "quit" => Some(Command::Quit),
         _ => None,
     }
 }
 
-fn process_command(command: &Command, board: &mut Board) -> Option<String> {
-    match command {
-        Command::Uci => Some(String::from(
-            "id name Chress\nid author Luc de Cafmeyer\nuciok",
-        )),
-        Command::IsReady => Some(String::from("readyok")),
-        Command::UciNewGame => {
-            *board = Board::new(START_FEN).unwrap();
-            Some(String::from("readyok"))
+/// Every option the engine supports, in the order they're declared to the GUI.
+const OPTIONS: &[UciOption] = &[
+    UciOption::spin("Hash", tt::DEFAULT_SIZE_MB as i64, 1, 4096),
+    UciOption::button("Clear Hash"),
+    UciOption::spin("Threads", 1, 1, 256),
+    UciOption::spin("MultiPV", 1, 1, 256),
+    UciOption::check("Ponder", false),
+    UciOption::spin("Move Overhead", 50, 0, 5000),
+    UciOption::spin("Contempt", 0, -100, 100),
+    // Opening book
+    UciOption::string("BookFile", "book.bin"),
+    UciOption::combo("BookSelection", "Weighted", &["Weighted", "Best"]),
+    UciOption::check("OwnBook", false),
+    // Endgame tablebases
+    UciOption::string("SyzygyPath", ""),
+    // Neural network evaluation, used instead of the evaluation terms below when set
+    #[cfg(feature = "nnue")]
+    UciOption::string("EvalFile", ""),
+    // Evaluation terms
+    UciOption::check("KingSafety", true),
+    UciOption::check("Mobility", true),
+    UciOption::check("PieceActivity", true),
+    // Search techniques
+    UciOption::check("NullMovePruning", true),
+    UciOption::check("LateMoveReductions", true),
+    UciOption::check("FutilityPruning", true),
+    UciOption::check("ReverseFutilityPruning", true),
+    UciOption::check("Razoring", true),
+    UciOption::check("CheckExtensions", true),
+];
+
+/// Handles `position [startpos | fen <fen>] [moves <moves>...]`.
+fn set_position(args: &[String], board: &mut Board) -> Option<String> {
+    let moves_index = args.iter().position(|arg| arg == "moves");
+    let (position, moves) = args.split_at(moves_index.unwrap_or(args.len()));
+
+    match position.first().map(String::as_str) {
+        Some("startpos") => board.load_from_fen(START_FEN).unwrap(),
+        Some("fen") => {
+            let fen = position[1..].join(" ");
+            if board.load_from_fen(&fen).is_err() {
+                return Some(format!("info string invalid FEN: {fen}"));
+            }
+        }
+        _ => return Some(String::from("info string expected startpos or fen")),
+    }
+
+    for mv in moves.iter().skip(1) {
+        let legal = Move::from_long_algebraic(mv).and_then(|parsed| {
+            board
+                .legal_moves()
+                .into_iter()
+                .find(|legal| legal.to_move() == parsed)
+        });
+
+        match legal {
+            Some(legal) => board.make_packed_move(legal).unwrap(),
+            None => return Some(format!("info string illegal move: {mv}")),
+        }
+    }
+
+    None
+}
+
+/// Turns the arguments of a `go` command into search limits for the side to move.
+fn parse_limits(args: &[String], side_to_move: Color, move_overhead: Duration) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let mut remaining = None;
+    let mut increment = Duration::ZERO;
+    let mut moves_to_go = None;
+
+    let millis = |value: Option<&String>| {
+        value
+            .and_then(|v| v.parse::<i64>().ok())
+            .map(|ms| Duration::from_millis(ms.max(0) as u64))
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), side_to_move) {
+            ("depth", _) => limits.depth = args.next().and_then(|v| v.parse().ok()),
+            ("nodes", _) => limits.nodes = args.next().and_then(|v| v.parse().ok()),
+            ("movetime", _) => limits.movetime = millis(args.next()),
+            ("movestogo", _) => moves_to_go = args.next().and_then(|v| v.parse().ok()),
+            ("wtime", Color::White) | ("btime", Color::Black) => remaining = millis(args.next()),
+            ("winc", Color::White) | ("binc", Color::Black) => {
+                increment = millis(args.next()).unwrap_or_default()
+            }
+            ("wtime" | "btime" | "winc" | "binc", _) => {
+                args.next();
+            }
+            _ => {}
+        }
+    }
+
+    if limits.movetime.is_none() {
+        if let Some(remaining) = remaining {
+            limits.movetime = Some(search::allocate_time(
+                remaining,
+                increment,
+                moves_to_go,
+                move_overhead,
+            ));
+        }
+    }
+
+    limits
+}
+
+/// Formats a score as `cp <centipawns>` or `mate <moves>`.
+fn format_score(score: Score) -> String {
+    match search::moves_to_mate(score) {
+        Some(moves) => format!("mate {moves}"),
+        None => format!("cp {score}"),
+    }
+}
+
+/// Formats search progress as a UCI `info` line.
+fn format_info(info: &SearchInfo) -> String {
+    match info {
+        SearchInfo::Iteration(result) => {
+            let lines = result.lines.iter().enumerate().map(|(i, (_, score, pv))| {
+                let pv = pv
+                    .iter()
+                    .map(|mv| mv.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                format!(
+                    "info multipv {} depth {} seldepth {} score {} nodes {} nps {} time {} hashfull {} pv {}",
+                    i + 1,
+                    result.depth,
+                    result.seldepth,
+                    format_score(*score),
+                    result.nodes,
+                    result.nps(),
+                    result.time.as_millis(),
+                    result.hashfull,
+                    pv
+                )
+            });
+
+            lines.collect::<Vec<_>>().join("\n")
+        }
+        SearchInfo::CurrentMove { depth, mv, number } => {
+            format!("info depth {depth} currmove {mv} currmovenumber {number}")
+        }
+        SearchInfo::Progress {
+            depth,
+            seldepth,
+            nodes,
+            time,
+            hashfull,
+        } => format!(
+            "info depth {depth} seldepth {seldepth} nodes {nodes} nps {} time {} hashfull {hashfull}",
+            search::nps(*nodes, *time),
+            time.as_millis()
+        ),
+    }
+}
+
+/// Everything the engine keeps between commands.
+struct Engine {
+    board: Board,
+    config: SearchConfig,
+    tt: Arc<TranspositionTable>,
+    /// Whether the GUI may send `go ponder`, in which case `bestmove` includes a ponder move
+    ponder: bool,
+    move_overhead: Duration,
+    // Evaluation terms that are switched on
+    king_safety: bool,
+    mobility: bool,
+    piece_activity: bool,
+    own_book: bool,
+    book_file: String,
+    book_selection: Selection,
+    /// The loaded book, if `OwnBook` is on and loading it worked
+    book: Option<Book>,
+    tablebases: Option<Arc<dyn Probe>>,
+    /// The network from `EvalFile`, if one was loaded
+    #[cfg(feature = "nnue")]
+    network: Option<Arc<Network>>,
+    /// The search currently running in the background, if any
+    search: Option<(JoinHandle<()>, Arc<SearchControl>)>,
+}
+
+impl Engine {
+    fn new() -> Self {
+        let mut engine = Self {
+            board: Board::new(START_FEN).unwrap(),
+            config: SearchConfig::default(),
+            tt: Arc::new(TranspositionTable::new(tt::DEFAULT_SIZE_MB)),
+            ponder: false,
+            move_overhead: Duration::ZERO,
+            king_safety: true,
+            mobility: true,
+            piece_activity: true,
+            own_book: false,
+            book_file: String::new(),
+            book_selection: Selection::Weighted,
+            book: None,
+            tablebases: None,
+            #[cfg(feature = "nnue")]
+            network: None,
+            search: None,
+        };
+
+        for option in OPTIONS {
+            if option.kind != OptionKind::Button {
+                engine.set_option(option.name, option.default_value());
+            }
+        }
+
+        engine
+    }
+
+    /// Applies a value that was already validated against the option's declaration, returning
+    /// a message for the GUI if something went wrong.
+    fn set_option(&mut self, name: &str, value: OptionValue) -> Option<String> {
+        match (name, value) {
+            ("Hash", OptionValue::Spin(size)) => {
+                self.tt = Arc::new(TranspositionTable::new(size as usize))
+            }
+            ("Clear Hash", _) => self.tt.clear(),
+            ("Threads", OptionValue::Spin(threads)) => self.config.threads = threads as usize,
+            ("MultiPV", OptionValue::Spin(lines)) => self.config.multi_pv = lines as usize,
+            ("Move Overhead", OptionValue::Spin(ms)) => {
+                self.move_overhead = Duration::from_millis(ms as u64)
+            }
+            ("Contempt", OptionValue::Spin(contempt)) => self.config.contempt = contempt as Score,
+            ("BookFile", OptionValue::String(path)) => {
+                self.book_file = path;
+                return self.load_book();
+            }
+            ("BookSelection", OptionValue::Combo(selection)) => {
+                self.book_selection = match selection.as_str() {
+                    "Best" => Selection::Best,
+                    _ => Selection::Weighted,
+                }
+            }
+            ("SyzygyPath", OptionValue::String(path)) => {
+                let syzygy = Syzygy::open(&path);
+                let found = syzygy.len();
+
+                self.tablebases = match syzygy.is_empty() {
+                    true => None,
+                    false => Some(Arc::new(syzygy)),
+                };
+                if !path.is_empty() {
+                    return Some(format!("info string Found {found} tablebases"));
+                }
+            }
+            #[cfg(feature = "nnue")]
+            ("EvalFile", OptionValue::String(path)) => {
+                self.network = None;
+                if path.is_empty() {
+                    return None;
+                }
+
+                match Network::load(&path) {
+                    Ok(network) => self.network = Some(Arc::new(network)),
+                    Err(err) => {
+                        return Some(format!("info string Could not load the network: {err}"))
+                    }
+                }
+            }
+            ("OwnBook", OptionValue::Check(value)) => {
+                self.own_book = value;
+                return self.load_book();
+            }
+            (name, OptionValue::Check(value)) => *self.check_option(name) = value,
+            (name, value) => unreachable!("{name} can't be set to {value:?}"),
+        }
+
+        None
+    }
+
+    /// Loads the opening book if `OwnBook` is on, or unloads it otherwise.
+    fn load_book(&mut self) -> Option<String> {
+        self.book = None;
+        if !self.own_book {
+            return None;
+        }
+
+        match Book::load(&self.book_file) {
+            Ok(book) => {
+                self.book = Some(book);
+                None
+            }
+            Err(err) => Some(format!(
+                "info string Could not load the opening book: {err}"
+            )),
+        }
+    }
+
+    /// A book move for the current position, if there's a book and it knows one.
+    fn book_move(&self) -> Option<PackedMove> {
+        let state = self.board.current_position();
+        let mv = self.book.as_ref()?.probe(state, self.book_selection)?;
+        let mv = PackedMove::from_move(mv, state);
+
+        state.is_packed_move_legal(mv).then_some(mv)
+    }
+
+    fn check_option(&mut self, name: &str) -> &mut bool {
+        match name {
+            "Ponder" => &mut self.ponder,
+            "KingSafety" => &mut self.king_safety,
+            "Mobility" => &mut self.mobility,
+            "PieceActivity" => &mut self.piece_activity,
+            "NullMovePruning" => &mut self.config.null_move_pruning,
+            "LateMoveReductions" => &mut self.config.late_move_reductions,
+            "FutilityPruning" => &mut self.config.futility_pruning,
+            "ReverseFutilityPruning" => &mut self.config.reverse_futility_pruning,
+            "Razoring" => &mut self.config.razoring,
+            "CheckExtensions" => &mut self.config.check_extensions,
+            _ => unreachable!("{name} isn't a check option"),
+        }
+    }
+
+    /// Evaluation weights with the switched off terms zeroed.
+    fn weights(&self) -> Weights {
+        let mut weights = WEIGHTS.clone();
+
+        if !self.king_safety {
+            weights.disable_king_safety();
+        }
+        if !self.mobility {
+            weights.disable_mobility();
+        }
+        if !self.piece_activity {
+            weights.disable_piece_activity();
+        }
+
+        weights
+    }
+
+    /// Starts searching the current position on a background thread, which prints `info` lines
+    /// as it goes and `bestmove` when it's done.
+    fn start_search(&mut self, args: &[String]) {
+        self.stop_search();
+
+        let side_to_move = self.board.current_position().active_color();
+        let limits = parse_limits(args, side_to_move, self.move_overhead);
+        let pondering = args.iter().any(|arg| arg == "ponder");
+
+        // Book moves are played straight away, unless the GUI expects the search to keep going
+        // until it's told to stop
+        if !pondering && !args.iter().any(|arg| arg == "infinite") {
+            if let Some(mv) = self.book_move() {
+                println!("bestmove {mv}");
+                return;
+            }
         }
-        Command::Position(moves) => {
-            for mv in moves {
-                let mv = mv.trim();
 
-                if mv == "startpos" {
-                    board.load_from_fen(START_FEN).unwrap();
-                    continue;
+        let control = Arc::new(SearchControl::new(pondering));
+
+        let mut board = self.board.clone();
+        board.set_weights(self.weights());
+        board.set_tablebases(self.tablebases.clone());
+        #[cfg(feature = "nnue")]
+        board.set_network(self.network.clone());
+        let config = self.config;
+        let tt = self.tt.clone();
+        let show_ponder = self.ponder;
+        let search_control = control.clone();
+
+        let handle = thread::spawn(move || {
+            let result = search::search_with_report(
+                &mut board,
+                limits,
+                &config,
+                &tt,
+                &search_control,
+                |info| {
+                    println!("{}", format_info(&info));
+                },
+            );
+
+            let response = match (result.best_move, result.pv.get(1)) {
+                (Some(mv), Some(ponder)) if show_ponder => {
+                    format!("bestmove {mv} ponder {ponder}")
                 }
+                (Some(mv), _) => format!("bestmove {mv}"),
+                (None, _) => String::from("bestmove 0000"),
+            };
+            println!("{response}");
+        });
 
-                let mv = Move::from_long_algebraic(mv)
-                    .expect("UCI move should be in long algebraic notation");
+        self.search = Some((handle, control));
+    }
 
-                board.make_move(mv).expect("UCI move should be legal");
+    /// Stops the background search, waiting for it to print its `bestmove`.
+    fn stop_search(&mut self) {
+        if let Some((handle, control)) = self.search.take() {
+            control.stop();
+            handle.join().unwrap();
+        }
+    }
+}
+
+fn process_command(command: &Command, engine: &mut Engine) -> Option<String> {
+    match command {
+        Command::Uci => {
+            let mut response = String::from("id name Chress\nid author Luc de Cafmeyer\n");
+            for option in OPTIONS {
+                response.push_str(&format!("{option}\n"));
             }
+            response.push_str("uciok");
+            Some(response)
+        }
+        Command::IsReady => Some(String::from("readyok")),
+        Command::UciNewGame => {
+            engine.stop_search();
+            engine.board = Board::new(START_FEN).unwrap();
+            engine.tt.clear();
             None
         }
-        Command::Go => {
-            // Play a random legal move
-            let legal_moves = board.legal_moves();
-            let random_index = thread_rng().gen_range(0..legal_moves.len());
-            let random_move = legal_moves[random_index];
-            Some(format!("bestmove {}", random_move.long_algebraic()))
+        Command::Position(args) => {
+            engine.stop_search();
+            set_position(args, &mut engine.board)
+        }
+        Command::Go(args) => {
+            engine.start_search(args);
+            None
+        }
+        Command::Stop => {
+            engine.stop_search();
+            None
+        }
+        Command::PonderHit => {
+            if let Some((_, control)) = &engine.search {
+                control.ponderhit();
+            }
+            None
+        }
+        Command::SetOption(args) => {
+            engine.stop_search();
+
+            match uci::parse_setoption(OPTIONS, args) {
+                Ok((option, value)) => engine.set_option(option.name, value),
+                Err(err) => Some(format!("info string {err}")),
+            }
+        }
+        Command::Quit => {
+            engine.stop_search();
+            None
         }
-        Command::Quit => None,
     }
 }
 
 fn main() {
-    let mut board = Board::new(START_FEN).unwrap();
+    let mut engine = Engine::new();
 
     loop {
         let mut input = String::new();
-        std::io::stdin().read_line(&mut input).unwrap();
+        if std::io::stdin().read_line(&mut input).unwrap() == 0 {
+            engine.stop_search();
+            break;
+        }
 
         let Some(command) = parse_command(&input) else {
             continue;
         };
-        let response = process_command(&command, &mut board);
+        let response = process_command(&command, &mut engine);
 
         if command == Command::Quit {
             break;
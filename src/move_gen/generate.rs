@@ -0,0 +1,404 @@
+//! Generates the source of `move_masks.rs` and `magics.rs`.
+//!
+//! This module is compiled into `build.rs` as well as the crate, so it only works on plain
+//! `u64` bitboards and can't use anything else from the crate. Magics are searched for with a
+//! fixed seed, so every build produces the same tables.
+
+use std::fmt::Write;
+
+/// Seed for the magic search done by `build.rs`.
+pub const SEED: u64 = 0x5eed_cafe_f00d_d00d;
+
+const KING_DELTAS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const KNIGHT_DELTAS: [(i8, i8); 8] = [
+    (2, 1),
+    (1, 2),
+    (-1, 2),
+    (-2, 1),
+    (-2, -1),
+    (-1, -2),
+    (1, -2),
+    (2, -1),
+];
+const ROOK_DELTAS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DELTAS: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+/// North, north east and so on clockwise, as in `RayDirection`
+const RAY_DELTAS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// SplitMix64, which is plenty for finding magics and needs no dependencies.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A random number with few bits set, which makes for better magics.
+    pub fn sparse_u64(&mut self) -> u64 {
+        self.next_u64() & self.next_u64() & self.next_u64()
+    }
+}
+
+/// The square `(rank, file)` steps away from `square`, if it's on the board.
+fn step(square: usize, (rank, file): (i8, i8)) -> Option<usize> {
+    let rank = (square / 8) as i8 + rank;
+    let file = (square % 8) as i8 + file;
+
+    ((0..8).contains(&rank) && (0..8).contains(&file)).then_some((rank * 8 + file) as usize)
+}
+
+/// Squares reached by a single step in each direction.
+fn leaper_moves(square: usize, deltas: &[(i8, i8)]) -> u64 {
+    deltas
+        .iter()
+        .filter_map(|&delta| step(square, delta))
+        .fold(0, |mask, target| mask | 1 << target)
+}
+
+/// Squares reached by sliding in each direction until the edge of the board or a blocker,
+/// which is included.
+pub fn sliding_moves(square: usize, blockers: u64, diagonal: bool) -> u64 {
+    let deltas = if diagonal { BISHOP_DELTAS } else { ROOK_DELTAS };
+
+    deltas.into_iter().fold(0, |moves, delta| {
+        moves | sliding_ray(square, delta, blockers)
+    })
+}
+
+/// Squares whose blockers change a slider's moves: every square it can reach on an empty board,
+/// except the last one in each direction.
+pub fn relevant_blockers(square: usize, diagonal: bool) -> u64 {
+    let deltas = if diagonal { BISHOP_DELTAS } else { ROOK_DELTAS };
+    let mut mask = 0;
+
+    for delta in deltas {
+        let mut current = square;
+        while let Some(target) = step(current, delta) {
+            if step(target, delta).is_none() {
+                break;
+            }
+            mask |= 1 << target;
+            current = target;
+        }
+    }
+
+    mask
+}
+
+/// Squares from `square` to the edge of the board in one direction, not including `square`.
+fn ray(square: usize, delta: (i8, i8)) -> u64 {
+    sliding_ray(square, delta, 0)
+}
+
+fn sliding_ray(square: usize, delta: (i8, i8), blockers: u64) -> u64 {
+    let mut mask = 0;
+    let mut current = square;
+
+    while let Some(target) = step(current, delta) {
+        mask |= 1 << target;
+        if blockers & 1 << target != 0 {
+            break;
+        }
+        current = target;
+    }
+
+    mask
+}
+
+/// The direction from `from` to `to`, if they share a rank, file or diagonal.
+fn ray_towards(from: usize, to: usize) -> Option<(i8, i8)> {
+    RAY_DELTAS
+        .into_iter()
+        .find(|&delta| ray(from, delta) & 1 << to != 0)
+}
+
+/// Squares strictly between `a` and `b`, or none if they aren't lined up.
+fn between(a: usize, b: usize) -> u64 {
+    match ray_towards(a, b) {
+        Some(delta) => ray(a, delta) & !ray(b, delta) & !(1 << b),
+        None => 0,
+    }
+}
+
+/// The whole rank, file or diagonal going through `a` and `b`, or none if they aren't lined up.
+fn line(a: usize, b: usize) -> u64 {
+    match ray_towards(a, b) {
+        Some((rank, file)) => ray(a, (rank, file)) | ray(a, (-rank, -file)) | 1 << a,
+        None => 0,
+    }
+}
+
+/// Every subset of `mask`, starting with `mask` itself and ending with the empty set.
+fn subsets(mask: u64) -> impl Iterator<Item = u64> {
+    let mut next = Some(mask);
+
+    std::iter::from_fn(move || {
+        let subset = next?;
+        next = (subset != 0).then(|| (subset - 1) & mask);
+        Some(subset)
+    })
+}
+
+fn pawn_pushes(square: usize, white: bool) -> u64 {
+    // Pawns never stand on the first or last rank
+    if !(8..56).contains(&square) {
+        return 0;
+    }
+
+    let (forward, start_rank) = if white { (1, 1) } else { (-1, 6) };
+    let mut mask = leaper_moves(square, &[(forward, 0)]);
+    if square / 8 == start_rank {
+        mask |= leaper_moves(square, &[(2 * forward, 0)]);
+    }
+
+    mask
+}
+
+fn pawn_captures(square: usize, white: bool) -> u64 {
+    if !(8..56).contains(&square) {
+        return 0;
+    }
+
+    let forward = if white { 1 } else { -1 };
+    leaper_moves(square, &[(forward, -1), (forward, 1)])
+}
+
+/// A magic for one square, as in `MagicEntry`.
+#[derive(Debug, Clone)]
+pub struct Magic {
+    pub mask: u64,
+    pub magic: u64,
+    pub index_bits: u8,
+    pub offset: usize,
+}
+
+impl Magic {
+    fn index(&self, blockers: u64) -> usize {
+        ((blockers & self.mask).wrapping_mul(self.magic) >> (64 - self.index_bits)) as usize
+    }
+}
+
+/// Looks for a magic indexing every subset of `mask` into `index_bits` bits, giving up after
+/// `attempts` candidates if there is a limit.
+fn find_magic(
+    square: usize,
+    mask: u64,
+    diagonal: bool,
+    index_bits: u8,
+    attempts: Option<u64>,
+    rng: &mut Rng,
+) -> Option<u64> {
+    let occupancies = subsets(mask)
+        .map(|blockers| (blockers, sliding_moves(square, blockers, diagonal)))
+        .collect::<Vec<_>>();
+
+    let mut table = vec![0; 1 << index_bits];
+    // Instead of clearing the table for each candidate, entries from older attempts are ignored
+    let mut filled_by = vec![0_u64; 1 << index_bits];
+
+    for attempt in 1.. {
+        if attempts.is_some_and(|attempts| attempt > attempts) {
+            return None;
+        }
+
+        let candidate = Magic {
+            mask,
+            magic: rng.sparse_u64(),
+            index_bits,
+            offset: 0,
+        };
+
+        // Magics that don't spread the mask's bits into the top byte hardly ever work
+        if (mask.wrapping_mul(candidate.magic) >> 56).count_ones() < 6 {
+            continue;
+        }
+
+        let fits = occupancies.iter().all(|&(blockers, moves)| {
+            let index = candidate.index(blockers);
+
+            if filled_by[index] != attempt {
+                filled_by[index] = attempt;
+                table[index] = moves;
+                true
+            } else {
+                table[index] == moves
+            }
+        });
+
+        if fits {
+            return Some(candidate.magic);
+        }
+    }
+
+    unreachable!()
+}
+
+/// Finds a magic for every square, using as many index bits as there are relevant blockers.
+/// With `shrink_attempts`, one bit fewer is then tried for that many candidates per square.
+/// Each square's moves start at `offset`, right after the previous square's.
+pub fn find_magics(
+    diagonal: bool,
+    mut offset: usize,
+    shrink_attempts: Option<u64>,
+    rng: &mut Rng,
+) -> Vec<Magic> {
+    let mut magics = Vec::with_capacity(64);
+
+    for square in 0..64 {
+        let mask = relevant_blockers(square, diagonal);
+
+        let mut index_bits = mask.count_ones() as u8;
+        let mut magic = find_magic(square, mask, diagonal, index_bits, None, rng).unwrap();
+
+        if let Some(attempts) = shrink_attempts {
+            let smaller = find_magic(square, mask, diagonal, index_bits - 1, Some(attempts), rng);
+            if let Some(smaller) = smaller {
+                index_bits -= 1;
+                magic = smaller;
+            }
+        }
+
+        magics.push(Magic {
+            mask,
+            magic,
+            index_bits,
+            offset,
+        });
+        offset += 1 << index_bits;
+    }
+
+    magics
+}
+
+/// Total size of the tables for `magics`, starting from the first one's offset.
+pub fn table_size(magics: &[Magic]) -> usize {
+    magics.iter().map(|magic| 1_usize << magic.index_bits).sum()
+}
+
+fn write_masks(buf: &mut String, name: &str, mask: impl Fn(usize) -> u64) {
+    writeln!(buf, "pub const {name}: [Mask; 64] = [").unwrap();
+    for square in 0..64 {
+        writeln!(buf, "    Mask({}),", mask(square)).unwrap();
+    }
+    writeln!(buf, "];").unwrap();
+}
+
+/// Source of `move_masks.rs`.
+pub fn move_masks_source() -> String {
+    let mut buf = String::new();
+
+    write_masks(&mut buf, "WHITE_PAWN_MOVE_MASKS", |sq| {
+        pawn_pushes(sq, true)
+    });
+    write_masks(&mut buf, "BLACK_PAWN_MOVE_MASKS", |sq| {
+        pawn_pushes(sq, false)
+    });
+    write_masks(&mut buf, "WHITE_PAWN_CAPTURE_MASKS", |sq| {
+        pawn_captures(sq, true)
+    });
+    write_masks(&mut buf, "BLACK_PAWN_CAPTURE_MASKS", |sq| {
+        pawn_captures(sq, false)
+    });
+    write_masks(&mut buf, "KNIGHT_MOVE_MASKS", |sq| {
+        leaper_moves(sq, &KNIGHT_DELTAS)
+    });
+    write_masks(&mut buf, "BISHOP_MOVE_MASKS", |sq| {
+        sliding_moves(sq, 0, true)
+    });
+    write_masks(&mut buf, "ROOK_MOVE_MASKS", |sq| {
+        sliding_moves(sq, 0, false)
+    });
+    write_masks(&mut buf, "KING_MOVE_MASKS", |sq| {
+        leaper_moves(sq, &KING_DELTAS)
+    });
+    write_masks(&mut buf, "BISHOP_RELEVANT_BLOCKERS", |sq| {
+        relevant_blockers(sq, true)
+    });
+    write_masks(&mut buf, "ROOK_RELEVANT_BLOCKERS", |sq| {
+        relevant_blockers(sq, false)
+    });
+
+    writeln!(buf, "pub static RAY_MASKS: [[Mask; 64]; 8] = [").unwrap();
+    for delta in RAY_DELTAS {
+        let row = (0..64)
+            .map(|sq| format!("Mask({})", ray(sq, delta)))
+            .collect::<Vec<_>>();
+        writeln!(buf, "    [{}],", row.join(", ")).unwrap();
+    }
+    writeln!(buf, "];").unwrap();
+
+    write_square_pairs(&mut buf, "BETWEEN_MASKS", between);
+    write_square_pairs(&mut buf, "LINE_MASKS", line);
+
+    buf
+}
+
+fn write_square_pairs(buf: &mut String, name: &str, mask: impl Fn(usize, usize) -> u64) {
+    writeln!(buf, "pub static {name}: [[Mask; 64]; 64] = [").unwrap();
+    for a in 0..64 {
+        let row = (0..64)
+            .map(|b| format!("Mask({})", mask(a, b)))
+            .collect::<Vec<_>>();
+        writeln!(buf, "    [{}],", row.join(", ")).unwrap();
+    }
+    writeln!(buf, "];").unwrap();
+}
+
+fn write_magics(buf: &mut String, name: &str, magics: &[Magic]) {
+    writeln!(buf, "pub const {name}: &[MagicEntry; 64] = &[").unwrap();
+    for magic in magics {
+        writeln!(buf, "    MagicEntry {{").unwrap();
+        writeln!(buf, "        mask: Mask({}),", magic.mask).unwrap();
+        writeln!(buf, "        magic: {},", magic.magic).unwrap();
+        writeln!(buf, "        index_bits: {},", magic.index_bits).unwrap();
+        writeln!(buf, "        offset: {},", magic.offset).unwrap();
+        writeln!(buf, "    }},").unwrap();
+    }
+    writeln!(buf, "];").unwrap();
+}
+
+/// Source of `magics.rs`, with rook moves first in the shared table followed by bishop moves.
+pub fn magics_source(seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let rook_magics = find_magics(false, 0, None, &mut rng);
+    let bishop_magics = find_magics(true, table_size(&rook_magics), None, &mut rng);
+
+    let mut buf = String::new();
+    writeln!(
+        buf,
+        "pub const SLIDING_TABLE_SIZE: usize = {};",
+        table_size(&rook_magics) + table_size(&bishop_magics)
+    )
+    .unwrap();
+    write_magics(&mut buf, "ROOK_MAGICS", &rook_magics);
+    write_magics(&mut buf, "BISHOP_MAGICS", &bishop_magics);
+
+    buf
+}
@@ -1,902 +1,6 @@
+//! Rook and bishop magics, generated by `build.rs` from `generate.rs`.
+
 use super::MagicEntry;
 use crate::board::mask::Mask;
-pub const ROOK_MAGICS: &[MagicEntry; 64] = &[
-    MagicEntry {
-        mask: Mask(
-            282578800148862,
-        ),
-        magic: 581175735214047744,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            565157600297596,
-        ),
-        magic: 2594144037847958784,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            1130315200595066,
-        ),
-        magic: 23362835392503808,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            2260630401190006,
-        ),
-        magic: 565290844835904,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            4521260802379886,
-        ),
-        magic: 577605378267750404,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            9042521604759646,
-        ),
-        magic: 5206198556930621442,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            18085043209519166,
-        ),
-        magic: 1153502049464221700,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            36170086419038334,
-        ),
-        magic: 432557231091744777,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            282578800180736,
-        ),
-        magic: 2594073935389786880,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            565157600328704,
-        ),
-        magic: 4522360331894918,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            1130315200625152,
-        ),
-        magic: 109546766057668880,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            2260630401218048,
-        ),
-        magic: 576539960174444570,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            4521260802403840,
-        ),
-        magic: 144398863216672817,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            9042521604775424,
-        ),
-        magic: 13846334672843964480,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            18085043209518592,
-        ),
-        magic: 342590643614257344,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            36170086419037696,
-        ),
-        magic: 73326501323375136,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            282578808340736,
-        ),
-        magic: 2594284660175406400,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            565157608292864,
-        ),
-        magic: 849441988841480,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            1130315208328192,
-        ),
-        magic: 144467066710137376,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            2260630408398848,
-        ),
-        magic: 581256822578742304,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            4521260808540160,
-        ),
-        magic: 153413208727359504,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            9042521608822784,
-        ),
-        magic: 576495940974936085,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            18085043209388032,
-        ),
-        magic: 6920066705650720784,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            36170086418907136,
-        ),
-        magic: 1729391055217950786,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            282580897300736,
-        ),
-        magic: 594510343842038272,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            565159647117824,
-        ),
-        magic: 2396480189460123904,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            1130317180306432,
-        ),
-        magic: 18019346379448320,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            2260632246683648,
-        ),
-        magic: 9042538379935746,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            4521262379438080,
-        ),
-        magic: 4614276579491586058,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            9042522644946944,
-        ),
-        magic: 5233221284271789120,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            18085043175964672,
-        ),
-        magic: 4647715916100927498,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            36170086385483776,
-        ),
-        magic: 4900061530961234176,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            283115671060736,
-        ),
-        magic: 36681941990770700,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            565681586307584,
-        ),
-        magic: 9224216736671236612,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            1130822006735872,
-        ),
-        magic: 612631388004327552,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            2261102847592448,
-        ),
-        magic: 1267737041203207,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            4521664529305600,
-        ),
-        magic: 6774366058614816,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            9042787892731904,
-        ),
-        magic: 74453190296944672,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            18085034619584512,
-        ),
-        magic: 8070740940757991617,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            36170077829103616,
-        ),
-        magic: 9223372655364147217,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            420017753620736,
-        ),
-        magic: 78078520516952320,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            699298018886144,
-        ),
-        magic: 9223656814669922368,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            1260057572672512,
-        ),
-        magic: 149181739804377152,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            2381576680245248,
-        ),
-        magic: 281818576207876,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            4624614895390720,
-        ),
-        magic: 285924563028040,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            9110691325681664,
-        ),
-        magic: 9259458018281595913,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            18082844186263552,
-        ),
-        magic: 5908794183720112400,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            36167887395782656,
-        ),
-        magic: 19421777759572880,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            35466950888980736,
-        ),
-        magic: 1153493250956528641,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            34905104758997504,
-        ),
-        magic: 4611688526691696648,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            34344362452452352,
-        ),
-        magic: 1153290941050669185,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            33222877839362048,
-        ),
-        magic: 4611688509508943880,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            30979908613181440,
-        ),
-        magic: 2251937253819432,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            26493970160820224,
-        ),
-        magic: 563087661082114,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            17522093256097792,
-        ),
-        magic: 2323858783325431824,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            35607136465616896,
-        ),
-        magic: 4616479957915533380,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            9079539427579068672,
-        ),
-        magic: 2305914546759664202,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            8935706818303361536,
-        ),
-        magic: 74485868035311758,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            8792156787827803136,
-        ),
-        magic: 1164184936618394113,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            8505056726876686336,
-        ),
-        magic: 10016714756274271361,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            7930856604974452736,
-        ),
-        magic: 36030034649026562,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            6782456361169985536,
-        ),
-        magic: 46163004316721162,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            4485655873561051136,
-        ),
-        magic: 580977554665128065,
-        index_bits: 16,
-    },
-    MagicEntry {
-        mask: Mask(
-            9115426935197958144,
-        ),
-        magic: 5066687691104785,
-        index_bits: 16,
-    },
-];
-pub const BISHOP_MAGICS: &[MagicEntry; 64] = &[
-    MagicEntry {
-        mask: Mask(
-            18049651735527936,
-        ),
-        magic: 35227321794689,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            70506452091904,
-        ),
-        magic: 6917530161546135552,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            275415828992,
-        ),
-        magic: 4620764826873626753,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            1075975168,
-        ),
-        magic: 2287260212922625,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            38021120,
-        ),
-        magic: 11532592752365682752,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            8657588224,
-        ),
-        magic: 337857941573730320,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2216338399232,
-        ),
-        magic: 2305993676668535300,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            567382630219776,
-        ),
-        magic: 72145590670167109,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            9024825867763712,
-        ),
-        magic: 69256479264,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            18049651735527424,
-        ),
-        magic: 3026423691237393154,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            70506452221952,
-        ),
-        magic: 1747442870358402816,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            275449643008,
-        ),
-        magic: 4539059416343552,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            9733406720,
-        ),
-        magic: 4827863414025125920,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2216342585344,
-        ),
-        magic: 824902299648,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            567382630203392,
-        ),
-        magic: 10435686273646596100,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            1134765260406784,
-        ),
-        magic: 8868050640896,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            4512412933816832,
-        ),
-        magic: 11081284194315,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            9024825867633664,
-        ),
-        magic: 2305860605696016448,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            18049651768822272,
-        ),
-        magic: 4616207211314677832,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            70515108615168,
-        ),
-        magic: 1729560480940295169,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2491752130560,
-        ),
-        magic: 725080648116732048,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            567383701868544,
-        ),
-        magic: 292874749818769416,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            1134765256220672,
-        ),
-        magic: 74520552160764938,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2269530512441344,
-        ),
-        magic: 651896389660317704,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2256206450263040,
-        ),
-        magic: 7493991979110629378,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            4512412900526080,
-        ),
-        magic: 25122192503128576,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            9024834391117824,
-        ),
-        magic: 18966576119816,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            18051867805491712,
-        ),
-        magic: 9232381443722985480,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            637888545440768,
-        ),
-        magic: 112590287708635148,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            1135039602493440,
-        ),
-        magic: 9232399310820942080,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2269529440784384,
-        ),
-        magic: 1134715327250504,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            4539058881568768,
-        ),
-        magic: 432776642578909233,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            1128098963916800,
-        ),
-        magic: 712484611234048,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2256197927833600,
-        ),
-        magic: 144115465654944000,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            4514594912477184,
-        ),
-        magic: 40533637892014162,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            9592139778506752,
-        ),
-        magic: 10088945287825,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            19184279556981248,
-        ),
-        magic: 1126181237096576,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2339762086609920,
-        ),
-        magic: 845631832719489,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            4538784537380864,
-        ),
-        magic: 9223442474737926145,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            9077569074761728,
-        ),
-        magic: 4504561708433664,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            562958610993152,
-        ),
-        magic: 11399741388619824,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            1125917221986304,
-        ),
-        magic: 572862754732104,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2814792987328512,
-        ),
-        magic: 12196046875363247360,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            5629586008178688,
-        ),
-        magic: 2323857614015955976,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            11259172008099840,
-        ),
-        magic: 294986351131529360,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            22518341868716544,
-        ),
-        magic: 3171845855068029445,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            9007336962655232,
-        ),
-        magic: 2324505565487109,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            18014673925310464,
-        ),
-        magic: 1152922617280205057,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2216338399232,
-        ),
-        magic: 2305922754945384456,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            4432676798464,
-        ),
-        magic: 489894724608,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            11064376819712,
-        ),
-        magic: 72339069417422848,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            22137335185408,
-        ),
-        magic: 72057596320415744,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            44272556441600,
-        ),
-        magic: 297599314807492864,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            87995357200384,
-        ),
-        magic: 78819601324452869,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            35253226045952,
-        ),
-        magic: 16140971459009986560,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            70506452091904,
-        ),
-        magic: 4683797488669368920,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            567382630219776,
-        ),
-        magic: 11277691304020224,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            1134765260406784,
-        ),
-        magic: 17602387125248,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            2832480465846272,
-        ),
-        magic: 4684869513180217352,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            5667157807464448,
-        ),
-        magic: 9277433100521964040,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            11333774449049600,
-        ),
-        magic: 648518346343452777,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            22526811443298304,
-        ),
-        magic: 154601029143445570,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            9024825867763712,
-        ),
-        magic: 725361427304420136,
-        index_bits: 14,
-    },
-    MagicEntry {
-        mask: Mask(
-            18049651735527936,
-        ),
-        magic: 1261025625565737024,
-        index_bits: 14,
-    },
-];
+
+include!(concat!(env!("OUT_DIR"), "/magics.rs"));
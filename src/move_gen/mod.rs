@@ -1,5 +1,8 @@
+pub mod generate;
 pub mod magics;
 pub mod move_masks;
+#[cfg(feature = "pext")]
+pub mod pext;
 
 pub mod direction {
     use crate::{
@@ -115,6 +118,8 @@ pub mod direction {
     }
 }
 
+use std::sync::OnceLock;
+
 use direction::Direction;
 
 use crate::board::{
@@ -123,7 +128,7 @@ use crate::board::{
     square::Square,
 };
 
-use magics::{BISHOP_MAGICS, ROOK_MAGICS};
+use magics::{BISHOP_MAGICS, ROOK_MAGICS, SLIDING_TABLE_SIZE};
 
 pub fn rank(i: usize) -> usize {
     i / 8
@@ -333,27 +338,19 @@ pub fn generate_king_move_masks() -> [Mask; 64] {
     masks.map(|val| Mask(val))
 }
 
-pub fn create_move_list(direction: Direction, magics: &[MagicEntry; 64]) -> Vec<Vec<Mask>> {
-    let mut moves = Vec::with_capacity(64);
-
-    for (i, magic) in magics.into_iter().enumerate() {
-        let move_table =
-            try_fill_magic_table(direction, magic, Square::from_usize(i).unwrap()).unwrap();
-        moves.push(move_table);
-    }
-
-    moves
-}
-
 #[derive(Debug)]
 pub struct TableFillError;
 
+/// Writes the moves for every blocker subset of `entry` into its part of a shared `table`,
+/// failing if two subsets with different moves collide.
 pub fn try_fill_magic_table(
     direction: Direction,
     entry: &MagicEntry,
     square: Square,
-) -> Result<Vec<Mask>, TableFillError> {
-    let mut table = vec![Mask(0); 1 << entry.index_bits];
+    table: &mut [Mask],
+) -> Result<(), TableFillError> {
+    let table = &mut table[entry.offset..entry.offset + (1 << entry.index_bits)];
+    table.fill(Mask(0));
 
     for blockers in entry.mask.subsets() {
         let moves = direction.moves_for(square, blockers);
@@ -366,35 +363,85 @@ pub fn try_fill_magic_table(
         }
     }
 
-    Ok(table)
+    Ok(())
+}
+
+static SLIDING_MOVES: OnceLock<SlidingMoves> = OnceLock::new();
+
+#[derive(Debug)]
+enum Backend {
+    // Rook and bishop moves for every square, each starting at its magic entry's offset
+    Magic(Vec<Mask>),
+    #[cfg(feature = "pext")]
+    Pext(Box<pext::PextTables>),
 }
 
 #[derive(Debug)]
 pub struct SlidingMoves {
-    rook_magic_table: Vec<Vec<Mask>>,
-    bishop_magic_table: Vec<Vec<Mask>>,
+    backend: Backend,
 }
 
 impl SlidingMoves {
-    pub fn init() -> Self {
+    /// The tables shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        SLIDING_MOVES.get_or_init(Self::init)
+    }
+
+    /// Uses PEXT when the `pext` feature is on and the CPU supports it, and magics otherwise.
+    fn init() -> Self {
+        #[cfg(feature = "pext")]
+        if let Some(tables) = Self::with_pext() {
+            return tables;
+        }
+
+        Self::with_magics()
+    }
+
+    #[cfg(feature = "pext")]
+    pub fn with_pext() -> Option<Self> {
+        Some(Self {
+            backend: Backend::Pext(Box::new(pext::PextTables::new()?)),
+        })
+    }
+
+    pub fn with_magics() -> Self {
+        let mut table = vec![Mask(0); SLIDING_TABLE_SIZE];
+
+        for (direction, magics) in [
+            (Direction::Orthogonal, ROOK_MAGICS),
+            (Direction::Diagonal, BISHOP_MAGICS),
+        ] {
+            for (i, magic) in magics.iter().enumerate() {
+                let square = Square::from_usize(i).unwrap();
+                try_fill_magic_table(direction, magic, square, &mut table).unwrap();
+            }
+        }
+
         Self {
-            rook_magic_table: create_move_list(Direction::Orthogonal, &ROOK_MAGICS),
-            bishop_magic_table: create_move_list(Direction::Diagonal, &BISHOP_MAGICS),
+            backend: Backend::Magic(table),
         }
     }
 
     pub fn get_rook_moves(&self, square: Square, blockers: Mask) -> Mask {
-        let magic = &ROOK_MAGICS[square as usize];
-        let moves = &self.rook_magic_table[square as usize];
-
-        moves[magic.index(blockers)]
+        match &self.backend {
+            Backend::Magic(table) => {
+                let magic = &ROOK_MAGICS[square as usize];
+                table[magic.offset + magic.index(blockers)]
+            }
+            #[cfg(feature = "pext")]
+            Backend::Pext(tables) => tables.get_rook_moves(square, blockers),
+        }
     }
 
     pub fn get_bishop_moves(&self, square: Square, blockers: Mask) -> Mask {
-        let magic = &BISHOP_MAGICS[square as usize];
-        let moves = &self.bishop_magic_table[square as usize];
-
-        moves[magic.index(blockers)]
+        match &self.backend {
+            Backend::Magic(table) => {
+                let magic = &BISHOP_MAGICS[square as usize];
+                table[magic.offset + magic.index(blockers)]
+            }
+            #[cfg(feature = "pext")]
+            Backend::Pext(tables) => tables.get_bishop_moves(square, blockers),
+        }
     }
 }
 
@@ -403,6 +450,8 @@ pub struct MagicEntry {
     pub mask: Mask,
     pub magic: u64,
     pub index_bits: u8,
+    /// Where this square's moves start in the shared table
+    pub offset: usize,
 }
 
 impl MagicEntry {
@@ -422,35 +471,153 @@ mod move_gen_tests {
 
     #[test]
     fn bishop_jumping() {
-        let movegen = SlidingMoves::init();
+        let movegen = SlidingMoves::get();
 
         BISHOP_MAGICS[Square::C1 as usize].mask.print();
 
         movegen.get_bishop_moves(Square::C1, Mask(u64::MAX)).print();
     }
 
+    #[test]
+    fn magic_lookups_match_slow_moves() {
+        let movegen = SlidingMoves::with_magics();
+        let mut expected_offset = 0;
+
+        for (direction, magics) in [
+            (Direction::Orthogonal, ROOK_MAGICS),
+            (Direction::Diagonal, BISHOP_MAGICS),
+        ] {
+            for (i, magic) in magics.iter().enumerate() {
+                // Tables are packed one after the other without gaps
+                assert_eq!(magic.offset, expected_offset);
+                expected_offset += 1 << magic.index_bits;
+
+                let square = Square::from_usize(i).unwrap();
+                assert_eq!(magic.mask, direction.all_blockers()[i]);
+
+                for blockers in magic.mask.subsets() {
+                    let moves = match direction {
+                        Direction::Orthogonal => movegen.get_rook_moves(square, blockers),
+                        Direction::Diagonal => movegen.get_bishop_moves(square, blockers),
+                    };
+                    assert_eq!(moves, direction.moves_for(square, blockers));
+                }
+            }
+        }
+
+        assert_eq!(expected_offset, SLIDING_TABLE_SIZE);
+    }
+
+    #[test]
+    fn generated_masks_match_reference() {
+        use move_masks::*;
+
+        let tables = [
+            (WHITE_PAWN_MOVE_MASKS, generate_white_pawn_move_masks()),
+            (BLACK_PAWN_MOVE_MASKS, generate_black_pawn_move_masks()),
+            (
+                WHITE_PAWN_CAPTURE_MASKS,
+                generate_white_pawn_capture_masks(),
+            ),
+            (
+                BLACK_PAWN_CAPTURE_MASKS,
+                generate_black_pawn_capture_masks(),
+            ),
+            (KNIGHT_MOVE_MASKS, generate_knight_move_masks()),
+            (KING_MOVE_MASKS, generate_king_move_masks()),
+            (ROOK_MOVE_MASKS, generate_rook_move_masks()),
+            (BISHOP_MOVE_MASKS, generate_bishop_move_masks()),
+        ];
+        for (generated, reference) in tables {
+            assert_eq!(generated, reference);
+        }
+
+        for (direction, relevant_blockers) in [
+            (Direction::Orthogonal, ROOK_RELEVANT_BLOCKERS),
+            (Direction::Diagonal, BISHOP_RELEVANT_BLOCKERS),
+        ] {
+            assert_eq!(relevant_blockers.to_vec(), direction.all_blockers());
+
+            // Sliding on an empty board matches the move masks
+            for i in 0..64 {
+                let square = Square::from_usize(i).unwrap();
+                let empty_board = direction.moves_for(square, Mask(0));
+                match direction {
+                    Direction::Orthogonal => assert_eq!(empty_board, ROOK_MOVE_MASKS[i]),
+                    Direction::Diagonal => assert_eq!(empty_board, BISHOP_MOVE_MASKS[i]),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rays_between_and_lines_match_slow_moves() {
+        use move_masks::{between, line, ray, RayDirection};
+
+        for i in 0..64 {
+            let a = Square::from_usize(i).unwrap();
+
+            for direction in [Direction::Orthogonal, Direction::Diagonal] {
+                let rays = RayDirection::ALL
+                    .into_iter()
+                    .filter(|ray_direction| {
+                        ray_direction.is_diagonal() == (direction == Direction::Diagonal)
+                    })
+                    .fold(Mask(0), |mask, ray_direction| mask | ray(a, ray_direction));
+                assert_eq!(rays, direction.moves_for(a, Mask(0)));
+            }
+
+            for j in 0..64 {
+                let b = Square::from_usize(j).unwrap();
+
+                let lined_up = [Direction::Orthogonal, Direction::Diagonal]
+                    .into_iter()
+                    .find(|direction| direction.moves_for(a, Mask(0)).contains(b));
+
+                match lined_up {
+                    Some(direction) => {
+                        // A slider on either square sees up to the other, and both stop there
+                        let expected = direction.moves_for(a, b.mask())
+                            & direction.moves_for(b, a.mask())
+                            & !a.mask()
+                            & !b.mask();
+                        assert_eq!(between(a, b), expected, "{a:?} {b:?}");
+
+                        let expected = (direction.moves_for(a, Mask(0))
+                            & direction.moves_for(b, Mask(0)))
+                            | a.mask()
+                            | b.mask();
+                        assert_eq!(line(a, b), expected, "{a:?} {b:?}");
+                        assert_eq!(line(a, b), line(b, a));
+                    }
+                    None => {
+                        assert_eq!(between(a, b), Mask(0));
+                        assert_eq!(line(a, b), Mask(0));
+                    }
+                }
+            }
+        }
+
+        assert_eq!(
+            between(Square::A1, Square::D4),
+            Square::B2.mask() | Square::C3.mask()
+        );
+        assert_eq!(line(Square::B1, Square::B5).count(), 8);
+    }
+
+    #[test]
+    fn tables_are_shared() {
+        assert!(std::ptr::eq(SlidingMoves::get(), SlidingMoves::get()));
+    }
+
     #[test]
     fn queen_moves() {
         let mut board = Board::new(START_FEN).unwrap();
 
-        board
-            .make_move(Move {
-                from: Square::E2,
-                to: Square::E4,
-            })
-            .unwrap();
-
-        board
-            .make_move(Move {
-                from: Square::G7,
-                to: Square::G5,
-            })
-            .unwrap();
-
-        dbg!(board.make_move(Move {
-            from: Square::D1,
-            to: Square::H5,
-        }))
-        .unwrap();
+        board.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+
+        board.make_move(Move::new(Square::G7, Square::G5)).unwrap();
+
+        dbg!(board.make_move(Move::new(Square::D1, Square::H5))).unwrap();
     }
 }
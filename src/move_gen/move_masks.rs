@@ -1,661 +1,52 @@
-use crate::board::mask::Mask;
-pub const BISHOP_MOVE_MASKS: [Mask; 64] = [
-    Mask(9241421688590303744),
-    Mask(36099303471056128),
-    Mask(141012904249856),
-    Mask(550848566272),
-    Mask(6480472064),
-    Mask(1108177604608),
-    Mask(283691315142656),
-    Mask(72624976668147712),
-    Mask(4620710844295151618),
-    Mask(9241421688590368773),
-    Mask(36099303487963146),
-    Mask(141017232965652),
-    Mask(1659000848424),
-    Mask(283693466779728),
-    Mask(72624976676520096),
-    Mask(145249953336262720),
-    Mask(2310355422147510788),
-    Mask(4620710844311799048),
-    Mask(9241421692918565393),
-    Mask(36100411639206946),
-    Mask(424704217196612),
-    Mask(72625527495610504),
-    Mask(145249955479592976),
-    Mask(290499906664153120),
-    Mask(1155177711057110024),
-    Mask(2310355426409252880),
-    Mask(4620711952330133792),
-    Mask(9241705379636978241),
-    Mask(108724279602332802),
-    Mask(145390965166737412),
-    Mask(290500455356698632),
-    Mask(580999811184992272),
-    Mask(577588851267340304),
-    Mask(1155178802063085600),
-    Mask(2310639079102947392),
-    Mask(4693335752243822976),
-    Mask(9386671504487645697),
-    Mask(326598935265674242),
-    Mask(581140276476643332),
-    Mask(1161999073681608712),
-    Mask(288793334762704928),
-    Mask(577868148797087808),
-    Mask(1227793891648880768),
-    Mask(2455587783297826816),
-    Mask(4911175566595588352),
-    Mask(9822351133174399489),
-    Mask(1197958188344280066),
-    Mask(2323857683139004420),
-    Mask(144117404414255168),
-    Mask(360293502378066048),
-    Mask(720587009051099136),
-    Mask(1441174018118909952),
-    Mask(2882348036221108224),
-    Mask(5764696068147249408),
-    Mask(11529391036782871041),
-    Mask(4611756524879479810),
-    Mask(567382630219904),
-    Mask(1416240237150208),
-    Mask(2833579985862656),
-    Mask(5667164249915392),
-    Mask(11334324221640704),
-    Mask(22667548931719168),
-    Mask(45053622886727936),
-    Mask(18049651735527937),
-];
-pub const BISHOP_RELEVANT_BLOCKERS: [Mask; 64] = [
-    Mask(18049651735527936),
-    Mask(70506452091904),
-    Mask(275415828992),
-    Mask(1075975168),
-    Mask(38021120),
-    Mask(8657588224),
-    Mask(2216338399232),
-    Mask(567382630219776),
-    Mask(9024825867763712),
-    Mask(18049651735527424),
-    Mask(70506452221952),
-    Mask(275449643008),
-    Mask(9733406720),
-    Mask(2216342585344),
-    Mask(567382630203392),
-    Mask(1134765260406784),
-    Mask(4512412933816832),
-    Mask(9024825867633664),
-    Mask(18049651768822272),
-    Mask(70515108615168),
-    Mask(2491752130560),
-    Mask(567383701868544),
-    Mask(1134765256220672),
-    Mask(2269530512441344),
-    Mask(2256206450263040),
-    Mask(4512412900526080),
-    Mask(9024834391117824),
-    Mask(18051867805491712),
-    Mask(637888545440768),
-    Mask(1135039602493440),
-    Mask(2269529440784384),
-    Mask(4539058881568768),
-    Mask(1128098963916800),
-    Mask(2256197927833600),
-    Mask(4514594912477184),
-    Mask(9592139778506752),
-    Mask(19184279556981248),
-    Mask(2339762086609920),
-    Mask(4538784537380864),
-    Mask(9077569074761728),
-    Mask(562958610993152),
-    Mask(1125917221986304),
-    Mask(2814792987328512),
-    Mask(5629586008178688),
-    Mask(11259172008099840),
-    Mask(22518341868716544),
-    Mask(9007336962655232),
-    Mask(18014673925310464),
-    Mask(2216338399232),
-    Mask(4432676798464),
-    Mask(11064376819712),
-    Mask(22137335185408),
-    Mask(44272556441600),
-    Mask(87995357200384),
-    Mask(35253226045952),
-    Mask(70506452091904),
-    Mask(567382630219776),
-    Mask(1134765260406784),
-    Mask(2832480465846272),
-    Mask(5667157807464448),
-    Mask(11333774449049600),
-    Mask(22526811443298304),
-    Mask(9024825867763712),
-    Mask(18049651735527936),
-];
-pub const KING_MOVE_MASKS: [Mask; 64] = [
-    Mask(770),
-    Mask(1797),
-    Mask(3594),
-    Mask(7188),
-    Mask(14376),
-    Mask(28752),
-    Mask(57504),
-    Mask(49216),
-    Mask(197123),
-    Mask(460039),
-    Mask(920078),
-    Mask(1840156),
-    Mask(3680312),
-    Mask(7360624),
-    Mask(14721248),
-    Mask(12599488),
-    Mask(50463488),
-    Mask(117769984),
-    Mask(235539968),
-    Mask(471079936),
-    Mask(942159872),
-    Mask(1884319744),
-    Mask(3768639488),
-    Mask(3225468928),
-    Mask(12918652928),
-    Mask(30149115904),
-    Mask(60298231808),
-    Mask(120596463616),
-    Mask(241192927232),
-    Mask(482385854464),
-    Mask(964771708928),
-    Mask(825720045568),
-    Mask(3307175149568),
-    Mask(7718173671424),
-    Mask(15436347342848),
-    Mask(30872694685696),
-    Mask(61745389371392),
-    Mask(123490778742784),
-    Mask(246981557485568),
-    Mask(211384331665408),
-    Mask(846636838289408),
-    Mask(1975852459884544),
-    Mask(3951704919769088),
-    Mask(7903409839538176),
-    Mask(15806819679076352),
-    Mask(31613639358152704),
-    Mask(63227278716305408),
-    Mask(54114388906344448),
-    Mask(216739030602088448),
-    Mask(505818229730443264),
-    Mask(1011636459460886528),
-    Mask(2023272918921773056),
-    Mask(4046545837843546112),
-    Mask(8093091675687092224),
-    Mask(16186183351374184448),
-    Mask(13853283560024178688),
-    Mask(144959613005987840),
-    Mask(362258295026614272),
-    Mask(724516590053228544),
-    Mask(1449033180106457088),
-    Mask(2898066360212914176),
-    Mask(5796132720425828352),
-    Mask(11592265440851656704),
-    Mask(4665729213955833856),
-];
-pub const WHITE_PAWN_CAPTURE_MASKS: [Mask; 64] = [
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(131072),
-    Mask(327680),
-    Mask(655360),
-    Mask(1310720),
-    Mask(2621440),
-    Mask(5242880),
-    Mask(10485760),
-    Mask(4194304),
-    Mask(33554432),
-    Mask(83886080),
-    Mask(167772160),
-    Mask(335544320),
-    Mask(671088640),
-    Mask(1342177280),
-    Mask(2684354560),
-    Mask(1073741824),
-    Mask(8589934592),
-    Mask(21474836480),
-    Mask(42949672960),
-    Mask(85899345920),
-    Mask(171798691840),
-    Mask(343597383680),
-    Mask(687194767360),
-    Mask(274877906944),
-    Mask(2199023255552),
-    Mask(5497558138880),
-    Mask(10995116277760),
-    Mask(21990232555520),
-    Mask(43980465111040),
-    Mask(87960930222080),
-    Mask(175921860444160),
-    Mask(70368744177664),
-    Mask(562949953421312),
-    Mask(1407374883553280),
-    Mask(2814749767106560),
-    Mask(5629499534213120),
-    Mask(11258999068426240),
-    Mask(22517998136852480),
-    Mask(45035996273704960),
-    Mask(18014398509481984),
-    Mask(144115188075855872),
-    Mask(360287970189639680),
-    Mask(720575940379279360),
-    Mask(1441151880758558720),
-    Mask(2882303761517117440),
-    Mask(5764607523034234880),
-    Mask(11529215046068469760),
-    Mask(4611686018427387904),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-];
-pub const BLACK_PAWN_CAPTURE_MASKS: [Mask; 64] = [
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(2),
-    Mask(5),
-    Mask(10),
-    Mask(20),
-    Mask(40),
-    Mask(80),
-    Mask(160),
-    Mask(64),
-    Mask(512),
-    Mask(1280),
-    Mask(2560),
-    Mask(5120),
-    Mask(10240),
-    Mask(20480),
-    Mask(40960),
-    Mask(16384),
-    Mask(131072),
-    Mask(327680),
-    Mask(655360),
-    Mask(1310720),
-    Mask(2621440),
-    Mask(5242880),
-    Mask(10485760),
-    Mask(4194304),
-    Mask(33554432),
-    Mask(83886080),
-    Mask(167772160),
-    Mask(335544320),
-    Mask(671088640),
-    Mask(1342177280),
-    Mask(2684354560),
-    Mask(1073741824),
-    Mask(8589934592),
-    Mask(21474836480),
-    Mask(42949672960),
-    Mask(85899345920),
-    Mask(171798691840),
-    Mask(343597383680),
-    Mask(687194767360),
-    Mask(274877906944),
-    Mask(2199023255552),
-    Mask(5497558138880),
-    Mask(10995116277760),
-    Mask(21990232555520),
-    Mask(43980465111040),
-    Mask(87960930222080),
-    Mask(175921860444160),
-    Mask(70368744177664),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-];
-pub const ROOK_RELEVANT_BLOCKERS: [Mask; 64] = [
-    Mask(282578800148862),
-    Mask(565157600297596),
-    Mask(1130315200595066),
-    Mask(2260630401190006),
-    Mask(4521260802379886),
-    Mask(9042521604759646),
-    Mask(18085043209519166),
-    Mask(36170086419038334),
-    Mask(282578800180736),
-    Mask(565157600328704),
-    Mask(1130315200625152),
-    Mask(2260630401218048),
-    Mask(4521260802403840),
-    Mask(9042521604775424),
-    Mask(18085043209518592),
-    Mask(36170086419037696),
-    Mask(282578808340736),
-    Mask(565157608292864),
-    Mask(1130315208328192),
-    Mask(2260630408398848),
-    Mask(4521260808540160),
-    Mask(9042521608822784),
-    Mask(18085043209388032),
-    Mask(36170086418907136),
-    Mask(282580897300736),
-    Mask(565159647117824),
-    Mask(1130317180306432),
-    Mask(2260632246683648),
-    Mask(4521262379438080),
-    Mask(9042522644946944),
-    Mask(18085043175964672),
-    Mask(36170086385483776),
-    Mask(283115671060736),
-    Mask(565681586307584),
-    Mask(1130822006735872),
-    Mask(2261102847592448),
-    Mask(4521664529305600),
-    Mask(9042787892731904),
-    Mask(18085034619584512),
-    Mask(36170077829103616),
-    Mask(420017753620736),
-    Mask(699298018886144),
-    Mask(1260057572672512),
-    Mask(2381576680245248),
-    Mask(4624614895390720),
-    Mask(9110691325681664),
-    Mask(18082844186263552),
-    Mask(36167887395782656),
-    Mask(35466950888980736),
-    Mask(34905104758997504),
-    Mask(34344362452452352),
-    Mask(33222877839362048),
-    Mask(30979908613181440),
-    Mask(26493970160820224),
-    Mask(17522093256097792),
-    Mask(35607136465616896),
-    Mask(9079539427579068672),
-    Mask(8935706818303361536),
-    Mask(8792156787827803136),
-    Mask(8505056726876686336),
-    Mask(7930856604974452736),
-    Mask(6782456361169985536),
-    Mask(4485655873561051136),
-    Mask(9115426935197958144),
-];
-pub const BLACK_PAWN_MOVE_MASKS: [Mask; 64] = [
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(1),
-    Mask(2),
-    Mask(4),
-    Mask(8),
-    Mask(16),
-    Mask(32),
-    Mask(64),
-    Mask(128),
-    Mask(256),
-    Mask(512),
-    Mask(1024),
-    Mask(2048),
-    Mask(4096),
-    Mask(8192),
-    Mask(16384),
-    Mask(32768),
-    Mask(65536),
-    Mask(131072),
-    Mask(262144),
-    Mask(524288),
-    Mask(1048576),
-    Mask(2097152),
-    Mask(4194304),
-    Mask(8388608),
-    Mask(16777216),
-    Mask(33554432),
-    Mask(67108864),
-    Mask(134217728),
-    Mask(268435456),
-    Mask(536870912),
-    Mask(1073741824),
-    Mask(2147483648),
-    Mask(4294967296),
-    Mask(8589934592),
-    Mask(17179869184),
-    Mask(34359738368),
-    Mask(68719476736),
-    Mask(137438953472),
-    Mask(274877906944),
-    Mask(549755813888),
-    Mask(1103806595072),
-    Mask(2207613190144),
-    Mask(4415226380288),
-    Mask(8830452760576),
-    Mask(17660905521152),
-    Mask(35321811042304),
-    Mask(70643622084608),
-    Mask(141287244169216),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-];
-pub const KNIGHT_MOVE_MASKS: [Mask; 64] = [
-    Mask(132096),
-    Mask(329728),
-    Mask(659712),
-    Mask(1319424),
-    Mask(2638848),
-    Mask(5277696),
-    Mask(10489856),
-    Mask(4202496),
-    Mask(33816580),
-    Mask(84410376),
-    Mask(168886289),
-    Mask(337772578),
-    Mask(675545156),
-    Mask(1351090312),
-    Mask(2685403152),
-    Mask(1075839008),
-    Mask(8657044482),
-    Mask(21609056261),
-    Mask(43234889994),
-    Mask(86469779988),
-    Mask(172939559976),
-    Mask(345879119952),
-    Mask(687463207072),
-    Mask(275414786112),
-    Mask(2216203387392),
-    Mask(5531918402816),
-    Mask(11068131838464),
-    Mask(22136263676928),
-    Mask(44272527353856),
-    Mask(88545054707712),
-    Mask(175990581010432),
-    Mask(70506185244672),
-    Mask(567348067172352),
-    Mask(1416171111120896),
-    Mask(2833441750646784),
-    Mask(5666883501293568),
-    Mask(11333767002587136),
-    Mask(22667534005174272),
-    Mask(45053588738670592),
-    Mask(18049583422636032),
-    Mask(145241105196122112),
-    Mask(362539804446949376),
-    Mask(725361088165576704),
-    Mask(1450722176331153408),
-    Mask(2901444352662306816),
-    Mask(5802888705324613632),
-    Mask(11533718717099671552),
-    Mask(4620693356194824192),
-    Mask(288234782788157440),
-    Mask(576469569871282176),
-    Mask(1224997833292120064),
-    Mask(2449995666584240128),
-    Mask(4899991333168480256),
-    Mask(9799982666336960512),
-    Mask(1152939783987658752),
-    Mask(2305878468463689728),
-    Mask(1128098930098176),
-    Mask(2257297371824128),
-    Mask(4796069720358912),
-    Mask(9592139440717824),
-    Mask(19184278881435648),
-    Mask(38368557762871296),
-    Mask(4679521487814656),
-    Mask(9077567998918656),
-];
-pub const WHITE_PAWN_MOVE_MASKS: [Mask; 64] = [
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(16842752),
-    Mask(33685504),
-    Mask(67371008),
-    Mask(134742016),
-    Mask(269484032),
-    Mask(538968064),
-    Mask(1077936128),
-    Mask(2155872256),
-    Mask(16777216),
-    Mask(33554432),
-    Mask(67108864),
-    Mask(134217728),
-    Mask(268435456),
-    Mask(536870912),
-    Mask(1073741824),
-    Mask(2147483648),
-    Mask(4294967296),
-    Mask(8589934592),
-    Mask(17179869184),
-    Mask(34359738368),
-    Mask(68719476736),
-    Mask(137438953472),
-    Mask(274877906944),
-    Mask(549755813888),
-    Mask(1099511627776),
-    Mask(2199023255552),
-    Mask(4398046511104),
-    Mask(8796093022208),
-    Mask(17592186044416),
-    Mask(35184372088832),
-    Mask(70368744177664),
-    Mask(140737488355328),
-    Mask(281474976710656),
-    Mask(562949953421312),
-    Mask(1125899906842624),
-    Mask(2251799813685248),
-    Mask(4503599627370496),
-    Mask(9007199254740992),
-    Mask(18014398509481984),
-    Mask(36028797018963968),
-    Mask(72057594037927936),
-    Mask(144115188075855872),
-    Mask(288230376151711744),
-    Mask(576460752303423488),
-    Mask(1152921504606846976),
-    Mask(2305843009213693952),
-    Mask(4611686018427387904),
-    Mask(9223372036854775808),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-    Mask(0),
-];
-pub const ROOK_MOVE_MASKS: [Mask; 64] = [
-    Mask(72340172838076926),
-    Mask(144680345676153597),
-    Mask(289360691352306939),
-    Mask(578721382704613623),
-    Mask(1157442765409226991),
-    Mask(2314885530818453727),
-    Mask(4629771061636907199),
-    Mask(9259542123273814143),
-    Mask(72340172838141441),
-    Mask(144680345676217602),
-    Mask(289360691352369924),
-    Mask(578721382704674568),
-    Mask(1157442765409283856),
-    Mask(2314885530818502432),
-    Mask(4629771061636939584),
-    Mask(9259542123273813888),
-    Mask(72340172854657281),
-    Mask(144680345692602882),
-    Mask(289360691368494084),
-    Mask(578721382720276488),
-    Mask(1157442765423841296),
-    Mask(2314885530830970912),
-    Mask(4629771061645230144),
-    Mask(9259542123273748608),
-    Mask(72340177082712321),
-    Mask(144680349887234562),
-    Mask(289360695496279044),
-    Mask(578721386714368008),
-    Mask(1157442769150545936),
-    Mask(2314885534022901792),
-    Mask(4629771063767613504),
-    Mask(9259542123257036928),
-    Mask(72341259464802561),
-    Mask(144681423712944642),
-    Mask(289361752209228804),
-    Mask(578722409201797128),
-    Mask(1157443723186933776),
-    Mask(2314886351157207072),
-    Mask(4629771607097753664),
-    Mask(9259542118978846848),
-    Mask(72618349279904001),
-    Mask(144956323094725122),
-    Mask(289632270724367364),
-    Mask(578984165983651848),
-    Mask(1157687956502220816),
-    Mask(2315095537539358752),
-    Mask(4629910699613634624),
-    Mask(9259541023762186368),
-    Mask(143553341945872641),
-    Mask(215330564830528002),
-    Mask(358885010599838724),
-    Mask(645993902138460168),
-    Mask(1220211685215703056),
-    Mask(2368647251370188832),
-    Mask(4665518383679160384),
-    Mask(9259260648297103488),
-    Mask(18302911464433844481),
-    Mask(18231136449196065282),
-    Mask(18087586418720506884),
-    Mask(17800486357769390088),
-    Mask(17226286235867156496),
-    Mask(16077885992062689312),
-    Mask(13781085504453754944),
-    Mask(9187484529235886208),
-];
+//! Move masks for every piece and square, generated by `build.rs` from `generate.rs`.
+
+use crate::board::{mask::Mask, square::Square};
+
+include!(concat!(env!("OUT_DIR"), "/move_masks.rs"));
+
+/// The eight directions a queen moves in, in the order of `RAY_MASKS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayDirection {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl RayDirection {
+    pub const ALL: [RayDirection; 8] = [
+        RayDirection::North,
+        RayDirection::NorthEast,
+        RayDirection::East,
+        RayDirection::SouthEast,
+        RayDirection::South,
+        RayDirection::SouthWest,
+        RayDirection::West,
+        RayDirection::NorthWest,
+    ];
+
+    pub fn is_diagonal(&self) -> bool {
+        *self as usize % 2 == 1
+    }
+}
+
+/// Squares from `square` to the edge of the board in `direction`, not including `square`.
+pub fn ray(square: Square, direction: RayDirection) -> Mask {
+    RAY_MASKS[direction as usize][square as usize]
+}
+
+/// Squares strictly between `a` and `b` if they share a rank, file or diagonal, and no squares
+/// otherwise.
+pub fn between(a: Square, b: Square) -> Mask {
+    BETWEEN_MASKS[a as usize][b as usize]
+}
+
+/// The whole rank, file or diagonal going through both `a` and `b`, edge to edge, or no squares
+/// if they aren't lined up.
+pub fn line(a: Square, b: Square) -> Mask {
+    LINE_MASKS[a as usize][b as usize]
+}
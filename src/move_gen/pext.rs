@@ -0,0 +1,131 @@
+//! Sliding moves indexed with the BMI2 `pext` instruction, which gathers the blocker bits under
+//! a square's relevant blocker mask into a dense index, so no magic numbers are needed.
+
+use crate::board::{mask::Mask, square::Square};
+
+use super::{
+    direction::Direction,
+    move_masks::{BISHOP_RELEVANT_BLOCKERS, ROOK_RELEVANT_BLOCKERS},
+};
+
+#[derive(Debug)]
+pub struct PextTables {
+    // Rook moves for every square, followed by bishop moves
+    table: Vec<Mask>,
+    rook_offsets: [usize; 64],
+    bishop_offsets: [usize; 64],
+}
+
+impl PextTables {
+    /// Builds the tables, or returns `None` if the CPU doesn't support BMI2.
+    pub fn new() -> Option<Self> {
+        if !has_bmi2() {
+            return None;
+        }
+
+        let mut tables = Self {
+            table: Vec::new(),
+            rook_offsets: [0; 64],
+            bishop_offsets: [0; 64],
+        };
+
+        for (direction, relevant_blockers) in [
+            (Direction::Orthogonal, &ROOK_RELEVANT_BLOCKERS),
+            (Direction::Diagonal, &BISHOP_RELEVANT_BLOCKERS),
+        ] {
+            for (i, mask) in relevant_blockers.iter().enumerate() {
+                let square = Square::from_usize(i).unwrap();
+                let offset = tables.table.len();
+
+                match direction {
+                    Direction::Orthogonal => tables.rook_offsets[i] = offset,
+                    Direction::Diagonal => tables.bishop_offsets[i] = offset,
+                }
+
+                tables.table.resize(offset + (1 << mask.count()), Mask(0));
+                for blockers in mask.subsets() {
+                    // Safe since BMI2 support was checked above
+                    let index = unsafe { pext(blockers.0, mask.0) } as usize;
+                    tables.table[offset + index] = direction.moves_for(square, blockers);
+                }
+            }
+        }
+
+        Some(tables)
+    }
+
+    pub fn get_rook_moves(&self, square: Square, blockers: Mask) -> Mask {
+        let mask = ROOK_RELEVANT_BLOCKERS[square as usize];
+        // Safe since tables only exist if the CPU supports BMI2
+        let index = unsafe { pext(blockers.0, mask.0) } as usize;
+
+        self.table[self.rook_offsets[square as usize] + index]
+    }
+
+    pub fn get_bishop_moves(&self, square: Square, blockers: Mask) -> Mask {
+        let mask = BISHOP_RELEVANT_BLOCKERS[square as usize];
+        let index = unsafe { pext(blockers.0, mask.0) } as usize;
+
+        self.table[self.bishop_offsets[square as usize] + index]
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn has_bmi2() -> bool {
+    std::is_x86_feature_detected!("bmi2")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn has_bmi2() -> bool {
+    false
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2")]
+unsafe fn pext(value: u64, mask: u64) -> u64 {
+    std::arch::x86_64::_pext_u64(value, mask)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+unsafe fn pext(_value: u64, _mask: u64) -> u64 {
+    unreachable!("PEXT tables are never built without BMI2")
+}
+
+#[cfg(test)]
+mod pext_tests {
+    use super::*;
+    use crate::move_gen::SlidingMoves;
+
+    #[test]
+    fn matches_magics_for_every_blocker_subset() {
+        let Some(pext) = PextTables::new() else {
+            println!("BMI2 isn't supported, skipping");
+            return;
+        };
+        let magics = SlidingMoves::with_magics();
+
+        for i in 0..64 {
+            let square = Square::from_usize(i).unwrap();
+
+            for blockers in ROOK_RELEVANT_BLOCKERS[i].subsets() {
+                assert_eq!(
+                    pext.get_rook_moves(square, blockers),
+                    magics.get_rook_moves(square, blockers)
+                );
+            }
+            for blockers in BISHOP_RELEVANT_BLOCKERS[i].subsets() {
+                assert_eq!(
+                    pext.get_bishop_moves(square, blockers),
+                    magics.get_bishop_moves(square, blockers)
+                );
+            }
+
+            // Pieces outside the relevant squares make no difference
+            let everything = Mask(u64::MAX);
+            assert_eq!(
+                pext.get_rook_moves(square, everything),
+                magics.get_rook_moves(square, everything)
+            );
+        }
+    }
+}
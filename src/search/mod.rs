@@ -0,0 +1,1400 @@
+mod pv;
+mod smp;
+pub mod tt;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::{
+        moves::{MoveList, PackedMove},
+        Board,
+    },
+    eval::Score,
+    tablebase::{self, Probe, Wdl},
+};
+
+use self::{
+    pv::PvTable,
+    tt::{Bound, Entry, TranspositionTable},
+};
+
+pub const INFINITY: Score = 32_000;
+pub const MATE: Score = 31_000;
+pub const MAX_PLY: usize = 128;
+
+/// Mate scores are stored as `MATE - ply`, so anything above this is a forced mate.
+pub const MATE_THRESHOLD: Score = MATE - MAX_PLY as Score;
+/// Tablebase wins are scored as `TB_WIN - ply`, which is below any mate but above any
+/// evaluation.
+pub const TB_WIN: Score = MATE_THRESHOLD - MAX_PLY as Score;
+/// Anything above this is a forced mate or a tablebase win.
+const TB_WIN_THRESHOLD: Score = TB_WIN - MAX_PLY as Score;
+
+/// How often (in nodes) the search checks whether it has run out of time.
+const CHECK_INTERVAL: u64 = 1024;
+/// Minimum time between two progress reports.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+/// The move currently searched at the root is only reported once the search has run this long,
+/// so that short searches don't flood the output.
+const CURRENT_MOVE_DELAY: Duration = Duration::from_secs(1);
+
+/// Piece values used for ordering captures (most valuable victim, least valuable attacker).
+const ORDERING_VALUES: [Score; 6] = [1, 3, 3, 5, 9, 20];
+
+/// Half width of the first aspiration window around the previous iteration's score.
+const ASPIRATION_WINDOW: Score = 25;
+/// Aspiration windows aren't used until the scores of iterations settle down.
+const ASPIRATION_MIN_DEPTH: usize = 4;
+
+// Pruning and reduction parameters
+const NULL_MOVE_MIN_DEPTH: usize = 3;
+const REVERSE_FUTILITY_MAX_DEPTH: usize = 6;
+const REVERSE_FUTILITY_MARGIN: Score = 80;
+const RAZORING_MAX_DEPTH: usize = 2;
+const RAZORING_MARGIN: Score = 300;
+const FUTILITY_MAX_DEPTH: usize = 3;
+const FUTILITY_MARGIN: Score = 100;
+const LMR_MIN_DEPTH: usize = 3;
+const LMR_MIN_MOVE_INDEX: usize = 3;
+
+/// Conditions for stopping a search. A search with no limits set runs until it reaches
+/// `MAX_PLY`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchLimits {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+}
+
+/// Switches for the search's pruning, reduction and extension techniques, so that each one can
+/// be tested on its own, along with other settings that change how the search behaves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchConfig {
+    /// Number of best root moves to find, each with its own score and principal variation
+    pub multi_pv: usize,
+    /// Number of threads searching in parallel (Lazy SMP)
+    pub threads: usize,
+    /// How much the side to move at the root dislikes draws, in centipawns
+    pub contempt: Score,
+    pub null_move_pruning: bool,
+    pub late_move_reductions: bool,
+    pub futility_pruning: bool,
+    pub reverse_futility_pruning: bool,
+    pub razoring: bool,
+    pub check_extensions: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            multi_pv: 1,
+            threads: 1,
+            contempt: 0,
+            null_move_pruning: true,
+            late_move_reductions: true,
+            futility_pruning: true,
+            reverse_futility_pruning: true,
+            razoring: true,
+            check_extensions: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// Best move found, or `None` if there are no legal moves
+    pub best_move: Option<PackedMove>,
+    /// Score from the side to move's point of view
+    pub score: Score,
+    /// Deepest fully searched depth
+    pub depth: usize,
+    /// Highest ply reached in the last completed iteration, including quiescence search
+    pub seldepth: usize,
+    pub nodes: u64,
+    pub time: Duration,
+    /// Transposition table usage in permille
+    pub hashfull: u32,
+    /// Principal variation, starting with `best_move`
+    pub pv: Vec<PackedMove>,
+    /// The best `SearchConfig::multi_pv` root moves, best first, each with its score and
+    /// principal variation
+    pub lines: Vec<(PackedMove, Score, Vec<PackedMove>)>,
+}
+
+impl SearchResult {
+    /// Nodes searched per second.
+    pub fn nps(&self) -> u64 {
+        nps(self.nodes, self.time)
+    }
+}
+
+/// Progress reported while a search is running.
+#[derive(Debug, Clone, Copy)]
+pub enum SearchInfo<'a> {
+    /// An iteration of iterative deepening completed
+    Iteration(&'a SearchResult),
+    /// The root started searching its `number`th move (counting from 1)
+    CurrentMove {
+        depth: usize,
+        mv: PackedMove,
+        number: usize,
+    },
+    /// Sent periodically during long iterations
+    Progress {
+        depth: usize,
+        seldepth: usize,
+        nodes: u64,
+        time: Duration,
+        hashfull: u32,
+    },
+}
+
+/// Nodes searched per second.
+pub fn nps(nodes: u64, time: Duration) -> u64 {
+    let micros = time.as_micros().max(1);
+    (nodes as u128 * 1_000_000 / micros) as u64
+}
+
+/// Converts a mate score into the number of moves until mate, negative if the side to move is
+/// getting mated. Returns `None` for other scores.
+pub fn moves_to_mate(score: Score) -> Option<i32> {
+    if score >= MATE_THRESHOLD {
+        Some((MATE - score + 1) / 2)
+    } else if score <= -MATE_THRESHOLD {
+        Some(-(MATE + score) / 2)
+    } else {
+        None
+    }
+}
+
+/// Lets another thread stop a running search, or tell a pondering search that the opponent
+/// played the expected move.
+#[derive(Debug, Default)]
+pub struct SearchControl {
+    stop: AtomicBool,
+    pondering: AtomicBool,
+}
+
+impl SearchControl {
+    /// A pondering search ignores its node and time limits, and doesn't return before it's
+    /// stopped or told about a ponder hit, even if it reaches its depth limit.
+    pub fn new(pondering: bool) -> Self {
+        Self {
+            stop: AtomicBool::new(false),
+            pondering: AtomicBool::new(pondering),
+        }
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Turns a pondering search into a normal one. Its time limit counts from now.
+    pub fn ponderhit(&self) {
+        self.pondering.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+}
+
+/// Searches the current position with iterative deepening until one of the limits is reached,
+/// using a fresh transposition table of the default size.
+pub fn search(board: &mut Board, limits: SearchLimits, config: &SearchConfig) -> SearchResult {
+    let tt = TranspositionTable::new(tt::DEFAULT_SIZE_MB);
+    search_with_report(
+        board,
+        limits,
+        config,
+        &tt,
+        &SearchControl::default(),
+        |_| {},
+    )
+}
+
+/// Like `search`, but with a transposition table that is kept between searches, calling
+/// `report` with the progress of the search as it goes.
+///
+/// With more than one thread configured, helper threads search copies of the board at the same
+/// time, sharing only the transposition table. Only the calling thread reports progress and
+/// checks the limits; the final result is picked by a vote among all threads.
+pub fn search_with_report(
+    board: &mut Board,
+    limits: SearchLimits,
+    config: &SearchConfig,
+    tt: &TranspositionTable,
+    control: &SearchControl,
+    mut report: impl FnMut(SearchInfo),
+) -> SearchResult {
+    let stop = AtomicBool::new(false);
+    let nodes = AtomicU64::new(0);
+    let shared = Shared {
+        tt,
+        control,
+        stop: &stop,
+        nodes: &nodes,
+    };
+
+    thread::scope(|scope| {
+        let helpers = (1..config.threads.max(1))
+            .map(|id| {
+                let mut board = board.clone();
+                let config = SearchConfig {
+                    multi_pv: 1,
+                    ..*config
+                };
+
+                scope.spawn(move || {
+                    Search::new(
+                        &mut board,
+                        SearchLimits::default(),
+                        &config,
+                        id,
+                        shared,
+                        &mut |_| {},
+                    )
+                    .iterative_deepening()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let main = Search::new(board, limits, config, 0, shared, &mut report).iterative_deepening();
+
+        // The result of a pondering search is only wanted once the opponent has moved
+        while control.is_pondering() && !control.is_stopped() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        stop.store(true, Ordering::Relaxed);
+
+        let mut results = vec![main];
+        results.extend(helpers.into_iter().map(|helper| helper.join().unwrap()));
+
+        // MultiPV lines all come from the main thread, so only a single line is voted on
+        let mut result = if config.multi_pv > 1 {
+            results.swap_remove(0)
+        } else {
+            let best = smp::select_best_thread(&results);
+            results.swap_remove(best)
+        };
+
+        result.nodes = nodes.load(Ordering::Relaxed);
+        result.hashfull = tt.hashfull();
+        result
+    })
+}
+
+/// Finds the best `count` moves in the position, best first, each with its score and principal
+/// variation. Returns fewer moves if there aren't enough legal moves.
+pub fn search_multi_pv(
+    board: &mut Board,
+    limits: SearchLimits,
+    config: &SearchConfig,
+    count: usize,
+) -> Vec<(PackedMove, Score, Vec<PackedMove>)> {
+    let config = SearchConfig {
+        multi_pv: count,
+        ..*config
+    };
+
+    search(board, limits, &config).lines
+}
+
+/// Splits the remaining clock time into a time budget for the next move, keeping `overhead` in
+/// reserve for communication delays with the GUI.
+pub fn allocate_time(
+    remaining: Duration,
+    increment: Duration,
+    moves_to_go: Option<u32>,
+    overhead: Duration,
+) -> Duration {
+    const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+    let budget = remaining / moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1) + increment / 2;
+    let available = remaining.saturating_sub(overhead);
+
+    budget.min(available).max(Duration::from_millis(1))
+}
+
+/// State shared between all search threads.
+#[derive(Clone, Copy)]
+struct Shared<'a> {
+    tt: &'a TranspositionTable,
+    control: &'a SearchControl,
+    /// Set by the main thread once it's done, to stop the helpers
+    stop: &'a AtomicBool,
+    /// Nodes searched by all threads, updated every `CHECK_INTERVAL` nodes
+    nodes: &'a AtomicU64,
+}
+
+struct Search<'a> {
+    board: &'a mut Board,
+    limits: SearchLimits,
+    config: &'a SearchConfig,
+    /// 0 for the main thread
+    id: usize,
+    shared: Shared<'a>,
+    report: &'a mut dyn FnMut(SearchInfo),
+    start: Instant,
+    /// When the time limit started counting, which is later than `start` after a ponder hit
+    limit_start: Instant,
+    was_pondering: bool,
+    last_progress: Instant,
+    nodes: u64,
+    /// Nodes already added to the shared node count
+    flushed_nodes: u64,
+    depth: usize,
+    seldepth: usize,
+    stopped: bool,
+
+    /// Indexed by [from][to], incremented whenever a quiet move causes a beta cutoff
+    history: Box<[[Score; 64]; 64]>,
+    pv: PvTable,
+
+    tablebases: Option<Arc<dyn Probe>>,
+    /// The root moves that keep the best tablebase result, if the root is in the tablebases
+    root_moves: Option<MoveList>,
+}
+
+impl<'a> Search<'a> {
+    fn new(
+        board: &'a mut Board,
+        limits: SearchLimits,
+        config: &'a SearchConfig,
+        id: usize,
+        shared: Shared<'a>,
+        report: &'a mut dyn FnMut(SearchInfo),
+    ) -> Self {
+        let tablebases = board.tablebases();
+        let root_moves = tablebases
+            .as_deref()
+            .and_then(|tablebases| tablebase::root_moves(board, tablebases));
+
+        Self {
+            board,
+            limits,
+            config,
+            id,
+            shared,
+            report,
+            start: Instant::now(),
+            limit_start: Instant::now(),
+            was_pondering: shared.control.is_pondering(),
+            last_progress: Instant::now(),
+            nodes: 0,
+            flushed_nodes: 0,
+            depth: 0,
+            seldepth: 0,
+            stopped: false,
+            history: Box::new([[0; 64]; 64]),
+            pv: PvTable::new(),
+            tablebases,
+            root_moves,
+        }
+    }
+
+    fn iterative_deepening(&mut self) -> SearchResult {
+        let max_depth = self.limits.depth.unwrap_or(MAX_PLY).min(MAX_PLY);
+        let line_count = self.config.multi_pv.max(1);
+
+        let mut result = SearchResult {
+            best_move: self.root_moves().first().copied(),
+            score: 0,
+            depth: 0,
+            seldepth: 0,
+            nodes: 0,
+            time: Duration::ZERO,
+            hashfull: 0,
+            pv: Vec::new(),
+            lines: Vec::new(),
+        };
+
+        'deepening: for depth in 1..=max_depth {
+            // Helpers skip some depths so that threads don't all search the same one
+            if self.id > 0 && smp::skip_depth(self.id, depth) {
+                continue;
+            }
+
+            self.depth = depth;
+            self.seldepth = 0;
+
+            let mut lines: Vec<(PackedMove, Score, Vec<PackedMove>)> =
+                Vec::with_capacity(line_count);
+
+            // Each line is the best move among those not already picked by an earlier line
+            for i in 0..line_count {
+                let previous = result.lines.get(i);
+                let excluded = lines.iter().map(|(mv, ..)| *mv).collect::<Vec<_>>();
+
+                let Some((score, best_move)) = self.aspiration_search(
+                    depth,
+                    previous.map(|(_, score, _)| *score),
+                    previous.map(|(mv, ..)| *mv),
+                    &excluded,
+                ) else {
+                    // Results of an unfinished iteration can't be trusted
+                    break 'deepening;
+                };
+
+                match best_move {
+                    Some(mv) => lines.push((mv, score, self.pv.line(0))),
+                    None if i == 0 => {
+                        // Checkmate or stalemate
+                        result.best_move = None;
+                        result.score = score;
+                        result.depth = depth;
+                        break 'deepening;
+                    }
+                    None => break,
+                }
+            }
+
+            // A later line can come out better than an earlier one when the search is unstable
+            lines.sort_by_key(|(_, score, _)| -score);
+
+            let (best_move, score, pv) = &lines[0];
+            result.best_move = Some(*best_move);
+            result.score = *score;
+            result.pv = pv.clone();
+            result.lines = lines;
+            result.depth = depth;
+            result.seldepth = self.seldepth;
+            result.nodes = self.total_nodes();
+            result.time = self.start.elapsed();
+            result.hashfull = self.shared.tt.hashfull();
+
+            (self.report)(SearchInfo::Iteration(&result));
+        }
+
+        self.flush_nodes();
+        result.time = self.start.elapsed();
+        result
+    }
+
+    /// Searches the root with a narrow window around the previous iteration's score, widening it
+    /// whenever the score falls outside of it. Returns `None` if the search was stopped.
+    fn aspiration_search(
+        &mut self,
+        depth: usize,
+        previous_score: Option<Score>,
+        previous_best: Option<PackedMove>,
+        excluded: &[PackedMove],
+    ) -> Option<(Score, Option<PackedMove>)> {
+        let mut window = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match previous_score {
+            Some(score) if depth >= ASPIRATION_MIN_DEPTH => (
+                (score - window).max(-INFINITY),
+                (score + window).min(INFINITY),
+            ),
+            _ => (-INFINITY, INFINITY),
+        };
+
+        loop {
+            let (score, best_move) = self.search_root(depth, alpha, beta, previous_best, excluded);
+
+            if self.stopped {
+                return None;
+            }
+
+            window *= 2;
+
+            if score <= alpha && alpha > -INFINITY {
+                alpha = (score - window).max(-INFINITY);
+            } else if score >= beta && beta < INFINITY {
+                beta = (score + window).min(INFINITY);
+            } else {
+                return Some((score, best_move));
+            }
+        }
+    }
+
+    /// Searches every root move except the `excluded` ones. Returns no move if there are no
+    /// legal moves, or if all of them are excluded (with a score of `-INFINITY`).
+    fn search_root(
+        &mut self,
+        depth: usize,
+        mut alpha: Score,
+        beta: Score,
+        previous_best: Option<PackedMove>,
+        excluded: &[PackedMove],
+    ) -> (Score, Option<PackedMove>) {
+        self.pv.clear(0);
+
+        let mut moves = self.root_moves();
+        if moves.is_empty() {
+            return (self.no_moves_score(0), None);
+        }
+
+        moves.retain(|mv| !excluded.contains(mv));
+        self.order_moves(&mut moves, previous_best);
+
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+
+        for (i, mv) in moves.into_iter().enumerate() {
+            if self.start.elapsed() >= CURRENT_MOVE_DELAY {
+                (self.report)(SearchInfo::CurrentMove {
+                    depth,
+                    mv,
+                    number: excluded.len() + i + 1,
+                });
+            }
+
+            self.board.make_packed_move(mv).unwrap();
+            let score = self.principal_variation_search(i, depth - 1, 1, alpha, beta, depth - 1);
+            self.board.unmake_move().unwrap();
+
+            if self.stopped {
+                break;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+                self.pv.update(0, mv);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        (best_score, best_move)
+    }
+
+    /// The legal moves at the root, narrowed down by the tablebases if possible.
+    fn root_moves(&self) -> MoveList {
+        match &self.root_moves {
+            Some(moves) => moves.clone(),
+            None => self.board.legal_moves(),
+        }
+    }
+
+    /// Searches the move just made, returning its score from the point of view of the side that
+    /// made it. Only the first move is searched with the full window; the others are searched
+    /// with a zero window (at `reduced_depth`) just to prove that they're worse, and only
+    /// searched again with the full window and depth if that fails.
+    fn principal_variation_search(
+        &mut self,
+        move_index: usize,
+        depth: usize,
+        ply: usize,
+        alpha: Score,
+        beta: Score,
+        reduced_depth: usize,
+    ) -> Score {
+        if move_index == 0 {
+            return -self.negamax(depth, ply, -beta, -alpha, true);
+        }
+
+        let mut score = -self.negamax(reduced_depth, ply, -alpha - 1, -alpha, true);
+
+        if score > alpha && reduced_depth < depth {
+            score = -self.negamax(depth, ply, -alpha - 1, -alpha, true);
+        }
+        if score > alpha && score < beta {
+            score = -self.negamax(depth, ply, -beta, -alpha, true);
+        }
+
+        score
+    }
+
+    fn negamax(
+        &mut self,
+        mut depth: usize,
+        ply: usize,
+        mut alpha: Score,
+        beta: Score,
+        allow_null_move: bool,
+    ) -> Score {
+        self.pv.clear(ply);
+
+        if self.should_stop() {
+            return 0;
+        }
+
+        if self.board.is_draw() {
+            return self.draw_score(ply);
+        }
+
+        let in_check = self.board.in_check();
+
+        // Don't stop searching while in check, so that mates at the horizon aren't missed
+        if in_check && self.config.check_extensions {
+            depth += 1;
+        }
+
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(ply, alpha, beta);
+        }
+
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        let is_pv = beta - alpha > 1;
+        let original_alpha = alpha;
+
+        let key = self.board.current_position().hash();
+        let entry = self.shared.tt.probe(key);
+
+        if let Some(entry) = entry {
+            let score = score_from_tt(entry.score, ply);
+
+            if !is_pv && entry.depth as usize >= depth {
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        // Tablebase results take the 50 move rule into account, so they're only exact right
+        // after the counter was reset
+        if let Some(tablebases) = &self.tablebases {
+            let state = self.board.current_position();
+
+            if state.halfmoves() == 0 && tablebases.covers(state) {
+                if let Some(wdl) = tablebases.probe_wdl(state) {
+                    let score = match wdl {
+                        Wdl::Win => TB_WIN - ply as Score,
+                        Wdl::CursedWin => 1,
+                        Wdl::Draw => self.draw_score(ply),
+                        Wdl::BlessedLoss => -1,
+                        Wdl::Loss => -TB_WIN + ply as Score,
+                    };
+
+                    self.shared.tt.store(
+                        key,
+                        Entry {
+                            best_move: None,
+                            score: score_to_tt(score, ply),
+                            depth: MAX_PLY as u8,
+                            bound: Bound::Exact,
+                        },
+                    );
+                    return score;
+                }
+            }
+        }
+
+        let static_eval = self.board.evaluate();
+
+        if !is_pv && !in_check {
+            // Reverse futility pruning: we're so far ahead that a shallow search won't change that
+            if self.config.reverse_futility_pruning
+                && depth <= REVERSE_FUTILITY_MAX_DEPTH
+                && static_eval - REVERSE_FUTILITY_MARGIN * depth as Score >= beta
+            {
+                return static_eval;
+            }
+
+            // Razoring: we're so far behind that only a capture could help
+            if self.config.razoring
+                && depth <= RAZORING_MAX_DEPTH
+                && static_eval + RAZORING_MARGIN * (depth as Score) < alpha
+            {
+                let score = self.quiescence(ply, alpha, beta);
+                if score < alpha {
+                    return score;
+                }
+            }
+
+            // Null move pruning: if passing still fails high, a real move almost certainly would.
+            // This doesn't hold in zugzwang, which mostly happens with only pawns left.
+            let color = self.board.current_position().active_color();
+            if self.config.null_move_pruning
+                && allow_null_move
+                && depth >= NULL_MOVE_MIN_DEPTH
+                && static_eval >= beta
+                && self.board.current_position().has_non_pawn_material(color)
+            {
+                let reduction = 2 + depth / 4;
+
+                self.board.make_null_move();
+                let score = -self.negamax(
+                    depth.saturating_sub(1 + reduction),
+                    ply + 1,
+                    -beta,
+                    -beta + 1,
+                    false,
+                );
+                self.board.unmake_move().unwrap();
+
+                if self.stopped {
+                    return 0;
+                }
+
+                if score >= beta {
+                    // Don't trust mate scores from a null move search
+                    return if score >= MATE_THRESHOLD { beta } else { score };
+                }
+            }
+        }
+
+        let mut moves = self.board.legal_moves();
+        if moves.is_empty() {
+            return self.no_moves_score(ply);
+        }
+
+        let tt_move = entry.and_then(|entry| entry.best_move);
+        self.order_moves(&mut moves, tt_move);
+
+        // Futility pruning: quiet moves can't raise a hopeless static evaluation above alpha
+        let futile = self.config.futility_pruning
+            && !is_pv
+            && !in_check
+            && depth <= FUTILITY_MAX_DEPTH
+            && static_eval + FUTILITY_MARGIN * depth as Score <= alpha;
+
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+
+        for (i, mv) in moves.into_iter().enumerate() {
+            let is_quiet = self.is_quiet(mv);
+
+            if futile && is_quiet && i > 0 {
+                continue;
+            }
+
+            self.board.make_packed_move(mv).unwrap();
+            let gives_check = self.board.in_check();
+
+            // Late move reductions: quiet moves ordered late rarely turn out to be best, so
+            // search them shallower first and only search them fully if they beat alpha
+            let reduced_depth = if self.config.late_move_reductions
+                && depth >= LMR_MIN_DEPTH
+                && i >= LMR_MIN_MOVE_INDEX
+                && is_quiet
+                && !in_check
+                && !gives_check
+            {
+                let reduction = self.lmr_reduction(depth, i, mv);
+                (depth - 1).saturating_sub(reduction).max(1)
+            } else {
+                depth - 1
+            };
+
+            let score =
+                self.principal_variation_search(i, depth - 1, ply + 1, alpha, beta, reduced_depth);
+
+            self.board.unmake_move().unwrap();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+                self.pv.update(ply, mv);
+            }
+            if alpha >= beta {
+                if is_quiet {
+                    self.history[mv.from_square() as usize][mv.to_square() as usize] +=
+                        (depth * depth) as Score;
+                }
+                break;
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+
+        self.shared.tt.store(
+            key,
+            Entry {
+                best_move,
+                score: score_to_tt(best_score, ply),
+                depth: depth.min(u8::MAX as usize) as u8,
+                bound,
+            },
+        );
+
+        best_score
+    }
+
+    /// How many plies to reduce a late quiet move by. Moves with a good history are reduced
+    /// less.
+    fn lmr_reduction(&self, depth: usize, move_index: usize, mv: PackedMove) -> usize {
+        let mut reduction = (0.75 + (depth as f64).ln() * (move_index as f64).ln() / 2.25) as usize;
+
+        if reduction > 0 && self.history[mv.from_square() as usize][mv.to_square() as usize] > 0 {
+            reduction -= 1;
+        }
+
+        reduction
+    }
+
+    /// Searches captures only, so that the static evaluation is never taken in the middle of an
+    /// exchange.
+    fn quiescence(&mut self, ply: usize, mut alpha: Score, beta: Score) -> Score {
+        self.pv.clear(ply);
+
+        if self.should_stop() {
+            return 0;
+        }
+
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        let stand_pat = self.board.evaluate();
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat;
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        let mut captures = self.board.legal_moves();
+        captures.retain(|mv| mv.flag().is_capture() && !mv.flag().is_underpromotion());
+        self.order_moves(&mut captures, None);
+
+        let mut best_score = stand_pat;
+
+        for mv in captures {
+            self.board.make_packed_move(mv).unwrap();
+            let score = -self.quiescence(ply + 1, -beta, -alpha);
+            self.board.unmake_move().unwrap();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best_score
+    }
+
+    /// Score of a position without any legal moves: checkmate or stalemate.
+    fn no_moves_score(&self, ply: usize) -> Score {
+        if self.board.in_check() {
+            -MATE + ply as Score
+        } else {
+            self.draw_score(ply)
+        }
+    }
+
+    /// Score of a drawn position, which is worth `contempt` less than equal for the side to move
+    /// at the root.
+    fn draw_score(&self, ply: usize) -> Score {
+        if ply.is_multiple_of(2) {
+            -self.config.contempt
+        } else {
+            self.config.contempt
+        }
+    }
+
+    /// Whether the move is neither a capture nor a promotion.
+    fn is_quiet(&self, mv: PackedMove) -> bool {
+        !mv.flag().is_promotion() && !mv.flag().is_capture()
+    }
+
+    /// Puts `first` at the front, followed by captures ordered by MVV-LVA, then quiet moves
+    /// ordered by history, and underpromotions last.
+    fn order_moves(&self, moves: &mut MoveList, first: Option<PackedMove>) {
+        const CAPTURE_BONUS: Score = 1_000_000;
+
+        let state = self.board.current_position();
+
+        moves.sort_by_score(|mv| {
+            if Some(*mv) == first {
+                return Score::MAX;
+            }
+            if mv.flag().is_underpromotion() {
+                return Score::MIN;
+            }
+
+            let (from, to) = (mv.from_square(), mv.to_square());
+            let Some(victim) = state.piece_at_square(to) else {
+                return self.history[from as usize][to as usize];
+            };
+            let attacker = state.piece_at_square(from).unwrap();
+
+            CAPTURE_BONUS + ORDERING_VALUES[victim.kind_index()] * 10
+                - ORDERING_VALUES[attacker.kind_index()]
+        });
+    }
+
+    /// Nodes searched so far by all threads.
+    fn total_nodes(&self) -> u64 {
+        self.shared.nodes.load(Ordering::Relaxed) + self.nodes - self.flushed_nodes
+    }
+
+    fn flush_nodes(&mut self) {
+        self.shared
+            .nodes
+            .fetch_add(self.nodes - self.flushed_nodes, Ordering::Relaxed);
+        self.flushed_nodes = self.nodes;
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        // Helper threads only stop when the main thread tells them to
+        if self.id > 0 {
+            if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+                self.flush_nodes();
+                self.stopped = self.shared.stop.load(Ordering::Relaxed);
+            }
+            return self.stopped;
+        }
+
+        let pondering = self.shared.control.is_pondering();
+
+        if let Some(nodes) = self.limits.nodes {
+            if !pondering && self.total_nodes() >= nodes {
+                self.stopped = true;
+            }
+        }
+
+        if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.flush_nodes();
+            let elapsed = self.start.elapsed();
+
+            if self.was_pondering && !pondering {
+                self.limit_start = Instant::now();
+            }
+            self.was_pondering = pondering;
+
+            if self.shared.control.is_stopped() {
+                self.stopped = true;
+            }
+
+            if !pondering
+                && self
+                    .limits
+                    .movetime
+                    .is_some_and(|movetime| self.limit_start.elapsed() >= movetime)
+            {
+                self.stopped = true;
+            }
+
+            if self.last_progress.elapsed() >= PROGRESS_INTERVAL {
+                self.last_progress = Instant::now();
+
+                let info = SearchInfo::Progress {
+                    depth: self.depth,
+                    seldepth: self.seldepth,
+                    nodes: self.total_nodes(),
+                    time: elapsed,
+                    hashfull: self.shared.tt.hashfull(),
+                };
+                (self.report)(info);
+            }
+        }
+
+        self.stopped
+    }
+}
+
+/// Mate and tablebase scores are relative to the root, but the same position can be reached at
+/// different plies, so they're stored in the transposition table relative to the position
+/// instead.
+fn score_to_tt(score: Score, ply: usize) -> Score {
+    if score >= TB_WIN_THRESHOLD {
+        score + ply as Score
+    } else if score <= -TB_WIN_THRESHOLD {
+        score - ply as Score
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: Score, ply: usize) -> Score {
+    if score >= TB_WIN_THRESHOLD {
+        score - ply as Score
+    } else if score <= -TB_WIN_THRESHOLD {
+        score + ply as Score
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+    use crate::board::{moves::Move, piece::Piece, square::Square, BoardState, START_FEN};
+
+    fn search_fen(fen: &str, depth: usize) -> SearchResult {
+        search_fen_with(fen, depth, &SearchConfig::default())
+    }
+
+    fn search_fen_with(fen: &str, depth: usize, config: &SearchConfig) -> SearchResult {
+        let mut board = Board::new(fen).unwrap();
+        search(
+            &mut board,
+            SearchLimits {
+                depth: Some(depth),
+                ..Default::default()
+            },
+            config,
+        )
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let result = search_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2);
+
+        assert_eq!(
+            result.best_move.map(Move::from),
+            Move::from_long_algebraic("a1a8")
+        );
+        assert_eq!(result.score, MATE - 1);
+    }
+
+    #[test]
+    fn wins_hanging_queen() {
+        let result = search_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2);
+
+        assert_eq!(
+            result.best_move.map(Move::from),
+            Move::from_long_algebraic("d2d5")
+        );
+    }
+
+    #[test]
+    fn checkmated_and_stalemated() {
+        let mated = search_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", 1);
+        assert_eq!(mated.best_move, None);
+        assert_eq!(mated.score, -MATE);
+
+        let stalemate = search_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 1);
+        assert_eq!(stalemate.best_move, None);
+        assert_eq!(stalemate.score, 0);
+    }
+
+    #[test]
+    fn respects_node_limit() {
+        let mut board = Board::new(crate::board::START_FEN).unwrap();
+        let result = search(
+            &mut board,
+            SearchLimits {
+                nodes: Some(500),
+                ..Default::default()
+            },
+            &SearchConfig::default(),
+        );
+
+        assert!(result.best_move.is_some());
+        assert!(result.nodes <= 501);
+    }
+
+    #[test]
+    fn pruning_keeps_tactics() {
+        // Mate in two: 1. Rd8+ Rxd8 2. Rxd8#
+        const FEN: &str = "r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1";
+
+        let all_off = SearchConfig {
+            multi_pv: 1,
+            threads: 1,
+            contempt: 0,
+            null_move_pruning: false,
+            late_move_reductions: false,
+            futility_pruning: false,
+            reverse_futility_pruning: false,
+            razoring: false,
+            check_extensions: false,
+        };
+
+        let pruned = search_fen(FEN, 4);
+        let unpruned = search_fen_with(FEN, 4, &all_off);
+
+        assert_eq!(unpruned.score, MATE - 3);
+        assert_eq!(pruned.score, MATE - 3);
+        assert!(pruned.nodes < unpruned.nodes);
+    }
+
+    #[test]
+    fn reports_principal_variation() {
+        let result = search_fen("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1", 5);
+
+        let pv = result
+            .pv
+            .iter()
+            .map(|mv| mv.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(pv, ["d2d8", "a8d8", "d1d8"]);
+        assert_eq!(result.best_move, result.pv.first().copied());
+    }
+
+    #[test]
+    fn helper_threads_agree() {
+        let config = SearchConfig {
+            threads: 3,
+            ..Default::default()
+        };
+        let result = search_fen_with("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1", 4, &config);
+
+        assert_eq!(result.score, MATE - 3);
+        assert_eq!(
+            result.best_move.map(Move::from),
+            Move::from_long_algebraic("d2d8")
+        );
+    }
+
+    #[test]
+    fn transposition_table_is_reused() {
+        let mut board = Board::new(START_FEN).unwrap();
+        let tt = TranspositionTable::new(1);
+        let limits = SearchLimits {
+            depth: Some(4),
+            ..Default::default()
+        };
+
+        let first = search_with_report(
+            &mut board,
+            limits,
+            &SearchConfig::default(),
+            &tt,
+            &SearchControl::default(),
+            |_| {},
+        );
+        let second = search_with_report(
+            &mut board,
+            limits,
+            &SearchConfig::default(),
+            &tt,
+            &SearchControl::default(),
+            |_| {},
+        );
+
+        assert!(first.hashfull > 0);
+        assert!(second.nodes < first.nodes);
+    }
+
+    #[test]
+    fn pondering_waits_for_ponderhit() {
+        let control = SearchControl::new(true);
+        let limits = SearchLimits {
+            nodes: Some(100),
+            ..Default::default()
+        };
+
+        let mut board = Board::new(START_FEN).unwrap();
+        let tt = TranspositionTable::new(1);
+
+        thread::scope(|scope| {
+            let search = scope.spawn(|| {
+                search_with_report(
+                    &mut board,
+                    limits,
+                    &SearchConfig::default(),
+                    &tt,
+                    &control,
+                    |_| {},
+                )
+            });
+
+            // The node limit only applies after the ponder hit
+            thread::sleep(Duration::from_millis(200));
+            assert!(!search.is_finished());
+
+            control.ponderhit();
+            let result = search.join().unwrap();
+            assert!(result.nodes > 100);
+            assert!(result.best_move.is_some());
+        });
+    }
+
+    #[test]
+    fn finds_several_lines() {
+        // White is in check and has only three legal moves
+        const FEN: &str = "7k/8/8/8/8/8/1r6/QK6 w - - 0 1";
+
+        let mut board = Board::new(FEN).unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..Default::default()
+        };
+        let lines = search_multi_pv(&mut board, limits, &SearchConfig::default(), 5);
+
+        assert_eq!(lines.len(), board.legal_moves().len());
+        assert!(lines.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        for (mv, _, pv) in &lines {
+            assert_eq!(pv.first(), Some(mv));
+        }
+
+        let moves = lines.iter().map(|(mv, ..)| *mv).collect::<Vec<_>>();
+        for mv in board.legal_moves() {
+            assert!(moves.contains(&mv));
+        }
+    }
+
+    #[test]
+    fn contempt_scores_draws() {
+        // Every move leads to a draw by insufficient material
+        const FEN: &str = "8/8/8/4k3/8/8/8/4K1N1 w - - 0 1";
+
+        for contempt in [0, 25, -10] {
+            let config = SearchConfig {
+                contempt,
+                ..Default::default()
+            };
+
+            assert_eq!(search_fen_with(FEN, 3, &config).score, -contempt);
+        }
+    }
+
+    /// Pretends that whoever has a queen wins any position with up to three pieces, and wins
+    /// fastest with the queen on h8.
+    #[derive(Debug)]
+    struct QueenWins;
+
+    impl Probe for QueenWins {
+        fn max_pieces(&self) -> u32 {
+            3
+        }
+
+        fn probe_wdl(&self, state: &BoardState) -> Option<Wdl> {
+            let color = state.active_color();
+
+            if state.mask(Piece::Queen(color)).0 != 0 {
+                Some(Wdl::Win)
+            } else if state.mask(Piece::Queen(color.swapped())).0 != 0 {
+                Some(Wdl::Loss)
+            } else {
+                Some(Wdl::Draw)
+            }
+        }
+
+        fn probe_dtz(&self, state: &BoardState) -> Option<i32> {
+            let queen_on_h8 = matches!(state.piece_at_square(Square::H8), Some(Piece::Queen(_)));
+            let plies = if queen_on_h8 { 1 } else { 5 };
+
+            Some(match self.probe_wdl(state)? {
+                Wdl::Win => plies,
+                Wdl::Loss => -plies,
+                _ => 0,
+            })
+        }
+    }
+
+    fn search_fen_with_tablebases(fen: &str, depth: usize) -> SearchResult {
+        let mut board = Board::new(fen).unwrap();
+        board.set_tablebases(Some(Arc::new(QueenWins)));
+
+        search(
+            &mut board,
+            SearchLimits {
+                depth: Some(depth),
+                ..Default::default()
+            },
+            &SearchConfig::default(),
+        )
+    }
+
+    #[test]
+    fn scores_tablebase_positions_exactly() {
+        // Taking the rook with either piece leaves a won KQvK
+        let result = search_fen_with_tablebases("8/8/8/4k3/8/8/1r6/KQ6 w - - 0 1", 2);
+        assert_eq!(result.score, TB_WIN - 1);
+    }
+
+    #[test]
+    fn plays_tablebase_root_moves() {
+        let result = search_fen_with_tablebases("8/8/4k3/8/8/8/8/Q1K5 w - - 0 1", 3);
+        assert_eq!(
+            result.best_move.map(Move::from),
+            Move::from_long_algebraic("a1h8")
+        );
+    }
+
+    #[test]
+    fn stores_tablebase_scores_relative_to_the_position() {
+        assert_eq!(score_to_tt(TB_WIN - 5, 3), TB_WIN - 2);
+        assert_eq!(score_from_tt(TB_WIN - 2, 7), TB_WIN - 9);
+        assert_eq!(score_to_tt(-TB_WIN + 5, 3), -TB_WIN + 2);
+        assert_eq!(score_from_tt(-TB_WIN + 2, 7), -TB_WIN + 9);
+        assert_eq!(score_to_tt(500, 3), 500);
+    }
+
+    #[test]
+    fn converts_mate_scores() {
+        assert_eq!(moves_to_mate(MATE - 1), Some(1));
+        assert_eq!(moves_to_mate(MATE - 3), Some(2));
+        assert_eq!(moves_to_mate(-MATE + 2), Some(-1));
+        assert_eq!(moves_to_mate(-MATE), Some(0));
+        assert_eq!(moves_to_mate(150), None);
+    }
+
+    #[test]
+    fn reports_every_iteration() {
+        let mut board = Board::new(START_FEN).unwrap();
+        let mut depths = Vec::new();
+
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..Default::default()
+        };
+        let tt = TranspositionTable::new(1);
+        let result = search_with_report(
+            &mut board,
+            limits,
+            &SearchConfig::default(),
+            &tt,
+            &SearchControl::default(),
+            |info| {
+                if let SearchInfo::Iteration(result) = info {
+                    assert!(result.seldepth >= result.depth);
+                    depths.push(result.depth);
+                }
+            },
+        );
+
+        assert_eq!(depths, [1, 2, 3]);
+        assert_eq!(result.depth, 3);
+    }
+
+    #[test]
+    fn allocates_time_from_clock() {
+        let overhead = Duration::from_millis(50);
+
+        let budget = allocate_time(
+            Duration::from_secs(60),
+            Duration::from_secs(1),
+            None,
+            overhead,
+        );
+        assert_eq!(budget, Duration::from_millis(2500));
+
+        let budget = allocate_time(Duration::from_millis(20), Duration::ZERO, Some(1), overhead);
+        assert_eq!(budget, Duration::from_millis(1));
+
+        let budget = allocate_time(Duration::from_secs(1), Duration::ZERO, Some(1), overhead);
+        assert_eq!(budget, Duration::from_millis(950));
+    }
+}
@@ -0,0 +1,70 @@
+use crate::board::moves::PackedMove;
+
+use super::MAX_PLY;
+
+/// Triangular principal variation table. Row `ply` holds the best line found from that ply
+/// onwards, which is built by prepending the best move to the row of the ply below it.
+pub struct PvTable {
+    moves: Box<[[Option<PackedMove>; MAX_PLY + 1]; MAX_PLY + 1]>,
+    lengths: [usize; MAX_PLY + 1],
+}
+
+impl PvTable {
+    pub fn new() -> Self {
+        Self {
+            moves: Box::new([[None; MAX_PLY + 1]; MAX_PLY + 1]),
+            lengths: [0; MAX_PLY + 1],
+        }
+    }
+
+    /// Forgets the line at `ply`, which has to happen whenever a node is entered.
+    pub fn clear(&mut self, ply: usize) {
+        self.lengths[ply] = 0;
+    }
+
+    /// Makes `mv` followed by the line at `ply + 1` the new line at `ply`.
+    pub fn update(&mut self, ply: usize, mv: PackedMove) {
+        let child_length = self.lengths.get(ply + 1).copied().unwrap_or(0);
+        let length = (child_length + 1).min(MAX_PLY + 1 - ply);
+
+        self.moves[ply][0] = Some(mv);
+        for i in 1..length {
+            self.moves[ply][i] = self.moves[ply + 1][i - 1];
+        }
+        self.lengths[ply] = length;
+    }
+
+    /// The current best line starting at `ply`.
+    pub fn line(&self, ply: usize) -> Vec<PackedMove> {
+        self.moves[ply][..self.lengths[ply]]
+            .iter()
+            .map(|mv| mv.unwrap())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod pv_tests {
+    use super::*;
+    use crate::board::{moves::MoveFlag, square::Square};
+
+    #[test]
+    fn builds_line_from_child() {
+        let e2e4 = PackedMove::new(Square::E2, Square::E4, MoveFlag::DoublePush);
+        let e7e5 = PackedMove::new(Square::E7, Square::E5, MoveFlag::DoublePush);
+        let g1f3 = PackedMove::new(Square::G1, Square::F3, MoveFlag::Quiet);
+
+        let mut pv = PvTable::new();
+        pv.clear(3);
+        pv.update(2, g1f3);
+        pv.update(1, e7e5);
+        pv.update(0, e2e4);
+
+        assert_eq!(pv.line(0), vec![e2e4, e7e5, g1f3]);
+
+        // A new child line replaces the old one
+        pv.clear(1);
+        pv.update(0, g1f3);
+        assert_eq!(pv.line(0), vec![g1f3]);
+    }
+}
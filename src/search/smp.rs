@@ -0,0 +1,117 @@
+//! Helpers for Lazy SMP: threads search the same position independently and only communicate
+//! through the transposition table.
+
+use std::collections::HashMap;
+
+use super::{SearchResult, MATE_THRESHOLD};
+
+// Helper threads are split into groups that skip `SKIP_SIZE` depths at a time, each starting at
+// a different phase, so that they spread out over neighbouring depths
+const SKIP_SIZE: [usize; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [usize; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+/// Whether the helper thread with the given id (starting from 1) should skip `depth`.
+pub fn skip_depth(id: usize, depth: usize) -> bool {
+    let i = (id - 1) % SKIP_SIZE.len();
+    ((depth + SKIP_PHASE[i]) / SKIP_SIZE[i]) % 2 == 1
+}
+
+/// Picks the thread whose result should be played. Every thread votes for its best move, with
+/// deeper searches and higher scores counting for more, and the thread with the most voted move
+/// wins, preferring deeper searches. A thread that found a mate always wins. Falls back to the
+/// main thread (index 0) if no helper finished an iteration.
+pub fn select_best_thread(results: &[SearchResult]) -> usize {
+    let finished = || {
+        results
+            .iter()
+            .enumerate()
+            .filter(|(_, result)| result.depth > 0 && result.best_move.is_some())
+    };
+
+    let Some(min_score) = finished().map(|(_, result)| result.score).min() else {
+        return 0;
+    };
+
+    let mut votes = HashMap::new();
+    for (_, result) in finished() {
+        *votes.entry(result.best_move).or_insert(0) +=
+            (result.score - min_score + 14) as i64 * result.depth as i64;
+    }
+
+    let mut best = 0;
+    for (i, result) in finished() {
+        let current = &results[best];
+
+        let better = if current.depth == 0 || current.best_move.is_none() {
+            true
+        } else if current.score >= MATE_THRESHOLD || result.score >= MATE_THRESHOLD {
+            result.score > current.score
+        } else {
+            (votes[&result.best_move], result.depth) > (votes[&current.best_move], current.depth)
+        };
+
+        if better {
+            best = i;
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod smp_tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        board::moves::{Move, MoveFlag, PackedMove},
+        search::MATE,
+    };
+
+    fn result(mv: &str, score: i32, depth: usize) -> SearchResult {
+        let mv = Move::from_long_algebraic(mv).unwrap();
+        let mv = PackedMove::new(mv.from, mv.to, MoveFlag::Quiet);
+
+        SearchResult {
+            best_move: Some(mv),
+            score,
+            depth,
+            seldepth: depth,
+            nodes: 0,
+            time: Duration::ZERO,
+            hashfull: 0,
+            pv: vec![mv],
+            lines: vec![(mv, score, vec![mv])],
+        }
+    }
+
+    #[test]
+    fn helpers_spread_over_depths() {
+        assert!(!skip_depth(1, 4));
+        assert!(skip_depth(2, 4));
+        assert!((1..=20).any(|id| skip_depth(id, 7)));
+        assert!((1..=20).any(|id| !skip_depth(id, 7)));
+    }
+
+    #[test]
+    fn majority_move_wins() {
+        let results = [
+            result("e2e4", 20, 10),
+            result("d2d4", 25, 10),
+            result("d2d4", 22, 11),
+        ];
+        assert_eq!(select_best_thread(&results), 2);
+    }
+
+    #[test]
+    fn mate_wins() {
+        let results = [result("e2e4", 20, 10), result("h5f7", MATE - 3, 6)];
+        assert_eq!(select_best_thread(&results), 1);
+    }
+
+    #[test]
+    fn unfinished_helpers_are_ignored() {
+        let results = [result("e2e4", 20, 10), result("d2d4", 500, 0)];
+        assert_eq!(select_best_thread(&results), 0);
+    }
+}
@@ -0,0 +1,182 @@
+//! Transposition table shared between search threads without any locking.
+//!
+//! Each slot holds two atomics: the packed entry data, and the position's hash XORed with that
+//! data. If two threads write the same slot at the same time, the key check on the next probe
+//! fails and the torn entry is simply treated as a miss.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{board::moves::PackedMove, eval::Score};
+
+pub const DEFAULT_SIZE_MB: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The search failed high, so the score is at least this
+    Lower,
+    /// The search failed low, so the score is at most this
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub best_move: Option<PackedMove>,
+    pub score: Score,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+impl Entry {
+    // Data layout: packed move (16 bits, all zeroes for none since a1a1 is never a move), bound
+    // (2 bits), depth (8 bits), score (16 bits)
+    fn pack(&self) -> u64 {
+        let data = self.best_move.map_or(0, |mv| mv.bits() as u64);
+
+        let bound = match self.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+
+        data | bound << 16 | (self.depth as u64) << 18 | (self.score as i16 as u16 as u64) << 26
+    }
+
+    fn unpack(data: u64) -> Option<Self> {
+        let bound = match (data >> 16) & 0b11 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            // Empty slot
+            _ => return None,
+        };
+
+        let best_move = match data as u16 {
+            0 => None,
+            bits => Some(PackedMove::from_bits(bits)?),
+        };
+
+        Some(Self {
+            best_move,
+            score: (data >> 26) as u16 as i16 as Score,
+            depth: (data >> 18) as u8,
+            bound,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+#[derive(Debug)]
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        let count = (size_mb * 1024 * 1024 / std::mem::size_of::<Slot>()).max(1);
+
+        Self {
+            slots: (0..count).map(|_| Slot::default()).collect(),
+        }
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn slot(&self, key: u64) -> &Slot {
+        let index = (key as u128 * self.slots.len() as u128) >> 64;
+        &self.slots[index as usize]
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let slot = self.slot(key);
+        let data = slot.data.load(Ordering::Relaxed);
+
+        if slot.key.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+
+        Entry::unpack(data)
+    }
+
+    /// Stores an entry, unless the slot already holds a deeper search of the same position.
+    pub fn store(&self, key: u64, entry: Entry) {
+        let slot = self.slot(key);
+
+        if let Some(old) = self.probe(key) {
+            if old.depth > entry.depth && entry.bound != Bound::Exact {
+                return;
+            }
+        }
+
+        let data = entry.pack();
+        slot.key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    /// How full the table is in permille, estimated from the first thousand slots.
+    pub fn hashfull(&self) -> u32 {
+        let sample = &self.slots[..self.slots.len().min(1000)];
+        let used = sample
+            .iter()
+            .filter(|slot| slot.data.load(Ordering::Relaxed) != 0)
+            .count();
+
+        (used * 1000 / sample.len()) as u32
+    }
+}
+
+#[cfg(test)]
+mod tt_tests {
+    use super::*;
+    use crate::board::{moves::MoveFlag, square::Square};
+
+    #[test]
+    fn stores_and_probes() {
+        let table = TranspositionTable::new(1);
+        let entry = Entry {
+            best_move: Some(PackedMove::new(
+                Square::E7,
+                Square::E8,
+                MoveFlag::QueenPromotion,
+            )),
+            score: -30_990,
+            depth: 12,
+            bound: Bound::Lower,
+        };
+
+        assert_eq!(table.probe(0xdead_beef), None);
+
+        table.store(0xdead_beef, entry);
+        assert_eq!(table.probe(0xdead_beef), Some(entry));
+        assert!(table.hashfull() <= 1);
+
+        table.clear();
+        assert_eq!(table.probe(0xdead_beef), None);
+    }
+
+    #[test]
+    fn keeps_deeper_entries() {
+        let table = TranspositionTable::new(1);
+        let deep = Entry {
+            best_move: None,
+            score: 50,
+            depth: 8,
+            bound: Bound::Upper,
+        };
+
+        table.store(42, deep);
+        table.store(42, Entry { depth: 2, ..deep });
+        assert_eq!(table.probe(42), Some(deep));
+    }
+}
@@ -0,0 +1,516 @@
+//! Endgame tablebases.
+//!
+//! The search only talks to tablebases through the `Probe` trait: WDL results (win, draw or
+//! loss with the 50 move rule taken into account) are used to cut off the search with exact
+//! scores, and DTZ results (distance to the next capture or pawn move) are used at the root to
+//! only play moves that keep the best result while making progress.
+//!
+//! `Syzygy` probes the `.rtbw` and `.rtbz` files in a Syzygy path, which are loaded the first
+//! time they're needed. The tables leave out positions where a capture is best, so captures are
+//! searched before the tables are probed.
+
+mod table;
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::board::{
+    moves::{MoveList, PackedMove},
+    piece::{Color, Piece},
+    Board, BoardState,
+};
+
+use table::{Table, TableKind};
+
+/// First bytes of every Syzygy WDL file
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+/// First bytes of every Syzygy DTZ file
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+/// Result of a position for the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    /// A loss that turns into a draw thanks to the 50 move rule
+    BlessedLoss,
+    Draw,
+    /// A win that turns into a draw because of the 50 move rule
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    pub fn flipped(self) -> Self {
+        match self {
+            Self::Loss => Self::Win,
+            Self::BlessedLoss => Self::CursedWin,
+            Self::Draw => Self::Draw,
+            Self::CursedWin => Self::BlessedLoss,
+            Self::Win => Self::Loss,
+        }
+    }
+}
+
+/// A source of tablebase results.
+pub trait Probe: Debug + Send + Sync {
+    /// Positions with more pieces than this (kings included) are never found.
+    fn max_pieces(&self) -> u32;
+
+    /// The result of a position without castling rights, for the side to move.
+    fn probe_wdl(&self, state: &BoardState) -> Option<Wdl>;
+
+    /// Plies until the next capture or pawn move when playing the best moves, positive if the
+    /// side to move wins and negative if it loses, or 0 for draws. Cursed wins and blessed
+    /// losses are 100 plies further from zero.
+    fn probe_dtz(&self, state: &BoardState) -> Option<i32>;
+
+    /// Whether the position is small enough to be in the tablebases at all.
+    fn covers(&self, state: &BoardState) -> bool {
+        state.all_pieces_mask().count() <= self.max_pieces()
+            && state.castling_rights().iter().all(|allowed| !allowed)
+    }
+}
+
+/// The legal root moves that keep the best tablebase result, or `None` if the root position or
+/// one of its children can't be probed. Winning moves that reset the 50 move counter soonest
+/// are kept, and when losing, the ones that delay it the longest.
+pub fn root_moves(board: &mut Board, tablebases: &dyn Probe) -> Option<MoveList> {
+    if !tablebases.covers(board.current_position()) {
+        return None;
+    }
+
+    let mut ranked = Vec::new();
+    for mv in board.legal_moves() {
+        let zeroing = resets_counter(board.current_position(), mv);
+        board.make_packed_move(mv).unwrap();
+
+        let rank = if board.legal_moves().is_empty() {
+            // Checkmate wins straight away, while stalemate is a draw
+            match board.in_check() {
+                true => Some((Wdl::Win, 0)),
+                false => Some((Wdl::Draw, 0)),
+            }
+        } else {
+            let state = board.current_position();
+            tablebases.probe_wdl(state).and_then(|wdl| {
+                let wdl = wdl.flipped();
+
+                // Resetting the counter makes this move's DTZ the one before zeroing, as in
+                // `Syzygy::probe_dtz`
+                let dtz = match zeroing {
+                    true => dtz_before_zeroing(wdl),
+                    false => {
+                        let dtz = -tablebases.probe_dtz(state)?;
+                        dtz + dtz.signum()
+                    }
+                };
+
+                Some((wdl, dtz))
+            })
+        };
+
+        board.unmake_move().unwrap();
+        ranked.push((mv, rank?));
+    }
+
+    let best_wdl = ranked.iter().map(|(_, (wdl, _))| *wdl).max()?;
+    ranked.retain(|(_, (wdl, _))| *wdl == best_wdl);
+
+    // Flipped to our point of view, DTZs are positive when winning and negative when losing, so
+    // in both cases the smallest one is best
+    if best_wdl != Wdl::Draw {
+        let best_dtz = ranked.iter().map(|(_, (_, dtz))| *dtz).min()?;
+        ranked.retain(|(_, (_, dtz))| *dtz == best_dtz);
+    }
+
+    Some(ranked.into_iter().map(|(mv, _)| mv).collect())
+}
+
+/// The name of the table holding a position, such as `KRPvKR`, with `strong` first.
+fn table_name(state: &BoardState, strong: Color) -> String {
+    let side = |color| {
+        [
+            Piece::King(color),
+            Piece::Queen(color),
+            Piece::Rook(color),
+            Piece::Bishop(color),
+            Piece::Knight(color),
+            Piece::Pawn(color),
+        ]
+        .into_iter()
+        .flat_map(|piece| {
+            let count = state.mask(piece).count() as usize;
+            std::iter::repeat_n(piece.to_char().to_ascii_uppercase(), count)
+        })
+        .collect::<String>()
+    };
+
+    format!("{}v{}", side(strong), side(strong.swapped()))
+}
+
+/// The WDL and DTZ files found for one table.
+#[derive(Debug, Default)]
+struct TableFiles {
+    wdl: Option<TableFile>,
+    dtz: Option<TableFile>,
+}
+
+/// A table file, which is read the first time it's probed.
+#[derive(Debug)]
+struct TableFile {
+    path: PathBuf,
+    table: OnceLock<Option<Table>>,
+}
+
+impl TableFile {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            table: OnceLock::new(),
+        }
+    }
+
+    /// The decoded table, or `None` if the file can't be read or is malformed.
+    fn table(&self, kind: TableKind) -> Option<&Table> {
+        self.table
+            .get_or_init(|| {
+                let name = self.path.file_stem()?.to_str()?;
+                Table::new(fs::read(&self.path).ok()?, name, kind)
+            })
+            .as_ref()
+    }
+}
+
+/// Syzygy tablebase files found in a set of directories.
+#[derive(Debug, Default)]
+pub struct Syzygy {
+    tables: HashMap<String, TableFiles>,
+    max_pieces: u32,
+}
+
+impl Syzygy {
+    /// Looks for tables in a list of directories, separated by `;` on Windows and `:`
+    /// elsewhere as is usual for the `SyzygyPath` option. Files with the wrong header are
+    /// skipped.
+    pub fn open(path: &str) -> Self {
+        let separator = if cfg!(windows) { ';' } else { ':' };
+        let mut syzygy = Self::default();
+
+        for dir in path.split(separator).filter(|dir| !dir.is_empty()) {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+
+            for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+                let (Some(name), Some(extension)) = (path.file_stem(), path.extension()) else {
+                    continue;
+                };
+                let Some(name) = name.to_str().map(String::from) else {
+                    continue;
+                };
+
+                let magic = match extension.to_str() {
+                    Some("rtbw") => WDL_MAGIC,
+                    Some("rtbz") => DTZ_MAGIC,
+                    _ => continue,
+                };
+                if !has_magic(&path, magic) {
+                    continue;
+                }
+
+                let pieces = name.chars().filter(|ch| *ch != 'v').count() as u32;
+                syzygy.max_pieces = syzygy.max_pieces.max(pieces);
+
+                let files = syzygy.tables.entry(name).or_default();
+                match magic {
+                    WDL_MAGIC => files.wdl = Some(TableFile::new(path)),
+                    _ => files.dtz = Some(TableFile::new(path)),
+                }
+            }
+        }
+
+        syzygy
+    }
+
+    /// Number of tables with at least one file.
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// The files of the table holding a position, whichever side is stronger, and whether the
+    /// position has to be mirrored to be looked up because black is the stronger side.
+    fn files(&self, state: &BoardState) -> Option<(&TableFiles, bool)> {
+        [Color::White, Color::Black].into_iter().find_map(|strong| {
+            Some((
+                self.tables.get(&table_name(state, strong))?,
+                strong == Color::Black,
+            ))
+        })
+    }
+
+    /// The WDL result stored in the tables, which may be wrong when a capture is the best move.
+    fn probe_wdl_table(&self, state: &BoardState) -> Option<Wdl> {
+        // Bare kings aren't in any table
+        if state.all_pieces_mask().count() == 2 {
+            return Some(Wdl::Draw);
+        }
+
+        let (files, mirrored) = self.files(state)?;
+        files
+            .wdl
+            .as_ref()?
+            .table(TableKind::Wdl)?
+            .probe_wdl(state, mirrored)
+    }
+
+    /// The DTZ stored in the tables, or `Some(None)` if they only have the other side to move.
+    fn probe_dtz_table(&self, state: &BoardState, wdl: Wdl) -> Option<Option<i32>> {
+        let (files, mirrored) = self.files(state)?;
+        files
+            .dtz
+            .as_ref()?
+            .table(TableKind::Dtz)?
+            .probe_dtz(state, mirrored, wdl)
+    }
+
+    /// Searches captures (and pawn moves, if `pawn_moves` is set) before probing the tables,
+    /// since the tables don't store the right result when one of them is best. Also returns
+    /// whether the best move resets the 50 move counter, in which case the DTZ tables don't
+    /// store the right value either.
+    fn search(&self, state: &BoardState, pawn_moves: bool) -> Option<(Wdl, bool)> {
+        let moves = state.legal_moves();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+
+        for mv in moves.iter() {
+            let searched_move = match pawn_moves {
+                true => resets_counter(state, *mv),
+                false => mv.flag().is_capture(),
+            };
+            if !searched_move {
+                continue;
+            }
+            searched += 1;
+
+            let next = state.make_packed_move(*mv).ok()?;
+            let (wdl, _) = self.search(&next, false)?;
+            let wdl = wdl.flipped();
+
+            if wdl > best {
+                best = wdl;
+                if wdl == Wdl::Win {
+                    return Some((wdl, true));
+                }
+            }
+        }
+
+        // If every legal move was searched, the tables aren't needed
+        let all_searched = searched > 0 && searched == moves.len();
+        let stored = match all_searched {
+            true => best,
+            false => self.probe_wdl_table(state)?,
+        };
+
+        match best >= stored {
+            true => Some((best, best > Wdl::Draw || all_searched)),
+            false => Some((stored, false)),
+        }
+    }
+}
+
+/// Whether a move is a capture or a pawn move, which reset the 50 move counter.
+fn resets_counter(state: &BoardState, mv: PackedMove) -> bool {
+    mv.flag().is_capture()
+        || matches!(
+            state.piece_at_square(mv.from_square()),
+            Some(Piece::Pawn(_))
+        )
+}
+
+/// The DTZ of a position whose best move resets the 50 move counter.
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Loss => -1,
+        Wdl::BlessedLoss => -101,
+        Wdl::Draw => 0,
+        Wdl::CursedWin => 101,
+        Wdl::Win => 1,
+    }
+}
+
+fn wdl_sign(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Loss | Wdl::BlessedLoss => -1,
+        Wdl::Draw => 0,
+        Wdl::CursedWin | Wdl::Win => 1,
+    }
+}
+
+fn has_magic(path: &Path, magic: [u8; 4]) -> bool {
+    use std::io::Read;
+
+    let mut header = [0; 4];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| header == magic)
+}
+
+impl Probe for Syzygy {
+    fn max_pieces(&self) -> u32 {
+        self.max_pieces
+    }
+
+    fn probe_wdl(&self, state: &BoardState) -> Option<Wdl> {
+        if !self.covers(state) {
+            return None;
+        }
+
+        self.search(state, false).map(|(wdl, _)| wdl)
+    }
+
+    fn probe_dtz(&self, state: &BoardState) -> Option<i32> {
+        if !self.covers(state) {
+            return None;
+        }
+
+        let (wdl, zeroing) = self.search(state, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        if let Some(dtz) = self.probe_dtz_table(state, wdl)? {
+            let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+            return Some((dtz + 100 * cursed as i32) * wdl_sign(wdl));
+        }
+
+        // The table only has the other side to move, so look one move ahead for the best move
+        // that keeps the result
+        let mut best = None;
+        for mv in state.legal_moves() {
+            let zeroing = resets_counter(state, mv);
+            let next = state.make_packed_move(mv).ok()?;
+
+            let dtz = match zeroing {
+                // Resetting the counter makes this move's DTZ the one before zeroing
+                true => -dtz_before_zeroing(self.search(&next, false)?.0),
+                false => {
+                    let dtz = -self.probe_dtz(&next)?;
+                    dtz + dtz.signum()
+                }
+            };
+
+            // Checkmate is always the quickest win
+            let mates = next.in_check(next.active_color()) && next.legal_moves().is_empty();
+            let dtz = if mates { 1 } else { dtz };
+
+            if dtz.signum() == wdl_sign(wdl) && best.is_none_or(|best| dtz < best) {
+                best = Some(dtz);
+            }
+        }
+
+        // Without legal moves the position is checkmate
+        Some(best.unwrap_or(-1))
+    }
+}
+
+#[cfg(test)]
+mod tablebase_tests {
+    use super::*;
+
+    #[test]
+    fn names_tables() {
+        let state = BoardState::from_fen("8/8/4k3/3rp3/8/3RPP2/4K3/8 w - - 0 1").unwrap();
+
+        assert_eq!(table_name(&state, Color::White), "KRPPvKRP");
+        assert_eq!(table_name(&state, Color::Black), "KRPvKRPP");
+    }
+
+    #[test]
+    fn finds_table_files() {
+        let dir = std::env::temp_dir().join(format!("syzygy_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("KQvK.rtbw"), WDL_MAGIC).unwrap();
+        fs::write(dir.join("KQvK.rtbz"), DTZ_MAGIC).unwrap();
+        fs::write(dir.join("KRPvKR.rtbw"), [0; 4]).unwrap();
+
+        let syzygy = Syzygy::open(&format!("/nonexistent:{}", dir.display()));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(syzygy.len(), 1);
+        assert_eq!(syzygy.max_pieces(), 3);
+
+        let kqk = BoardState::from_fen("8/8/8/4k3/8/8/8/KQ6 b - - 0 1").unwrap();
+        let kqk_mirrored = BoardState::from_fen("8/8/8/4K3/8/8/8/kq6 w - - 0 1").unwrap();
+        assert!(syzygy.covers(&kqk));
+        assert!(syzygy.files(&kqk).is_some());
+        assert!(syzygy.files(&kqk_mirrored).is_some());
+
+        assert!(Syzygy::open("").is_empty());
+    }
+
+    #[test]
+    fn probes_real_tables() {
+        // KQvK, KRvK, KBvK, KNvK and KPvK from the Syzygy 3-4-5 piece set
+        let syzygy = Syzygy::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy"));
+        assert_eq!(syzygy.len(), 5);
+
+        for (fen, wdl, dtz) in [
+            ("8/8/8/4k3/8/8/8/KQ6 w - - 0 1", Wdl::Win, 17),
+            ("8/8/8/4k3/8/8/8/KQ6 b - - 0 1", Wdl::Loss, -18),
+            ("8/8/8/8/8/8/8/KQ5k b - - 0 1", Wdl::Loss, -16),
+            // Stalemate
+            ("k7/8/1Q6/8/8/8/8/6K1 b - - 0 1", Wdl::Draw, 0),
+            // Black is the stronger side
+            ("8/8/8/8/8/8/8/kq4K1 w - - 0 1", Wdl::Loss, -16),
+            ("8/8/8/4k3/8/8/8/R3K3 w - - 0 1", Wdl::Win, 27),
+            ("8/8/8/4k3/8/8/8/R3K3 b - - 0 1", Wdl::Loss, -28),
+            ("8/8/8/8/3k4/8/8/K6R w - - 0 1", Wdl::Win, 29),
+            ("7k/8/8/8/8/8/r7/6K1 b - - 0 1", Wdl::Win, 17),
+            // The undefended piece is captured
+            ("8/8/8/8/8/2k5/1Q6/7K b - - 0 1", Wdl::Draw, 0),
+            ("8/8/8/8/8/2k5/1R6/7K b - - 0 1", Wdl::Draw, 0),
+            ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", Wdl::Win, 3),
+            ("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1", Wdl::Draw, 0),
+            ("8/8/8/8/4K3/8/4P3/4k3 b - - 0 1", Wdl::Draw, 0),
+            ("4k3/8/8/8/8/8/P7/K7 w - - 0 1", Wdl::Draw, 0),
+            ("8/8/8/8/8/8/p7/k6K b - - 0 1", Wdl::Win, 3),
+            ("8/8/8/8/8/8/p5K1/k7 w - - 0 1", Wdl::Loss, -4),
+            ("8/8/8/4k3/8/8/8/KN6 w - - 0 1", Wdl::Draw, 0),
+            ("8/8/8/4k3/8/8/8/KB6 b - - 0 1", Wdl::Draw, 0),
+        ] {
+            let state = BoardState::from_fen(fen).unwrap();
+
+            assert_eq!(syzygy.probe_wdl(&state), Some(wdl), "{fen}");
+            assert_eq!(syzygy.probe_dtz(&state), Some(dtz), "{fen}");
+        }
+
+        // Missing tables
+        let krvkp = BoardState::from_fen("8/8/8/4k3/8/8/3p4/R3K3 w - - 0 1").unwrap();
+        assert_eq!(syzygy.probe_wdl(&krvkp), None);
+    }
+
+    #[test]
+    fn keeps_zeroing_root_moves() {
+        let syzygy = Syzygy::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy"));
+
+        // Promoting to a queen or a rook are the only moves with a DTZ of 1
+        let mut board = Board::new("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        assert_eq!(syzygy.probe_dtz(board.current_position()), Some(1));
+
+        let moves = root_moves(&mut board, &syzygy).unwrap();
+        let moves = moves.iter().map(|mv| mv.to_string()).collect::<Vec<_>>();
+        assert_eq!(moves, ["e7e8q", "e7e8r"]);
+    }
+}
@@ -0,0 +1,925 @@
+//! Decoding of Syzygy table files.
+//!
+//! A table stores one value per position index, compressed with recursive pairing (symbols that
+//! stand for pairs of other symbols) and canonical Huffman codes, in blocks that can be found
+//! quickly through a sparse index. Positions are turned into indices by mirroring them into a
+//! canonical form and then counting the placements of each group of identical pieces, with the
+//! order of the groups and of the pieces chosen by the generator for every table.
+
+use std::{fmt, sync::OnceLock};
+
+use crate::board::{
+    mask::Mask,
+    piece::{Color, Piece},
+    BoardState,
+};
+
+use super::Wdl;
+
+/// The values are from the point of view of the side to move stored in the flags, or of both
+/// sides in tables that store both
+const STM: u8 = 1;
+/// DTZ values are stored as indices into a map
+const MAPPED: u8 = 2;
+/// Winning DTZ values are in plies rather than moves
+const WIN_PLIES: u8 = 4;
+/// Losing DTZ values are in plies rather than moves
+const LOSS_PLIES: u8 = 8;
+/// The DTZ map has 16-bit values
+const WIDE: u8 = 16;
+/// Every position has the same value, and there's no compressed data
+const SINGLE_VALUE: u8 = 128;
+
+/// Header flag for tables that store both sides to move separately
+const SPLIT: u8 = 1;
+/// Header flag for tables with pawns
+const HAS_PAWNS: u8 = 2;
+
+/// Number of ways to place the two kings when neither is unique, with the first one in the
+/// a1-d1-d4 triangle
+const KINGS_SIZE: u64 = 462;
+/// Number of ways to place the first three pieces when one of them is unique
+const UNIQUE_SIZE: u64 = 31332;
+
+/// Lookup tables shared by every table's position encoding.
+struct Encoding {
+    /// Squares below the a1-h8 diagonal, numbered 0 to 27
+    map_b1h1h7: [u64; 64],
+    /// Squares in the a1-d1-d4 triangle, numbered 0 to 9 with the diagonal last
+    map_a1d1d4: [usize; 64],
+    /// Placements of two kings with the first in the a1-d1-d4 triangle, numbered 0 to 461
+    map_kk: [[u64; 64]; 10],
+    /// Binomial coefficients, `binomial[k][n]` being n choose k
+    binomial: [[u64; 64]; 7],
+    /// For pawns on a2 to h7, the number of squares left for the other pawns when this is the
+    /// leading pawn. The leading pawn is the one with the highest value
+    map_pawns: [usize; 64],
+    /// Index of the leading pawn's square, by number of leading pawns
+    lead_pawn_idx: [[u64; 64]; 7],
+    /// Number of indices for the leading pawns, by number of leading pawns and file
+    lead_pawns_size: [[u64; 4]; 7],
+}
+
+impl Encoding {
+    fn get() -> &'static Self {
+        static ENCODING: OnceLock<Encoding> = OnceLock::new();
+        ENCODING.get_or_init(Self::new)
+    }
+
+    fn new() -> Self {
+        let mut encoding = Self {
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; 7],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; 7],
+            lead_pawns_size: [[0; 4]; 7],
+        };
+
+        let below_diagonal = (0..64).filter(|square| off_diagonal(*square) < 0);
+        for (code, square) in below_diagonal.enumerate() {
+            encoding.map_b1h1h7[square] = code as u64;
+        }
+
+        let mut code = 0;
+        let mut diagonal = Vec::new();
+        for square in (0..28).filter(|square| square % 8 < 4) {
+            if off_diagonal(square) < 0 {
+                encoding.map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            encoding.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // Positions with both kings on the diagonal are numbered last
+        let mut code = 0;
+        let mut both_on_diagonal = Vec::new();
+        for i in 0..10 {
+            let first = (0..28)
+                .find(|square| {
+                    square % 8 < 4
+                        && off_diagonal(*square) <= 0
+                        && encoding.map_a1d1d4[*square] == i
+                })
+                .unwrap();
+
+            for second in 0..64 {
+                if adjacent_or_same(first, second)
+                    || (off_diagonal(first) == 0 && off_diagonal(second) > 0)
+                {
+                    continue;
+                }
+
+                if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                    both_on_diagonal.push((i, second));
+                } else {
+                    encoding.map_kk[i][second] = code;
+                    code += 1;
+                }
+            }
+        }
+        for (i, second) in both_on_diagonal {
+            encoding.map_kk[i][second] = code;
+            code += 1;
+        }
+
+        encoding.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..7.min(n + 1) {
+                let with = if k > 0 {
+                    encoding.binomial[k - 1][n - 1]
+                } else {
+                    0
+                };
+                let without = if k < n {
+                    encoding.binomial[k][n - 1]
+                } else {
+                    0
+                };
+                encoding.binomial[k][n] = with + without;
+            }
+        }
+
+        // Pawns closer to the edge and then on lower ranks get higher values, starting from the
+        // 47 squares left when the leading pawn is on a2
+        for file in 0..4 {
+            for rank in 1..7 {
+                let square = 8 * rank + file;
+                let available = 47 - 2 * (6 * file + rank - 1);
+
+                encoding.map_pawns[square] = available;
+                encoding.map_pawns[square ^ 7] = available - 1;
+            }
+        }
+
+        for lead_pawns in 1..7 {
+            for file in 0..4 {
+                let mut idx = 0;
+
+                for rank in 1..7 {
+                    let square = 8 * rank + file;
+                    encoding.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += encoding.binomial[lead_pawns - 1][encoding.map_pawns[square]];
+                }
+
+                encoding.lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+
+        encoding
+    }
+}
+
+/// How far a square is above the a1-h8 diagonal, negative below it.
+fn off_diagonal(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+fn adjacent_or_same(a: usize, b: usize) -> bool {
+    (a / 8).abs_diff(b / 8) <= 1 && (a % 8).abs_diff(b % 8) <= 1
+}
+
+/// Mirrors a square in the a1-h8 diagonal.
+fn flip_diagonal(square: usize) -> usize {
+    ((square >> 3) | (square << 3)) & 63
+}
+
+/// The piece codes used in table files, 1 to 6 for white pawn to king and 9 to 14 for black.
+fn piece_code(piece: Piece) -> u8 {
+    let color = match piece.color() {
+        Color::White => 0,
+        Color::Black => 8,
+    };
+
+    piece.kind_index() as u8 + 1 + color
+}
+
+/// The compressed values of one side to move (and file of the leading pawn, for tables with
+/// pawns). Positions into the data are byte offsets from the start of the file.
+#[derive(Debug, Default)]
+struct PairsData {
+    flags: u8,
+    /// Piece codes in the order they're encoded
+    pieces: Vec<u8>,
+    /// Sizes of the groups of pieces that are encoded together
+    group_len: Vec<usize>,
+    /// Multiplier for each group's index, followed by the number of indices in the table
+    group_idx: Vec<u64>,
+    single_value: u16,
+
+    block_size: usize,
+    /// Number of values between two sparse index entries
+    span: u64,
+    sparse_index: usize,
+    sparse_index_len: usize,
+    block_lengths: usize,
+    block_lengths_len: usize,
+    blocks: usize,
+    blocks_len: usize,
+
+    min_sym_len: u32,
+    lowest_sym: usize,
+    /// The lowest code of each length, left aligned, so that longer codes have lower values
+    base64: Vec<u64>,
+    /// Number of values each symbol stands for, minus one
+    symlen: Vec<u32>,
+    /// Pairs of symbols that each symbol stands for, 12 bits each
+    btree: usize,
+
+    /// Where the DTZ value maps for each result start, in map entries from the start of the map
+    map_idx: [usize; 4],
+}
+
+/// Whether a table holds WDL or DTZ values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+/// A loaded WDL or DTZ table.
+pub struct Table {
+    data: Vec<u8>,
+    kind: TableKind,
+    has_pawns: bool,
+    /// Whether one side has a single piece of some kind besides its king
+    has_unique_pieces: bool,
+    /// Whether both sides have the same pieces, in which case only white to move is stored
+    symmetric: bool,
+    piece_count: usize,
+    /// Pawns of the leading color, which has the fewest pawns, and then of the other color
+    pawn_count: [usize; 2],
+    /// Indexed by side to move and then by the file of the leading pawn
+    pairs: Vec<Vec<PairsData>>,
+    /// Start of the DTZ value maps
+    map: usize,
+}
+
+impl fmt::Debug for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Table")
+            .field("kind", &self.kind)
+            .field("size", &self.data.len())
+            .finish()
+    }
+}
+
+impl Table {
+    /// Parses the file of the table with the given name, such as `KRPvKR`, returning `None` if
+    /// it's malformed.
+    pub fn new(data: Vec<u8>, name: &str, kind: TableKind) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        let count = |side: &str, piece| side.chars().filter(|ch| *ch == piece).count();
+
+        let white_pawns = count(white, 'P');
+        let black_pawns = count(black, 'P');
+        // The leading color is the one with fewer pawns, but at least one
+        let pawn_count = match black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns) {
+            true => [white_pawns, black_pawns],
+            false => [black_pawns, white_pawns],
+        };
+
+        let mut table = Self {
+            data,
+            kind,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: ['P', 'N', 'B', 'R', 'Q']
+                .iter()
+                .any(|piece| count(white, *piece) == 1 || count(black, *piece) == 1),
+            symmetric: white == black,
+            piece_count: white.len() + black.len(),
+            pawn_count,
+            pairs: Vec::new(),
+            map: 0,
+        };
+
+        let flags = *table.data.get(4)?;
+        if (flags & HAS_PAWNS != 0) != table.has_pawns || (flags & SPLIT != 0) == table.symmetric {
+            return None;
+        }
+
+        table.parse()?;
+        Some(table)
+    }
+
+    fn sides(&self) -> usize {
+        match self.kind == TableKind::Wdl && !self.symmetric {
+            true => 2,
+            false => 1,
+        }
+    }
+
+    fn files(&self) -> usize {
+        match self.has_pawns {
+            true => 4,
+            false => 1,
+        }
+    }
+
+    fn byte(&self, pos: usize) -> Option<u8> {
+        self.data.get(pos).copied()
+    }
+
+    fn u16_le(&self, pos: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(
+            self.data.get(pos..pos + 2)?.try_into().ok()?,
+        ))
+    }
+
+    fn u32_le(&self, pos: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(
+            self.data.get(pos..pos + 4)?.try_into().ok()?,
+        ))
+    }
+
+    fn u32_be(&self, pos: usize) -> Option<u32> {
+        Some(u32::from_be_bytes(
+            self.data.get(pos..pos + 4)?.try_into().ok()?,
+        ))
+    }
+
+    fn u64_be(&self, pos: usize) -> Option<u64> {
+        Some(u64::from_be_bytes(
+            self.data.get(pos..pos + 8)?.try_into().ok()?,
+        ))
+    }
+
+    /// Reads the layout of the table: the piece order and sizes for every side and file, then
+    /// the DTZ maps, sparse indices, block lengths and blocks, which follow each other in that
+    /// order.
+    fn parse(&mut self) -> Option<()> {
+        let sides = self.sides();
+        let files = self.files();
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+
+        // Skip the magic and the header flags
+        let mut pos = 5;
+        let mut pairs = (0..sides).map(|_| Vec::new()).collect::<Vec<_>>();
+
+        for file in 0..files {
+            let order = self.byte(pos)?;
+            let pawn_order = match both_pawns {
+                true => self.byte(pos + 1)?,
+                false => 0xff,
+            };
+            pos += 1 + both_pawns as usize;
+
+            let codes = self.data.get(pos..pos + self.piece_count)?;
+            pos += self.piece_count;
+
+            for (side, side_pairs) in pairs.iter_mut().enumerate() {
+                let shift = 4 * side;
+                let mut data = PairsData {
+                    pieces: codes.iter().map(|code| code >> shift & 0xf).collect(),
+                    ..Default::default()
+                };
+
+                let order = [order >> shift & 0xf, pawn_order >> shift & 0xf];
+                self.set_groups(&mut data, order, file)?;
+                side_pairs.push(data);
+            }
+        }
+        pos += pos & 1;
+
+        for file in 0..files {
+            for side_pairs in &mut pairs {
+                pos = self.set_sizes(&mut side_pairs[file], pos)?;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            self.map = pos;
+
+            for data in &mut pairs[0] {
+                if data.flags & MAPPED == 0 {
+                    continue;
+                }
+
+                if data.flags & WIDE != 0 {
+                    pos += pos & 1;
+                    for i in 0..4 {
+                        data.map_idx[i] = (pos - self.map) / 2 + 1;
+                        pos += 2 * self.u16_le(pos)? as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        data.map_idx[i] = pos - self.map + 1;
+                        pos += self.byte(pos)? as usize + 1;
+                    }
+                }
+            }
+            pos += pos & 1;
+        }
+
+        for file in 0..files {
+            for side_pairs in &mut pairs {
+                side_pairs[file].sparse_index = pos;
+                pos += 6 * side_pairs[file].sparse_index_len;
+            }
+        }
+
+        for file in 0..files {
+            for side_pairs in &mut pairs {
+                side_pairs[file].block_lengths = pos;
+                pos += 2 * side_pairs[file].block_lengths_len;
+            }
+        }
+
+        for file in 0..files {
+            for side_pairs in &mut pairs {
+                pos = pos.next_multiple_of(64);
+                side_pairs[file].blocks = pos;
+                pos += side_pairs[file].blocks_len * side_pairs[file].block_size;
+            }
+        }
+
+        if pos > self.data.len() {
+            return None;
+        }
+
+        self.pairs = pairs;
+        Some(())
+    }
+
+    /// Splits the pieces into groups that are encoded together, and works out the multiplier of
+    /// each group. The first group is the leading pawns, or three pieces including the kings
+    /// when one of them is unique, or just the kings. Every other group is the pieces of one
+    /// kind and color. `order` gives the position of the leading group and of the other color's
+    /// pawns in the order the groups' indices are combined.
+    fn set_groups(&self, data: &mut PairsData, order: [u8; 2], file: usize) -> Option<()> {
+        let encoding = Encoding::get();
+
+        let mut first_len = match (self.has_pawns, self.has_unique_pieces) {
+            (true, _) => 0,
+            (false, true) => 3,
+            (false, false) => 2,
+        };
+        data.group_len = vec![1];
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || data.pieces[i] == data.pieces[i - 1] {
+                *data.group_len.last_mut().unwrap() += 1;
+            } else {
+                data.group_len.push(1);
+            }
+        }
+
+        let groups = data.group_len.len();
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        if both_pawns && groups < 2 {
+            return None;
+        }
+
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - data.group_len[0];
+        if both_pawns {
+            free_squares -= data.group_len[1];
+        }
+
+        data.group_idx = vec![0; groups + 1];
+        let mut idx: u64 = 1;
+        let mut k = 0;
+
+        while next < groups || k == order[0] || k == order[1] {
+            if k == order[0] {
+                data.group_idx[0] = idx;
+                idx *= match (self.has_pawns, self.has_unique_pieces) {
+                    (true, _) => *encoding.lead_pawns_size.get(data.group_len[0])?.get(file)?,
+                    (false, true) => UNIQUE_SIZE,
+                    (false, false) => KINGS_SIZE,
+                };
+            } else if k == order[1] {
+                data.group_idx[1] = idx;
+                idx *= encoding.binomial.get(data.group_len[1])?[48 - data.group_len[0]];
+            } else {
+                data.group_idx[next] = idx;
+                idx *= encoding.binomial.get(data.group_len[next])?[free_squares];
+                free_squares -= data.group_len[next];
+                next += 1;
+            }
+
+            k += 1;
+            if k > 15 {
+                return None;
+            }
+        }
+        data.group_idx[groups] = idx;
+
+        Some(())
+    }
+
+    /// Reads the sizes and Huffman code tables of one side and file, returning the position
+    /// after them.
+    fn set_sizes(&self, data: &mut PairsData, mut pos: usize) -> Option<usize> {
+        data.flags = self.byte(pos)?;
+        pos += 1;
+
+        if data.flags & SINGLE_VALUE != 0 {
+            data.single_value = self.byte(pos)? as u16;
+            return Some(pos + 1);
+        }
+
+        let table_size = *data.group_idx.last()?;
+        data.block_size = 1 << self.byte(pos)?;
+        data.span = 1 << self.byte(pos + 1)?;
+        data.sparse_index_len = table_size.div_ceil(data.span) as usize;
+        let padding = self.byte(pos + 2)? as usize;
+        data.blocks_len = self.u32_le(pos + 3)? as usize;
+        // Padded so that the sparse index never points past the end
+        data.block_lengths_len = data.blocks_len + padding;
+        pos += 7;
+
+        let max_sym_len = self.byte(pos)? as u32;
+        data.min_sym_len = self.byte(pos + 1)? as u32;
+        pos += 2;
+        if data.min_sym_len == 0 || max_sym_len < data.min_sym_len || max_sym_len > 32 {
+            return None;
+        }
+
+        // Canonical Huffman codes: all the codes of one length are consecutive, and longer codes
+        // have lower values
+        data.lowest_sym = pos;
+        let lengths = (max_sym_len - data.min_sym_len + 1) as usize;
+        data.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = self.u16_le(pos + 2 * i)? as u64;
+            let next_lowest = self.u16_le(pos + 2 * i + 2)? as u64;
+            data.base64[i] = data.base64[i + 1]
+                .wrapping_add(lowest)
+                .wrapping_sub(next_lowest)
+                / 2;
+        }
+        for (i, base) in data.base64.iter_mut().enumerate() {
+            *base <<= 64 - i as u32 - data.min_sym_len;
+        }
+        pos += 2 * lengths;
+
+        let symbols = self.u16_le(pos)? as usize;
+        pos += 2;
+        data.btree = pos;
+        if self.data.len() < pos + 3 * symbols {
+            return None;
+        }
+
+        data.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                self.set_symlen(data, symbol, &mut visited)?;
+            }
+        }
+
+        Some(pos + 3 * symbols + (symbols & 1))
+    }
+
+    /// Works out how many values a symbol stands for by expanding it into its pair.
+    fn set_symlen(&self, data: &mut PairsData, symbol: usize, visited: &mut [bool]) -> Option<()> {
+        visited[symbol] = true;
+
+        let (left, right) = self.pair(data, symbol)?;
+        if right == 0xfff {
+            return Some(());
+        }
+
+        for child in [left, right] {
+            if !*visited.get(child)? {
+                self.set_symlen(data, child, visited)?;
+            }
+        }
+
+        data.symlen[symbol] = data.symlen[left] + data.symlen[right] + 1;
+        Some(())
+    }
+
+    /// The two symbols a symbol stands for. Symbols that stand for a single value have the value
+    /// on the left and 0xfff on the right.
+    fn pair(&self, data: &PairsData, symbol: usize) -> Option<(usize, usize)> {
+        let pos = data.btree + 3 * symbol;
+        let bytes = self.data.get(pos..pos + 3)?;
+
+        let left = ((bytes[1] as usize & 0xf) << 8) | bytes[0] as usize;
+        let right = ((bytes[2] as usize) << 4) | (bytes[1] as usize >> 4);
+        Some((left, right))
+    }
+
+    /// Decompresses the value at an index.
+    fn decompress(&self, data: &PairsData, idx: u64) -> Option<u16> {
+        if data.flags & SINGLE_VALUE != 0 {
+            return Some(data.single_value);
+        }
+
+        // The sparse index points at the value in the middle of every span, so start from the
+        // nearest one and walk through the block lengths from there
+        let k = (idx / data.span) as usize;
+        let entry = data.sparse_index + 6 * k;
+        let mut block = self.u32_le(entry)? as usize;
+        let mut offset = self.u16_le(entry + 4)? as i64;
+        offset += (idx % data.span) as i64 - (data.span / 2) as i64;
+
+        let block_length = |block: usize| -> Option<i64> {
+            Some(self.u16_le(data.block_lengths + 2 * block)? as i64 + 1)
+        };
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)?;
+        }
+        while offset >= block_length(block)? {
+            offset -= block_length(block)?;
+            block += 1;
+        }
+
+        // Read codes until reaching the symbol that covers the offset
+        let mut pos = data.blocks + block * data.block_size;
+        let mut buf = self.u64_be(pos)?;
+        let mut buf_size = 64;
+        pos += 8;
+
+        let mut symbol = loop {
+            let mut len = 0;
+            while buf < *data.base64.get(len)? {
+                len += 1;
+            }
+
+            let code = (buf - data.base64[len]) >> (64 - len as u32 - data.min_sym_len);
+            let symbol = (code + self.u16_le(data.lowest_sym + 2 * len)? as u64) as usize;
+            let values = *data.symlen.get(symbol)? as i64 + 1;
+
+            if offset < values {
+                break symbol;
+            }
+
+            offset -= values;
+            let len = len as u32 + data.min_sym_len;
+            buf <<= len;
+            buf_size -= len;
+
+            if buf_size <= 32 {
+                buf_size += 32;
+                buf |= (self.u32_be(pos).unwrap_or(0) as u64) << (64 - buf_size);
+                pos += 4;
+            }
+        };
+
+        // Expand the symbol's pairs down to the single value at the offset
+        while data.symlen[symbol] > 0 {
+            let (left, right) = self.pair(data, symbol)?;
+            let left_values = *data.symlen.get(left)? as i64 + 1;
+
+            if offset < left_values {
+                symbol = left;
+            } else {
+                offset -= left_values;
+                symbol = right;
+            }
+        }
+
+        self.pair(data, symbol).map(|(value, _)| value as u16)
+    }
+
+    /// The index of a position in the table, along with the side to move and leading pawn file
+    /// it's stored under. Positions where the weaker side is white are looked up as their
+    /// `mirrored` version, with the colors swapped and the board flipped.
+    fn index(&self, state: &BoardState, mirrored: bool) -> Option<(usize, usize, u64)> {
+        let encoding = Encoding::get();
+
+        // Symmetric tables only store white to move
+        let flip = mirrored || (self.symmetric && state.active_color() == Color::Black);
+        let (flip_color, flip_squares) = if flip { (8, 56) } else { (0, 0) };
+        let stm = flip as usize ^ (state.active_color() == Color::Black) as usize;
+
+        let mut squares = [0; 7];
+        let mut pieces = [0; 7];
+        let mut size = 0;
+        let mut lead_pawns = Mask(0);
+        let mut file = 0;
+
+        if self.has_pawns {
+            let color = match self.pairs[0][0].pieces[0] ^ flip_color {
+                1 => Color::White,
+                9 => Color::Black,
+                _ => return None,
+            };
+
+            lead_pawns = state.mask(Piece::Pawn(color));
+            for square in lead_pawns.iter() {
+                *squares.get_mut(size)? = square as usize ^ flip_squares;
+                size += 1;
+            }
+
+            let leading = (0..size).max_by_key(|i| encoding.map_pawns[squares[*i]])?;
+            squares.swap(0, leading);
+            file = (squares[0] % 8).min(7 - squares[0] % 8);
+        }
+        let lead_count = size;
+
+        for square in (state.all_pieces_mask() & !lead_pawns).iter() {
+            let piece = state.piece_at_square(square)?;
+            *squares.get_mut(size)? = square as usize ^ flip_squares;
+            pieces[size] = piece_code(piece) ^ flip_color;
+            size += 1;
+        }
+        if size != self.piece_count {
+            return None;
+        }
+
+        let data = &self.pairs[stm % self.sides()][file];
+
+        // Put the pieces in the table's order
+        for i in lead_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|j| pieces[*j] == data.pieces[i]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // The leading piece or pawn always goes on the queenside
+        if squares[0] % 8 > 3 {
+            for square in &mut squares[..size] {
+                *square ^= 7;
+            }
+        }
+
+        let mut idx = if self.has_pawns {
+            let mut idx = encoding.lead_pawn_idx[lead_count][squares[0]];
+
+            squares[1..lead_count].sort_by_key(|square| encoding.map_pawns[*square]);
+            for (i, square) in squares[1..lead_count].iter().enumerate() {
+                idx += encoding.binomial[i + 1][encoding.map_pawns[*square]];
+            }
+
+            idx
+        } else {
+            // Without pawns, the leading piece also goes on the first four ranks, and the first
+            // piece of the leading group that's off the diagonal goes below it
+            if squares[0] / 8 > 3 {
+                for square in &mut squares[..size] {
+                    *square ^= 56;
+                }
+            }
+
+            for i in 0..data.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+
+                if off > 0 {
+                    for square in &mut squares[i..size] {
+                        *square = flip_diagonal(*square);
+                    }
+                }
+                break;
+            }
+
+            match self.has_unique_pieces {
+                true => unique_pieces_index(&squares),
+                false => encoding.map_kk[encoding.map_a1d1d4[squares[0]]][squares[1]],
+            }
+        };
+
+        idx *= data.group_idx[0];
+
+        // The remaining groups, with squares taken by earlier groups left out. The other side's
+        // pawns can't be on the first rank either
+        let mut start = data.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+
+        for (group, &len) in data.group_len.iter().enumerate().skip(1) {
+            squares[start..start + len].sort_unstable();
+
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let adjust = squares[..start].iter().filter(|s| square > **s).count();
+                let skipped = if remaining_pawns { 8 } else { 0 };
+                n += encoding.binomial[i + 1][square.checked_sub(adjust + skipped)?];
+            }
+
+            remaining_pawns = false;
+            idx += n * data.group_idx[group];
+            start += len;
+        }
+
+        Some((stm, file, idx))
+    }
+
+    /// The result of a position, with captures not taken into account. Only for WDL tables.
+    pub fn probe_wdl(&self, state: &BoardState, mirrored: bool) -> Option<Wdl> {
+        let (stm, file, idx) = self.index(state, mirrored)?;
+        let data = &self.pairs[stm % self.sides()][file];
+
+        match self.decompress(data, idx)? {
+            0 => Some(Wdl::Loss),
+            1 => Some(Wdl::BlessedLoss),
+            2 => Some(Wdl::Draw),
+            3 => Some(Wdl::CursedWin),
+            4 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+
+    /// The DTZ of a position in plies, given its result, or `Some(None)` if the table only has
+    /// the position with the other side to move. Only for DTZ tables.
+    pub fn probe_dtz(&self, state: &BoardState, mirrored: bool, wdl: Wdl) -> Option<Option<i32>> {
+        let (stm, file, idx) = self.index(state, mirrored)?;
+        let data = &self.pairs[0][file];
+
+        // Symmetric tables without pawns are the same for both sides
+        let both_sides = self.symmetric && !self.has_pawns;
+        if !both_sides && (data.flags & STM) as usize != stm {
+            return Some(None);
+        }
+
+        let mut value = self.decompress(data, idx)? as usize;
+
+        if data.flags & MAPPED != 0 {
+            let map = match wdl {
+                Wdl::Win | Wdl::Draw => 0,
+                Wdl::Loss => 1,
+                Wdl::CursedWin => 2,
+                Wdl::BlessedLoss => 3,
+            };
+            let entry = data.map_idx[map] + value;
+
+            value = match data.flags & WIDE != 0 {
+                true => self.u16_le(self.map + 2 * entry)? as usize,
+                false => self.byte(self.map + entry)? as usize,
+            };
+        }
+
+        // Values are stored in moves rather than plies where that doesn't lose any precision
+        let in_moves = match wdl {
+            Wdl::Win => data.flags & WIN_PLIES == 0,
+            Wdl::Loss => data.flags & LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        if in_moves {
+            value *= 2;
+        }
+
+        Some(Some(value as i32 + 1))
+    }
+}
+
+/// Index of the first three pieces of tables with a unique piece, which are already mirrored so
+/// that the first one is in the a1-d1-d4 triangle and the first one off the diagonal is below
+/// it. Positions are numbered by how many of the pieces are on the diagonal.
+fn unique_pieces_index(squares: &[usize; 7]) -> u64 {
+    let encoding = Encoding::get();
+    let [first, second, third] = [squares[0], squares[1], squares[2]];
+
+    let adjust1 = (second > first) as u64;
+    let adjust2 = (third > first) as u64 + (third > second) as u64;
+    let rank = |square: usize| (square / 8) as u64;
+
+    if off_diagonal(first) != 0 {
+        (encoding.map_a1d1d4[first] as u64 * 63 + second as u64 - adjust1) * 62 + third as u64
+            - adjust2
+    } else if off_diagonal(second) != 0 {
+        (6 * 63 + rank(first) * 28 + encoding.map_b1h1h7[second]) * 62 + third as u64 - adjust2
+    } else if off_diagonal(third) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + rank(first) * 7 * 28
+            + (rank(second) - adjust1) * 28
+            + encoding.map_b1h1h7[third]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + rank(first) * 7 * 6
+            + (rank(second) - adjust1) * 6
+            + (rank(third) - adjust2)
+    }
+}
+
+#[cfg(test)]
+mod table_tests {
+    use super::*;
+
+    #[test]
+    fn encoding_sizes() {
+        let encoding = Encoding::get();
+
+        assert_eq!(
+            encoding.map_kk.iter().flatten().max(),
+            Some(&(KINGS_SIZE - 1))
+        );
+        assert_eq!(encoding.map_a1d1d4[1], 0);
+        assert_eq!(encoding.map_a1d1d4[27], 9);
+        assert_eq!(encoding.map_b1h1h7.iter().max(), Some(&27));
+        assert_eq!(encoding.binomial[2][5], 10);
+
+        // The a2 pawn leads over everything else
+        assert_eq!(encoding.map_pawns[8], 47);
+        assert_eq!(encoding.map_pawns[15], 46);
+        assert_eq!(encoding.lead_pawns_size[1].iter().sum::<u64>(), 24);
+    }
+}
@@ -0,0 +1,316 @@
+//! Typed UCI options. Each option declares its type, default value and bounds, which are used
+//! both to describe it to the GUI on `uci` and to validate `setoption` commands.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    Check {
+        default: bool,
+    },
+    Combo {
+        default: &'static str,
+        choices: &'static [&'static str],
+    },
+    String {
+        default: &'static str,
+    },
+    Button,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UciOption {
+    pub name: &'static str,
+    pub kind: OptionKind,
+}
+
+/// A validated option value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue {
+    Spin(i64),
+    Check(bool),
+    Combo(String),
+    String(String),
+    Button,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionError {
+    MissingName,
+    UnknownOption(String),
+    MissingValue(&'static str),
+    NotANumber {
+        name: &'static str,
+        min: i64,
+        max: i64,
+    },
+    OutOfRange {
+        name: &'static str,
+        min: i64,
+        max: i64,
+    },
+    NotABool(&'static str),
+    InvalidChoice {
+        name: &'static str,
+        choices: &'static [&'static str],
+    },
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingName => write!(f, "setoption needs an option name"),
+            Self::UnknownOption(name) => write!(f, "unknown option: {name}"),
+            Self::MissingValue(name) => write!(f, "{name} needs a value"),
+            Self::NotANumber { name, min, max } | Self::OutOfRange { name, min, max } => {
+                write!(f, "{name} expects a number from {min} to {max}")
+            }
+            Self::NotABool(name) => write!(f, "{name} expects true or false"),
+            Self::InvalidChoice { name, choices } => {
+                write!(f, "{name} expects one of: {}", choices.join(", "))
+            }
+        }
+    }
+}
+
+impl UciOption {
+    pub const fn spin(name: &'static str, default: i64, min: i64, max: i64) -> Self {
+        Self {
+            name,
+            kind: OptionKind::Spin { default, min, max },
+        }
+    }
+
+    pub const fn check(name: &'static str, default: bool) -> Self {
+        Self {
+            name,
+            kind: OptionKind::Check { default },
+        }
+    }
+
+    pub const fn combo(
+        name: &'static str,
+        default: &'static str,
+        choices: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            kind: OptionKind::Combo { default, choices },
+        }
+    }
+
+    pub const fn string(name: &'static str, default: &'static str) -> Self {
+        Self {
+            name,
+            kind: OptionKind::String { default },
+        }
+    }
+
+    pub const fn button(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: OptionKind::Button,
+        }
+    }
+
+    pub fn default_value(&self) -> OptionValue {
+        match self.kind {
+            OptionKind::Spin { default, .. } => OptionValue::Spin(default),
+            OptionKind::Check { default } => OptionValue::Check(default),
+            OptionKind::Combo { default, .. } => OptionValue::Combo(default.to_string()),
+            OptionKind::String { default } => OptionValue::String(default.to_string()),
+            OptionKind::Button => OptionValue::Button,
+        }
+    }
+
+    /// Checks a value from `setoption` against the option's type and bounds.
+    pub fn parse(&self, value: Option<&str>) -> Result<OptionValue, OptionError> {
+        let name = self.name;
+
+        if let OptionKind::Button = self.kind {
+            return Ok(OptionValue::Button);
+        }
+        let value = value.ok_or(OptionError::MissingValue(name))?;
+
+        match self.kind {
+            OptionKind::Spin { min, max, .. } => {
+                let number =
+                    value
+                        .parse::<i64>()
+                        .map_err(|_| OptionError::NotANumber { name, min, max })?;
+
+                if !(min..=max).contains(&number) {
+                    return Err(OptionError::OutOfRange { name, min, max });
+                }
+                Ok(OptionValue::Spin(number))
+            }
+            OptionKind::Check { .. } => match value {
+                "true" => Ok(OptionValue::Check(true)),
+                "false" => Ok(OptionValue::Check(false)),
+                _ => Err(OptionError::NotABool(name)),
+            },
+            OptionKind::Combo { choices, .. } => choices
+                .iter()
+                .find(|choice| choice.eq_ignore_ascii_case(value))
+                .map(|choice| OptionValue::Combo(choice.to_string()))
+                .ok_or(OptionError::InvalidChoice { name, choices }),
+            OptionKind::String { .. } => Ok(OptionValue::String(value.to_string())),
+            OptionKind::Button => unreachable!(),
+        }
+    }
+}
+
+/// Formats the option the way it's declared to the GUI after `uci`.
+impl fmt::Display for UciOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+
+        match self.kind {
+            OptionKind::Spin { default, min, max } => {
+                write!(f, "spin default {default} min {min} max {max}")
+            }
+            OptionKind::Check { default } => write!(f, "check default {default}"),
+            OptionKind::Combo { default, choices } => {
+                write!(f, "combo default {default}")?;
+                for choice in choices {
+                    write!(f, " var {choice}")?;
+                }
+                Ok(())
+            }
+            OptionKind::String { default } => {
+                let default = if default.is_empty() {
+                    "<empty>"
+                } else {
+                    default
+                };
+                write!(f, "string default {default}")
+            }
+            OptionKind::Button => write!(f, "button"),
+        }
+    }
+}
+
+/// Looks up the option named by the arguments of a `setoption name <name> [value <value>]`
+/// command and validates its value. Names can contain spaces and are case insensitive.
+pub fn parse_setoption<'a>(
+    options: &'a [UciOption],
+    args: &[String],
+) -> Result<(&'a UciOption, OptionValue), OptionError> {
+    let value_index = args.iter().position(|arg| arg == "value");
+    let (name, value) = match value_index {
+        Some(i) => (&args[..i], Some(args[i + 1..].join(" "))),
+        None => (args, None),
+    };
+
+    let name = match name.split_first() {
+        Some((keyword, name)) if keyword == "name" && !name.is_empty() => name.join(" "),
+        _ => return Err(OptionError::MissingName),
+    };
+
+    let option = options
+        .iter()
+        .find(|option| option.name.eq_ignore_ascii_case(&name))
+        .ok_or(OptionError::UnknownOption(name))?;
+
+    Ok((option, option.parse(value.as_deref())?))
+}
+
+#[cfg(test)]
+mod uci_tests {
+    use super::*;
+
+    const OPTIONS: &[UciOption] = &[
+        UciOption::spin("Move Overhead", 50, 0, 5000),
+        UciOption::check("Ponder", false),
+        UciOption::combo("Style", "Normal", &["Solid", "Normal", "Risky"]),
+        UciOption::string("SyzygyPath", ""),
+        UciOption::button("Clear Hash"),
+    ];
+
+    fn args(command: &str) -> Vec<String> {
+        command.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn declares_options() {
+        let declarations = OPTIONS.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            declarations,
+            [
+                "option name Move Overhead type spin default 50 min 0 max 5000",
+                "option name Ponder type check default false",
+                "option name Style type combo default Normal var Solid var Normal var Risky",
+                "option name SyzygyPath type string default <empty>",
+                "option name Clear Hash type button",
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_values() {
+        let parse = |command| {
+            parse_setoption(OPTIONS, &args(command)).map(|(option, value)| (option.name, value))
+        };
+
+        assert_eq!(
+            parse("name move overhead value 100"),
+            Ok(("Move Overhead", OptionValue::Spin(100)))
+        );
+        assert_eq!(
+            parse("name Ponder value true"),
+            Ok(("Ponder", OptionValue::Check(true)))
+        );
+        assert_eq!(
+            parse("name Style value risky"),
+            Ok(("Style", OptionValue::Combo(String::from("Risky"))))
+        );
+        assert_eq!(
+            parse("name SyzygyPath value /tb/wdl /tb/dtz"),
+            Ok((
+                "SyzygyPath",
+                OptionValue::String(String::from("/tb/wdl /tb/dtz"))
+            ))
+        );
+        assert_eq!(
+            parse("name Clear Hash"),
+            Ok(("Clear Hash", OptionValue::Button))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let parse = |command| parse_setoption(OPTIONS, &args(command)).map(|(_, value)| value);
+
+        assert_eq!(
+            parse("name Hash value 1"),
+            Err(OptionError::UnknownOption(String::from("Hash")))
+        );
+        assert_eq!(parse("value 1"), Err(OptionError::MissingName));
+        assert!(matches!(
+            parse("name Move Overhead value 9000"),
+            Err(OptionError::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            parse("name Move Overhead value soon"),
+            Err(OptionError::NotANumber { .. })
+        ));
+        assert_eq!(
+            parse("name Ponder value yes"),
+            Err(OptionError::NotABool("Ponder"))
+        );
+        assert!(matches!(
+            parse("name Style value Wild"),
+            Err(OptionError::InvalidChoice { .. })
+        ));
+        assert_eq!(
+            parse("name Ponder"),
+            Err(OptionError::MissingValue("Ponder"))
+        );
+    }
+}
@@ -12,7 +12,7 @@ pub mod perft {
         let legal_moves = board.legal_moves();
 
         for legal_move in legal_moves {
-            board.make_move(legal_move).unwrap();
+            board.make_packed_move(legal_move).unwrap();
             moves += perft(board, depth - 1);
             board.unmake_move().unwrap();
         }
@@ -28,21 +28,46 @@ pub mod perft {
         let mut results = Vec::with_capacity(moves.len());
 
         for mv in moves {
-            board.make_move(mv).unwrap();
+            board.make_packed_move(mv).unwrap();
             let nodes = perft(&mut board, depth - 1);
             board.unmake_move().unwrap();
 
-            results.push(format!(
-                "{}{} {}",
-                mv.from.to_string(),
-                mv.to.to_string(),
-                nodes
-            ));
+            results.push(format!("{mv} {nodes}"));
         }
 
         results
     }
 
+    #[test]
+    fn reference_counts() {
+        // Counts from https://www.chessprogramming.org/Perft_Results
+        const POSITIONS: &[(&str, usize, u64)] = &[
+            (START_FEN, 3, 8902),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                2,
+                2039,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 3, 2812),
+            // Both sides can promote, with and without capturing
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                3,
+                9467,
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                2,
+                1486,
+            ),
+        ];
+
+        for (fen, depth, expected) in POSITIONS {
+            let mut board = Board::new(fen).unwrap();
+            assert_eq!(perft(&mut board, *depth), *expected, "{fen}");
+        }
+    }
+
     #[test]
     fn starting_position() {
         let roce = "a4a5 418\nb2b3 440\nb2b4 441\nc2c3 420\nc2c4 441\nd2d3 559\nd2d4 580\ne2e3 619\ne2e4 620\nf2f3 400\nf2f4 421\ng2g3 440\ng2g4 441\nh2h3 400\nh2h4 440\na1a2 420\na1a3 540\nb1c3 460\nb1a3 440\ng1h3 420\ng1f3 460";